@@ -0,0 +1,5 @@
+use common::app_daemon::DaemonApp;
+
+fn main() {
+    let _ = common::graceful_run(DaemonApp::run);
+}
@@ -0,0 +1,339 @@
+//! runs the input capture, the key handling, the statistics and the outputs without any window,
+//! warnings are logged to stderr or a file instead of message dialogs
+//!
+//! counts are restored from and saved to the statistics file, every [`Daemon::SAVE_INTERVAL`]
+//! and when stopped by Ctrl+C or by closing the console
+
+use std::{
+    io::Write,
+    path::PathBuf,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::Thread,
+    time::{Duration, Instant},
+};
+
+use sak_rs::{os::windows::input::GlobalListener, sync::mpmc};
+use windows::{Win32::System::Console::SetConsoleCtrlHandler, core::BOOL};
+
+use crate::{
+    event_server::Counts,
+    key_overlay_core::{KeyOverlayCore, key_message::InputMessage},
+    msg_hook,
+    net_input::NetInput,
+    report,
+    setting::Setting,
+};
+
+use sak_rs::sync::mpmc::queue::BoundedReceiver as MpscReceiver;
+
+const USAGE: &str =
+    "用法: app_daemon [--setting <配置文件>] [--log <日志文件>] [--statistics <统计文件>]";
+
+/// cleared by [`console_ctrl_handler`]
+static RUNNING: AtomicBool = AtomicBool::new(true);
+/// set once the daemon is dropped and its statistics saved
+static STOPPED: AtomicBool = AtomicBool::new(false);
+static DAEMON_THREAD: OnceLock<Thread> = OnceLock::new();
+
+/// the process ends when this returns for a closed console, a log off or a shut down,
+/// so it waits for the daemon to stop, windows allows a few seconds
+unsafe extern "system" fn console_ctrl_handler(_ctrl_type: u32) -> BOOL {
+    const STOP_TIMEOUT: Duration = Duration::from_secs(4);
+    RUNNING.store(false, Ordering::Relaxed);
+    DAEMON_THREAD.get().map(Thread::unpark);
+    let dead_line = Instant::now() + STOP_TIMEOUT;
+    while !STOPPED.load(Ordering::Acquire) && Instant::now() < dead_line {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    true.into()
+}
+
+pub struct DaemonApp;
+
+impl DaemonApp {
+    pub fn run() {
+        let args = match Args::parse(std::env::args().skip(1)) {
+            Ok(args) => args,
+            Err(err) => {
+                eprintln!("{err}\n{USAGE}");
+                return;
+            }
+        };
+        let log: Box<dyn Write + Send> = match &args.log_path {
+            Some(log_path) => std::fs::File::options()
+                .create(true)
+                .append(true)
+                .open(log_path)
+                .map_or_else(
+                    |err| {
+                        eprintln!("无法打开日志文件 {log_path:?}: {err}");
+                        Box::new(std::io::stderr()) as Box<dyn Write + Send>
+                    },
+                    |file| Box::new(file) as Box<dyn Write + Send>,
+                ),
+            None => Box::new(std::io::stderr()),
+        };
+        report::log_to(log);
+
+        let setting = Setting::load_or_create(&args.setting_path);
+        let (keys_sender, keys_receiver) = mpmc::queue::bounded(crate::CHANNEL_CAP);
+        let daemon_thread = std::thread::current();
+        let _ = DAEMON_THREAD.set(daemon_thread.clone());
+        // Safety: the handler only touches statics
+        unsafe { SetConsoleCtrlHandler(Some(console_ctrl_handler), true) }
+            .unwrap_or_else(|err| report::warning(format!("无法监听控制台关闭: {err}")));
+        let request_redraw = || {
+            let daemon_thread = daemon_thread.clone();
+            Box::new(move || daemon_thread.unpark())
+        };
+        let _net_input = NetInput::start_or_warn(
+            &setting.input_setting.network,
+            keys_sender.clone(),
+            request_redraw(),
+        );
+        let output_setting = setting.output_setting.clone();
+        let mut daemon = Daemon::new(setting, keys_receiver, args.statistics_path);
+        let hook_shared = msg_hook::HookShared {
+            request_redraw: request_redraw(),
+            send_motion: daemon.core.send_motion(),
+            key_forwarder: _net_input.as_ref().and_then(NetInput::forwarder),
+        };
+        let _global_listener = GlobalListener::new(
+            msg_hook::create_msg_hook(keys_sender, hook_shared),
+            msg_hook::create_register_raw_input_hook(false, true),
+        );
+        daemon
+            .core
+            .start_outputs(&output_setting, args.setting_path.clone(), request_redraw());
+        report::info(format!("已启动，配置文件: {:?}", args.setting_path));
+
+        daemon.run(&RUNNING);
+        STOPPED.store(true, Ordering::Release);
+        report::info("已退出");
+    }
+}
+
+struct Args {
+    setting_path: PathBuf,
+    log_path: Option<PathBuf>,
+    statistics_path: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self {
+            setting_path: crate::key_overlay_setting_path(),
+            log_path: None,
+            statistics_path: None,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| format!("{arg} 缺少参数"))
+            };
+            match arg.as_str() {
+                "--setting" => parsed.setting_path = value()?,
+                "--log" => parsed.log_path = Some(value()?),
+                "--statistics" => parsed.statistics_path = Some(value()?),
+                _ => return Err(format!("未知参数: {arg}")),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+struct Daemon {
+    core: KeyOverlayCore,
+    statistics_path: Option<PathBuf>,
+    saved_counts: Counts,
+    next_save: Instant,
+}
+
+impl Daemon {
+    const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+    /// keeps wheel releases and kps on time while keys are active
+    const ACTIVE_INTERVAL: Duration = Duration::from_millis(10);
+
+    fn new(
+        setting: Setting,
+        keys_receiver: MpscReceiver<InputMessage>,
+        statistics_path: Option<PathBuf>,
+    ) -> Self {
+        let mut core = KeyOverlayCore::new(setting, keys_receiver);
+        let saved_counts = statistics_path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                let counts = std::fs::read(path)
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice::<Counts>(&bytes).ok());
+                counts.is_none().then(|| {
+                    report::warning(format!("无法读取统计文件 {path:?}，计数从零开始"));
+                });
+                counts
+            })
+            .unwrap_or_default();
+        core.restore_counts(&saved_counts);
+        Self {
+            core,
+            statistics_path,
+            saved_counts,
+            next_save: Instant::now() + Self::SAVE_INTERVAL,
+        }
+    }
+
+    /// until `running` is cleared and the thread unparked, the statistics are saved on return
+    fn run(mut self, running: &AtomicBool) {
+        while running.load(Ordering::Relaxed) {
+            let instant_now = Instant::now();
+            self.update(instant_now);
+            std::thread::park_timeout(self.timeout(instant_now));
+        }
+        // what came in since the last update is counted too
+        self.update(Instant::now());
+    }
+
+    fn update(&mut self, instant_now: Instant) {
+        self.core.update(instant_now);
+        self.core.take_pending_setting().map(|setting| {
+            self.core.reload(&setting);
+            report::info("已重新加载配置");
+        });
+        if self.next_save <= instant_now {
+            self.save_statistics();
+            self.next_save = instant_now + Self::SAVE_INTERVAL;
+        }
+    }
+
+    fn timeout(&self, instant_now: Instant) -> Duration {
+        let timeout = self.next_save.saturating_duration_since(instant_now);
+        if self.core.need_repaint() {
+            timeout.min(Self::ACTIVE_INTERVAL)
+        } else {
+            timeout
+        }
+    }
+
+    /// written only if the counts changed
+    fn save_statistics(&mut self) {
+        let Some(statistics_path) = &self.statistics_path else {
+            return;
+        };
+        let counts = Counts::new(self.core.key_handler());
+        if counts == self.saved_counts {
+            return;
+        }
+        let json = serde_json::to_vec_pretty(&counts).expect("unreachable");
+        match std::fs::write(statistics_path, json) {
+            Ok(()) => self.saved_counts = counts,
+            Err(err) => report::warning(format!("无法写入统计文件 {statistics_path:?}: {err}")),
+        }
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        self.save_statistics();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{key::Key, key_overlay_core::key_message::KeyMessage};
+
+    use super::*;
+
+    #[test]
+    fn args() {
+        let args = ["--setting", "a.json", "--statistics", "b.json"].map(String::from);
+        let args = Args::parse(args.into_iter()).expect("unreachable");
+        assert_eq!(args.setting_path, PathBuf::from("a.json"));
+        assert_eq!(args.log_path, None);
+        assert_eq!(args.statistics_path, Some(PathBuf::from("b.json")));
+        assert!(Args::parse(["--log"].map(String::from).into_iter()).is_err());
+        assert!(Args::parse(["-x"].map(String::from).into_iter()).is_err());
+    }
+
+    #[test]
+    fn statistics_persistence() {
+        let statistics_path =
+            std::env::temp_dir().join(format!("daemon_statistics_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&statistics_path);
+        let count_of_z = |daemon: &Daemon| {
+            let key_handler = daemon.core.key_handler();
+            key_handler
+                .key_properties()
+                .iter()
+                .position(|key_property| key_property.key_bind == Key::KeyZ)
+                .map(|index| key_handler.key_draw_caches()[index].count)
+        };
+
+        let (keys_sender, keys_receiver) = mpmc::queue::bounded(16);
+        let mut daemon = Daemon::new(
+            Setting::default_zxc(),
+            keys_receiver,
+            Some(statistics_path.clone()),
+        );
+        let instant = Instant::now();
+        [true, false].into_iter().for_each(|is_pressed| {
+            keys_sender.force_send(InputMessage::Key(KeyMessage::new(
+                Key::KeyZ,
+                is_pressed,
+                instant,
+            )));
+        });
+        daemon.update(instant);
+        assert_eq!(count_of_z(&daemon), Some(1));
+        drop(daemon);
+
+        let (_keys_sender, keys_receiver) = mpmc::queue::bounded(16);
+        let daemon = Daemon::new(
+            Setting::default_zxc(),
+            keys_receiver,
+            Some(statistics_path.clone()),
+        );
+        assert_eq!(count_of_z(&daemon), Some(1));
+        drop(daemon);
+        let _ = std::fs::remove_file(&statistics_path);
+    }
+
+    #[test]
+    fn statistics_saved_on_shutdown() {
+        let statistics_path =
+            std::env::temp_dir().join(format!("daemon_shutdown_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&statistics_path);
+
+        let (keys_sender, keys_receiver) = mpmc::queue::bounded(16);
+        let daemon = Daemon::new(
+            Setting::default_zxc(),
+            keys_receiver,
+            Some(statistics_path.clone()),
+        );
+        let instant = Instant::now();
+        [true, false].into_iter().for_each(|is_pressed| {
+            keys_sender.force_send(InputMessage::Key(KeyMessage::new(
+                Key::KeyZ,
+                is_pressed,
+                instant,
+            )));
+        });
+        // stopped before the first save interval
+        daemon.run(&AtomicBool::new(false));
+
+        let counts: Counts = std::fs::read(&statistics_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .expect("unreachable");
+        let count_of_z = counts
+            .keys
+            .iter()
+            .find(|key_count| key_count.key == Key::KeyZ)
+            .map(|key_count| key_count.count);
+        assert_eq!(count_of_z, Some(1));
+        let _ = std::fs::remove_file(&statistics_path);
+    }
+}
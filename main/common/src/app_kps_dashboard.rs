@@ -1,5 +1,6 @@
 use std::{
     collections::VecDeque,
+    sync::{Arc, atomic::AtomicBool},
     time::{Duration, Instant},
 };
 
@@ -7,7 +8,11 @@ use egui::{Color32, FontData, FontDefinitions, FontFamily, TextureHandle, Viewpo
 use sak_rs::{font::SystemFontsLoader, os::windows::input::GlobalListener, sync::mpmc};
 use serde::{Deserialize, Serialize};
 
-use crate::{key::Key, key_overlay_core::key_message::KeyMessage, message_dialog, msg_hook};
+use crate::{
+    key::Key,
+    key_overlay_core::key_message::{InputMessage, KeyMessage},
+    message_dialog, msg_hook,
+};
 
 use sak_rs::sync::mpmc::queue::BoundedReceiver as MpscReceiver;
 
@@ -53,7 +58,7 @@ impl KPSApp {
 
 struct App {
     kps: Kps,
-    keys_receiver: MpscReceiver<KeyMessage>,
+    keys_receiver: MpscReceiver<InputMessage>,
     keys_message_buf: Vec<KeyMessage>,
     key_repeat_flags: [bool; Self::KEY_REPEAT_FLAGS_CAP],
     _global_listener: GlobalListener,
@@ -71,10 +76,12 @@ impl App {
             request_redraw: Box::new(move || {
                 (!egui_ctx.has_requested_repaint()).then(|| egui_ctx.request_repaint());
             }),
+            send_motion: Arc::new(AtomicBool::new(false)),
+            key_forwarder: None,
         };
         let global_listener = GlobalListener::new(
             msg_hook::create_msg_hook(keys_sender, hook_shared),
-            msg_hook::create_register_raw_input_hook(kps_setting.no_mouse),
+            msg_hook::create_register_raw_input_hook(kps_setting.no_mouse, true),
         );
         Self::init_fonts(&cc.egui_ctx);
         Self {
@@ -113,13 +120,19 @@ impl eframe::App for App {
 
     fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
         let instant_now = std::time::Instant::now();
-        self.keys_message_buf.extend(self.keys_receiver.try_iter());
+        self.keys_message_buf
+            .extend(self.keys_receiver.try_iter().filter_map(InputMessage::key));
         self.keys_message_buf
             .drain(..)
             .filter(|key_message| {
-                debug_assert!(key_message.key != Key::Unknown);
-                let index = key_message.key as usize;
-                let flag = unsafe { self.key_repeat_flags.get_unchecked_mut(index) };
+                // keys without a virtual key, e.g. unmapped ones, still have a physical key
+                let key = match key_message.key {
+                    Key::Unknown => key_message.physical_key,
+                    key => key,
+                };
+                let Some(flag) = self.key_repeat_flags.get_mut(key as usize) else {
+                    return false;
+                };
                 let old_flag = *flag;
                 let is_pressed = key_message.is_pressed;
                 *flag = is_pressed;
@@ -1,9 +1,15 @@
-use std::time::Instant;
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Instant,
+};
 
 use crate::{
     app_main::key_shader,
-    key_overlay_core::{KeyOverlayCore, key_handler::KeyHandler, key_message::KeyMessage},
-    setting::Setting,
+    key_overlay_core::{
+        KeyOverlayCore, key_handler::KeyHandler, key_message::InputMessage,
+        mouse_motion::MouseMotion,
+    },
+    setting::{OutputSetting, Setting},
 };
 use eframe::egui_wgpu;
 use egui::{Color32, CornerRadius, FontData, FontDefinitions, FontFamily, FontId};
@@ -18,6 +24,9 @@ pub struct KeyOverlay {
     background_color: Color32,
     key_shader: key_shader::CustomCallback,
     font_family: FontFamily,
+    /// as loaded, laid out again when the window is resized
+    setting: Setting,
+    window_size: egui::Vec2,
 }
 
 impl KeyOverlay {
@@ -27,13 +36,12 @@ impl KeyOverlay {
         cc: &eframe::CreationContext,
         egui_ctx: &egui::Context,
         setting: Setting,
-        keys_receiver: MpscReceiver<KeyMessage>,
+        keys_receiver: MpscReceiver<InputMessage>,
     ) -> Self {
         let Setting {
             window_setting,
             font_name,
             background_color,
-            key_properties,
             ..
         } = &setting;
 
@@ -42,10 +50,12 @@ impl KeyOverlay {
         Self::init_fonts(egui_ctx, [&**font_name]);
 
         let instant_now = Instant::now();
-        let window_size = [window_setting.width, window_setting.height];
-        let key_shader = key_shader::CustomCallback::new(cc, key_properties, window_size);
+        let window_size = window_setting.size();
+        let laid_out = setting.laid_out(window_size);
+        let key_shader =
+            key_shader::CustomCallback::new(cc, &laid_out.key_properties, window_size.into());
         let font_family = egui::FontFamily::Name(Self::FONT_FAMILY_NAME.into());
-        let core = KeyOverlayCore::new(setting, keys_receiver);
+        let core = KeyOverlayCore::new(laid_out, keys_receiver);
         Self {
             core,
             instant_now,
@@ -53,9 +63,27 @@ impl KeyOverlay {
             background_color,
             key_shader,
             font_family,
+            setting,
+            window_size,
         }
     }
 
+    #[inline]
+    pub fn start_outputs(
+        &mut self,
+        output_setting: &OutputSetting,
+        profile_path: std::path::PathBuf,
+        request_redraw: Box<dyn FnMut() + Send>,
+    ) {
+        self.core
+            .start_outputs(output_setting, profile_path, request_redraw);
+    }
+
+    #[inline]
+    pub fn take_pending_setting(&mut self) -> Option<Box<Setting>> {
+        self.core.take_pending_setting()
+    }
+
     pub fn reload(&mut self, setting: &Setting, reload_font: bool) {
         let Setting {
             window_setting,
@@ -69,10 +97,23 @@ impl KeyOverlay {
         reload_font.then(|| Self::init_fonts(&self.egui_ctx, [&**font_name]));
 
         self.background_color = new_background_color;
-        self.core.reload(setting);
-        let window_size = [window_setting.width, window_setting.height];
-        self.key_shader
-            .reload(self.core.key_handler().key_properties(), window_size);
+        self.window_size = window_setting.size();
+        self.setting = setting.clone();
+        self.core.reload(&setting.laid_out(self.window_size));
+        self.key_shader.reload(
+            self.core.key_handler().key_properties(),
+            self.window_size.into(),
+        );
+    }
+
+    /// re-flows the layout to a new window size, counts and bars are kept
+    fn relayout(&mut self, window_size: egui::Vec2) {
+        self.window_size = window_size;
+        self.core.relayout(&self.setting.laid_out(window_size));
+        self.key_shader.reload(
+            self.core.key_handler().key_properties(),
+            self.window_size.into(),
+        );
     }
 
     fn init_fonts<'a>(
@@ -126,11 +167,22 @@ impl KeyOverlay {
         self.core.update(instant_now);
     }
 
-    pub fn keys_receiver(&self) -> &MpscReceiver<KeyMessage> {
+    pub fn keys_receiver(&self) -> &MpscReceiver<InputMessage> {
         self.core.keys_receiver()
     }
 
-    pub fn show(&self, ui: &mut egui::Ui) {
+    #[inline]
+    pub fn send_motion(&self) -> Arc<AtomicBool> {
+        self.core.send_motion()
+    }
+
+    pub fn key_handler(&self) -> &KeyHandler {
+        self.core.key_handler()
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let window_size = ui.max_rect().size();
+        (window_size != self.window_size).then(|| self.relayout(window_size));
         let painter = ui.painter();
         painter.rect_filled(
             painter.clip_rect(),
@@ -140,6 +192,7 @@ impl KeyOverlay {
         KeyDrawingPipeline {
             key_shader: &self.key_shader,
             key_handler: self.core.key_handler(),
+            mouse_motion: self.core.mouse_motion(),
             font_family: &self.font_family,
             instant_now: self.instant_now,
             painter,
@@ -155,6 +208,7 @@ impl KeyOverlay {
 struct KeyDrawingPipeline<'a> {
     key_shader: &'a key_shader::CustomCallback,
     key_handler: &'a KeyHandler,
+    mouse_motion: &'a MouseMotion,
     font_family: &'a egui::FontFamily,
     instant_now: Instant,
     painter: &'a egui::Painter,
@@ -166,6 +220,8 @@ impl<'a> KeyDrawingPipeline<'a> {
         self.draw_frames();
         self.draw_key_texts();
         self.draw_counter_texts();
+        self.draw_group_counter_texts();
+        self.draw_mouse_motions();
     }
 
     fn draw_bars(&self) {
@@ -176,15 +232,17 @@ impl<'a> KeyDrawingPipeline<'a> {
         key_shader_inner.bar_rects.clear();
         let bar_rect_iter = self.key_handler.key_draw_caches_flat_map_iter(
             instant_now,
-            &|index, begin_duration_secs| key_shader::BarRect {
+            &|index, begin_duration_secs, width_scale| key_shader::BarRect {
                 property_index: index as u32,
                 begin_duration_secs,
                 end_duration_secs: 0.0,
+                width_scale,
             },
-            &|index, begin_duration_secs, end_duration_secs| key_shader::BarRect {
+            &|index, begin_duration_secs, end_duration_secs, width_scale| key_shader::BarRect {
                 property_index: index as u32,
                 begin_duration_secs,
                 end_duration_secs,
+                width_scale,
             },
         );
         key_shader_inner.bar_rects.extend(bar_rect_iter);
@@ -211,11 +269,9 @@ impl<'a> KeyDrawingPipeline<'a> {
                 self.painter.rect(
                     rect,
                     CornerRadius::ZERO,
-                    if key_draw_cache.begin_hold_instant.is_some() {
-                        key_draw_cache.pressed_color
-                    } else {
-                        Color32::TRANSPARENT
-                    },
+                    key_draw_cache
+                        .pressed_color
+                        .gamma_multiply(key_draw_cache.pressed_fill()),
                     egui::Stroke::new(key_property.thickness, key_draw_cache.frame_color),
                     egui::StrokeKind::Inside,
                 );
@@ -244,9 +300,9 @@ impl<'a> KeyDrawingPipeline<'a> {
             .key_properties()
             .iter()
             .zip(self.key_handler.key_draw_caches().iter())
-            .filter(|(key_property, _)| key_property.key_counter.0)
+            .filter(|(key_property, _)| key_property.key_counter.enabled)
             .for_each(|(key_property, key_draw_cache)| {
-                let counter = &key_property.key_counter.1;
+                let counter = &key_property.key_counter.value;
                 self.painter.text(
                     key_property.position
                         + egui::vec2(key_property.width / 2.0, key_property.height / 2.0)
@@ -258,4 +314,67 @@ impl<'a> KeyDrawingPipeline<'a> {
                 );
             });
     }
+
+    fn draw_group_counter_texts(&self) {
+        self.key_handler
+            .key_group_counts_iter()
+            .for_each(|(key_group_property, count)| {
+                self.painter.text(
+                    key_group_property.position,
+                    egui::Align2::CENTER_CENTER,
+                    count,
+                    FontId::new(key_group_property.font_size, self.font_family.clone()),
+                    key_group_property.text_color.into(),
+                );
+            });
+    }
+
+    fn draw_mouse_motions(&self) {
+        let mouse_motion = self.mouse_motion;
+        let speed = mouse_motion.speed(self.instant_now);
+        let total_distance = mouse_motion.total_distance();
+        mouse_motion
+            .mouse_motion_properties()
+            .iter()
+            .for_each(|mouse_motion_property| {
+                let trail_color = Color32::from(mouse_motion_property.trail_color);
+                let points_iter = mouse_motion
+                    .trail_iter(self.instant_now, mouse_motion_property.trail_duration())
+                    .map(|(offset, age)| {
+                        let point = mouse_motion_property.position
+                            + offset * mouse_motion_property.trail_scale;
+                        (point, age)
+                    });
+                // pairs up neighbouring points without collecting them, like `MouseTrailShader`
+                points_iter.clone().zip(points_iter.skip(1)).for_each(
+                    |((begin, _), (end, age))| {
+                        self.painter.line_segment(
+                            [begin, end],
+                            egui::Stroke::new(
+                                mouse_motion_property.trail_thickness,
+                                trail_color.gamma_multiply(1.0 - age),
+                            ),
+                        );
+                    },
+                );
+                [
+                    (&mouse_motion_property.speed_readout, speed as u64),
+                    (
+                        &mouse_motion_property.distance_readout,
+                        total_distance as u64,
+                    ),
+                ]
+                .into_iter()
+                .filter_map(|(readout, value)| readout.get().map(|readout| (readout, value)))
+                .for_each(|(readout, value)| {
+                    self.painter.text(
+                        mouse_motion_property.position + readout.position.to_vec2(),
+                        egui::Align2::CENTER_CENTER,
+                        value,
+                        FontId::new(readout.font_size, self.font_family.clone()),
+                        readout.text_color.into(),
+                    );
+                });
+            });
+    }
 }
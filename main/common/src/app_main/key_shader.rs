@@ -18,6 +18,8 @@ pub struct BarRect {
     pub property_index: u32,
     pub begin_duration_secs: f32,
     pub end_duration_secs: f32,
+    /// scales the bar across its direction, around the key's center line
+    pub width_scale: f32,
 }
 
 #[repr(C)]
@@ -31,16 +33,35 @@ struct ScreenSize {
 #[derive(Default, Clone, Copy, bytemuck::NoUninit)]
 struct Property {
     pressed_color: [f32; 4],
-    key_position: [f32; 2],
-    width: f32,
-    height: f32,
-    direction: u32,
+    bar_origin: [f32; 2],
+    bar_direction: [f32; 2],
+    bar_start: f32,
+    bar_half_width: f32,
+    bar_corner_radius: f32,
     bar_speed: f32,
     max_distance: f32,
     fade_length: f32,
-    has_max_distance: u32,
     has_fade: u32,
-    _padding: [u32; 2],
+    _padding: u32,
+}
+
+impl Property {
+    fn new(key_property: &KeyProperty, window_size: [f32; 2]) -> Self {
+        let bar_axes = key_property.bar_axes();
+        Self {
+            pressed_color: Color32::from(key_property.pressed_color).to_normalized_gamma_f32(),
+            bar_origin: bar_axes.origin.into(),
+            bar_direction: bar_axes.direction.into(),
+            bar_start: bar_axes.start,
+            bar_half_width: bar_axes.half_width,
+            bar_corner_radius: bar_axes.corner_radius,
+            bar_speed: key_property.bar_speed,
+            max_distance: key_property.bar_max_distance(window_size.into()),
+            fade_length: key_property.fade_length.value,
+            has_fade: key_property.fade_length.enabled as u32,
+            _padding: Default::default(),
+        }
+    }
 }
 
 #[repr(C)]
@@ -182,6 +203,11 @@ impl CustomCallback {
                             offset: 8,
                             shader_location: 2,
                         },
+                        VertexAttribute {
+                            format: wgpu::VertexFormat::Float32,
+                            offset: 12,
+                            shader_location: 3,
+                        },
                     ],
                 }],
             },
@@ -213,19 +239,7 @@ impl CustomCallback {
         });
         let properties_contents: Box<_> = key_properties
             .iter()
-            .map(|key_property| Property {
-                pressed_color: Color32::from(key_property.pressed_color).to_normalized_gamma_f32(),
-                key_position: key_property.position.into(),
-                width: key_property.width,
-                height: key_property.height,
-                direction: key_property.key_direction as u32,
-                bar_speed: key_property.bar_speed,
-                max_distance: key_property.max_distance.1,
-                fade_length: key_property.fade_length.1,
-                has_max_distance: key_property.max_distance.0 as u32,
-                has_fade: key_property.fade_length.0 as u32,
-                _padding: Default::default(),
-            })
+            .map(|key_property| Property::new(key_property, window_size))
             .chain([Default::default()])
             .collect();
         let properties_buffer = device.create_buffer_init(&BufferInitDescriptor {
@@ -272,20 +286,7 @@ impl CustomCallback {
         });
         let properties_contents: Box<_> = key_properties
             .iter()
-            .map(|key_property| Property {
-                pressed_color: Into::<Color32>::into(key_property.pressed_color)
-                    .to_normalized_gamma_f32(),
-                key_position: key_property.position.into(),
-                width: key_property.width,
-                height: key_property.height,
-                direction: key_property.key_direction as u32,
-                bar_speed: key_property.bar_speed,
-                max_distance: key_property.max_distance.1,
-                fade_length: key_property.fade_length.1,
-                has_max_distance: key_property.max_distance.0 as u32,
-                has_fade: key_property.fade_length.0 as u32,
-                _padding: Default::default(),
-            })
+            .map(|key_property| Property::new(key_property, window_size))
             .chain([Default::default()])
             .collect();
         let new_properties_buffer = inner.device.create_buffer_init(&BufferInitDescriptor {
@@ -11,6 +11,7 @@ use sak_rs::{os::windows::input::GlobalListener, sync::mpmc};
 use crate::{
     app_main::key_overlay::KeyOverlay,
     msg_hook,
+    net_input::NetInput,
     setting::{Setting, WindowSetting},
 };
 
@@ -20,9 +21,9 @@ impl MainApp {
     pub fn run() {
         let setting = Setting::load_from_local_setting();
         let WindowSetting {
-            width,
-            height,
             enable_vsync,
+            resizable,
+            ..
         } = setting.window_setting;
 
         let icon_data = {
@@ -39,8 +40,8 @@ impl MainApp {
         };
         let native_options = eframe::NativeOptions {
             viewport: ViewportBuilder::default()
-                .with_inner_size([width, height])
-                .with_resizable(false)
+                .with_inner_size(setting.window_setting.size())
+                .with_resizable(resizable)
                 .with_maximize_button(false)
                 .with_minimize_button(false)
                 .with_icon(icon_data)
@@ -59,6 +60,7 @@ impl MainApp {
 struct App {
     key_overlay: KeyOverlay,
     _global_listener: GlobalListener,
+    _net_input: Option<NetInput>,
 }
 
 impl App {
@@ -66,20 +68,37 @@ impl App {
         cc.egui_ctx.request_repaint();
         let cap = crate::CHANNEL_CAP;
         let (keys_sender, keys_receiver) = mpmc::queue::bounded(cap);
-        let egui_ctx = cc.egui_ctx.clone();
-        let hook_shared = msg_hook::HookShared {
-            request_redraw: Box::new(move || {
+        let request_redraw = || {
+            let egui_ctx = cc.egui_ctx.clone();
+            Box::new(move || {
                 (!egui_ctx.has_requested_repaint()).then(|| egui_ctx.request_repaint());
-            }),
+            })
+        };
+        let net_input = NetInput::start_or_warn(
+            &setting.input_setting.network,
+            keys_sender.clone(),
+            request_redraw(),
+        );
+        let output_setting = setting.output_setting.clone();
+        let mut key_overlay = KeyOverlay::new(cc, &cc.egui_ctx, setting, keys_receiver);
+        let hook_shared = msg_hook::HookShared {
+            request_redraw: request_redraw(),
+            send_motion: key_overlay.send_motion(),
+            key_forwarder: net_input.as_ref().and_then(NetInput::forwarder),
         };
         let global_listener = GlobalListener::new(
             msg_hook::create_msg_hook(keys_sender, hook_shared),
-            msg_hook::create_register_raw_input_hook(false),
+            msg_hook::create_register_raw_input_hook(false, true),
+        );
+        key_overlay.start_outputs(
+            &output_setting,
+            crate::key_overlay_setting_path(),
+            request_redraw(),
         );
-        let key_overlay = KeyOverlay::new(cc, &cc.egui_ctx, setting, keys_receiver);
         Self {
             key_overlay,
             _global_listener: global_listener,
+            _net_input: net_input,
         }
     }
 }
@@ -92,6 +111,14 @@ impl eframe::App for App {
     fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
         let instant_now = Instant::now();
         self.key_overlay.update(instant_now);
+        self.key_overlay.take_pending_setting().map(|setting| {
+            let window_setting = &setting.window_setting;
+            ui.ctx()
+                .send_viewport_cmd(egui::ViewportCommand::InnerSize(window_setting.size()));
+            ui.ctx()
+                .send_viewport_cmd(egui::ViewportCommand::Resizable(window_setting.resizable));
+            self.key_overlay.reload(&setting, true);
+        });
 
         egui::CentralPanel::default()
             .frame(egui::Frame::NONE)
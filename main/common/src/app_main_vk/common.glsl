@@ -6,10 +6,6 @@ struct ScreenSize {
     float height;
 };
 
-struct Direction {
-    uint v;
-};
-
 struct Property {
     vec4 pressed_color;
     vec4 frame_color;
@@ -21,19 +17,26 @@ struct Property {
 
     float thickness;
     float bar_speed;
-    uint has_max_distance;
+    /// from `bar_origin` to where the bars start along `bar_direction`
+    float bar_start;
+    /// `max_distance`, or to the window edge
     float max_distance;
 
     uint has_fade;
     float fade_length;
-    /// 0: up, 1: down, 2: left, 3: right
-    Direction direction;
+    float bar_half_width;
     float font_size;
 
     vec4 counter_text_color;
 
-    uvec3 _padding;
+    /// the unit vector the bars travel along
+    vec2 bar_direction;
+    float bar_corner_radius;
     float counter_font_size;
+
+    /// on the center line of the bars, level with the key center
+    vec2 bar_origin;
+    vec2 _padding;
 };
 
 layout(set = 0, binding = 0) uniform readonly Uniforms {
@@ -39,6 +39,8 @@ struct VertexInput {
     in_begin_duration_secs: f32,
     #[format(R32_SFLOAT)]
     in_end_duration_secs: f32,
+    #[format(R32_SFLOAT)]
+    in_width_scale: f32,
 }
 
 #[derive(Clone)]
@@ -94,15 +96,17 @@ impl KeyBarShader {
     ) -> Option<impl FnOnce(&mut CommandBuilder) + use<>> {
         let vertex_input_iter = key_handler.key_draw_caches_flat_map_iter(
             instant_now,
-            &|index, in_begin_duration_secs| VertexInput {
+            &|index, in_begin_duration_secs, in_width_scale| VertexInput {
                 in_property_index: index as u32,
                 in_begin_duration_secs,
                 in_end_duration_secs: 0.0,
+                in_width_scale,
             },
-            &|index, in_begin_duration_secs, in_end_duration_secs| VertexInput {
+            &|index, in_begin_duration_secs, in_end_duration_secs, in_width_scale| VertexInput {
                 in_property_index: index as u32,
                 in_begin_duration_secs,
                 in_end_duration_secs,
+                in_width_scale,
             },
         );
         self.vertex_input_buf.extend(vertex_input_iter);
@@ -1,14 +1,18 @@
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Instant,
+};
 
 use crate::{
     app_main_vk::{
-        key_bar::KeyBarShader, numbers::NumbersShader, press_rect::PressRectShader, shaders,
-        static_overlay::StaticOverlayShader,
+        key_bar::KeyBarShader, mouse_trail::MouseTrailShader, numbers::NumbersShader,
+        press_rect::PressRectShader, shaders, static_overlay::StaticOverlayShader,
     },
     key_overlay_core::{
-        KeyOverlayCore, key_handler::KeyHandler, key_message::KeyMessage, key_property::KeyProperty,
+        KeyOverlayCore, key_handler::KeyHandler, key_message::InputMessage,
+        key_property::KeyProperty, mouse_motion::MouseMotion,
     },
-    setting::Setting,
+    setting::{OutputSetting, Setting},
 };
 
 use egui::Color32;
@@ -32,22 +36,69 @@ pub struct KeyOverlay {
     core: KeyOverlayCore,
     instant_now: Instant,
     shaders: Shaders,
+    /// as loaded, laid out again when the window is resized
+    setting: Setting,
+    window_size: egui::Vec2,
 }
 
 impl KeyOverlay {
     pub fn new(
         renderer: &Renderer,
         setting: Setting,
-        keys_receiver: MpscReceiver<KeyMessage>,
+        keys_receiver: MpscReceiver<InputMessage>,
     ) -> Self {
-        let shaders = Shaders::new(renderer, &setting);
-        let core = KeyOverlayCore::new(setting, keys_receiver);
+        let window_size = setting.window_setting.size();
+        let laid_out = setting.laid_out(window_size);
+        let shaders = Shaders::new(renderer, &laid_out);
+        let core = KeyOverlayCore::new(laid_out, keys_receiver);
 
         Self {
             core,
             instant_now: Instant::now(),
             shaders,
+            setting,
+            window_size,
+        }
+    }
+
+    #[inline]
+    pub fn start_outputs(
+        &mut self,
+        output_setting: &OutputSetting,
+        profile_path: std::path::PathBuf,
+        request_redraw: Box<dyn FnMut() + Send>,
+    ) {
+        self.core
+            .start_outputs(output_setting, profile_path, request_redraw);
+    }
+
+    #[inline]
+    pub fn take_pending_setting(&mut self) -> Option<Box<Setting>> {
+        self.core.take_pending_setting()
+    }
+
+    #[inline]
+    pub fn send_motion(&self) -> Arc<AtomicBool> {
+        self.core.send_motion()
+    }
+
+    pub fn reload(&mut self, renderer: &Renderer, setting: &Setting) {
+        self.window_size = setting.window_setting.size();
+        self.setting = setting.clone();
+        let laid_out = setting.laid_out(self.window_size);
+        self.shaders = Shaders::new(renderer, &laid_out);
+        self.core.reload(&laid_out);
+    }
+
+    /// re-flows the layout to a new window size, counts and bars are kept
+    pub fn relayout(&mut self, renderer: &Renderer, window_size: egui::Vec2) {
+        if window_size == self.window_size {
+            return;
         }
+        self.window_size = window_size;
+        let laid_out = self.setting.laid_out(window_size);
+        self.shaders = Shaders::with_fonts(renderer, &laid_out, self.shaders.fonts.clone());
+        self.core.relayout(&laid_out);
     }
 
     pub fn update(&mut self, instant_now: Instant) {
@@ -60,8 +111,11 @@ impl KeyOverlay {
         &mut self,
         instant_now: Instant,
     ) -> impl FnOnce(&mut CommandBuilder) + use<> {
-        self.shaders
-            .add_commands(instant_now, self.core.key_handler())
+        self.shaders.add_commands(
+            instant_now,
+            self.core.key_handler(),
+            self.core.mouse_motion(),
+        )
     }
 
     pub fn need_redraw(&self) -> bool {
@@ -74,6 +128,8 @@ struct Shaders {
     press_rect: PressRectShader,
     static_overlay: StaticOverlayShader,
     numbers: NumbersShader,
+    mouse_trail: MouseTrailShader,
+    fonts: Arc<FontFallbackList>,
 }
 
 #[derive(Clone)]
@@ -90,15 +146,8 @@ pub(super) struct ShaderInitResources<'a> {
 
 impl Shaders {
     fn new(renderer: &Renderer, setting: &Setting) -> Self {
-        let Setting {
-            window_setting,
-            font_name,
-            key_properties,
-            ..
-        } = setting;
-
         let fonts_loader = SystemFontsLoader::new();
-        let font_data = [&**font_name]
+        let font_data = [&*setting.font_name]
             .into_iter()
             .chain(crate::DEFAULT_FONT_NAMES)
             .filter_map(|name| {
@@ -112,11 +161,20 @@ impl Shaders {
             })
             .collect();
         let fonts = Arc::new(FontFallbackList::new(font_data));
+        Self::with_fonts(renderer, setting, fonts)
+    }
+
+    fn with_fonts(renderer: &Renderer, setting: &Setting, fonts: Arc<FontFallbackList>) -> Self {
+        let Setting {
+            window_setting,
+            key_properties,
+            ..
+        } = setting;
         let screen_size = [window_setting.width, window_setting.height];
         let uniform_buffer = Self::create_uniform_buffer(renderer.allocators(), screen_size);
         let properties_buffer =
-            Self::create_properties_buffer(renderer.allocators(), key_properties);
-        let (key_bar, press_rect, static_overlay, numbers) = std::thread::scope(|s| {
+            Self::create_properties_buffer(renderer.allocators(), key_properties, screen_size);
+        let (key_bar, press_rect, static_overlay, numbers, mouse_trail) = std::thread::scope(|s| {
             let resources = ShaderInitResources {
                 queue: renderer.queue(),
                 render_pass: renderer.render_pass(),
@@ -134,8 +192,9 @@ impl Shaders {
             let key_bar = KeyBarShader::new(&resources);
             let press_rect = PressRectShader::new(&resources);
             let numbers = NumbersShader::new(&resources);
+            let mouse_trail = MouseTrailShader::new(&resources);
             let static_overlay = static_overlay_create_thread.join().expect("unreachable");
-            (key_bar, press_rect, static_overlay, numbers)
+            (key_bar, press_rect, static_overlay, numbers, mouse_trail)
         });
 
         Self {
@@ -143,6 +202,8 @@ impl Shaders {
             press_rect,
             static_overlay,
             numbers,
+            mouse_trail,
+            fonts,
         }
     }
 }
@@ -174,10 +235,12 @@ impl Shaders {
     fn create_properties_buffer(
         allocators: &Allocators,
         key_properties: &[KeyProperty],
+        screen_size: [f32; 2],
     ) -> Subbuffer<[shaders::Property]> {
         let properties: Vec<_> = key_properties
             .iter()
-            .map(|key_property| shaders::Property {
+            .map(|key_property| (key_property, key_property.bar_axes()))
+            .map(|(key_property, bar_axes)| shaders::Property {
                 pressed_color: Color32::from(key_property.pressed_color).to_normalized_gamma_f32(),
                 frame_color: Color32::from(key_property.frame_color).to_normalized_gamma_f32(),
                 text_color: Color32::from(key_property.text_color).to_normalized_gamma_f32(),
@@ -186,17 +249,18 @@ impl Shaders {
                 height: key_property.height,
                 thickness: key_property.thickness,
                 bar_speed: key_property.bar_speed,
-                has_max_distance: key_property.max_distance.0 as u32,
-                max_distance: key_property.max_distance.1,
-                has_fade: key_property.fade_length.0 as u32,
-                fade_length: key_property.fade_length.1,
-                direction: shaders::Direction {
-                    v: key_property.key_direction as u32,
-                },
+                bar_start: bar_axes.start,
+                max_distance: key_property.bar_max_distance(screen_size.into()),
+                has_fade: key_property.fade_length.enabled as u32,
+                fade_length: key_property.fade_length.value,
+                bar_half_width: bar_axes.half_width,
                 font_size: key_property.font_size,
-                counter_text_color: Color32::from(key_property.key_counter.1.text_color)
+                counter_text_color: Color32::from(key_property.key_counter.value.text_color)
                     .to_normalized_gamma_f32(),
-                counter_font_size: key_property.key_counter.1.font_size,
+                bar_direction: bar_axes.direction.into(),
+                bar_corner_radius: bar_axes.corner_radius,
+                counter_font_size: key_property.key_counter.value.font_size,
+                bar_origin: bar_axes.origin.into(),
                 _padding: Default::default(),
             })
             .chain([unsafe { core::mem::zeroed() }])
@@ -221,15 +285,20 @@ impl Shaders {
         &mut self,
         instant_now: Instant,
         key_handler: &KeyHandler,
+        mouse_motion: &MouseMotion,
     ) -> impl FnOnce(&mut CommandBuilder) + use<> {
         let key_bar = self.key_bar.add_commands(instant_now, key_handler);
         let press_rect = self.press_rect.add_commands(key_handler);
         let static_overlay = self.static_overlay.add_commands();
-        let numbers = self.numbers.add_commands(key_handler);
+        let mouse_trail = self.mouse_trail.add_commands(instant_now, mouse_motion);
+        let numbers = self
+            .numbers
+            .add_commands(instant_now, key_handler, mouse_motion);
         move |c| {
             key_bar.map(|f| f(c));
             press_rect.map(|f| f(c));
             static_overlay(c);
+            mouse_trail.map(|f| f(c));
             numbers.map(|f| f(c));
         }
     }
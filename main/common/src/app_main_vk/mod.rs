@@ -1,5 +1,6 @@
 mod key_bar;
 mod key_overlay;
+mod mouse_trail;
 mod numbers;
 mod press_rect;
 mod shaders;
@@ -39,6 +40,7 @@ use winit::{
 use crate::{
     app_main_vk::key_overlay::KeyOverlay,
     msg_hook,
+    net_input::NetInput,
     setting::{Setting, WindowSetting},
 };
 
@@ -48,10 +50,11 @@ impl MainAppVk {
     pub fn run() {
         let setting = Setting::load_from_local_setting();
         let WindowSetting {
-            width,
-            height,
             enable_vsync,
+            resizable,
+            ..
         } = setting.window_setting;
+        let size = setting.window_setting.size();
 
         let icon = {
             let img = image::load_from_memory(include_bytes!("../../../icons/main_icon.png"))
@@ -67,7 +70,8 @@ impl MainAppVk {
         event_loop.set_control_flow(ControlFlow::Wait);
 
         let window_attributes = WindowAttributes::default()
-            .with_inner_size(PhysicalSize::new(width, height))
+            .with_inner_size(PhysicalSize::new(size.x, size.y))
+            .with_resizable(resizable)
             .with_window_icon(Some(icon));
         let mut app = App::new(AppCreateInfo {
             setting,
@@ -96,6 +100,7 @@ struct Inner {
     renderer: Renderer,
     key_overlay: KeyOverlay,
     _global_listener: GlobalListener,
+    _net_input: Option<NetInput>,
 }
 
 impl App {
@@ -109,7 +114,6 @@ impl App {
     fn create_window(attributes: WindowAttributes, event_loop: &ActiveEventLoop) -> Arc<Window> {
         let attributes = attributes
             .with_title("HP KeyOverlay")
-            .with_resizable(false)
             .with_min_inner_size(PhysicalSize::new(1, 1))
             .with_enabled_buttons(WindowButtons::CLOSE)
             .with_transparent(true)
@@ -123,6 +127,16 @@ impl Inner {
     fn update(&mut self) {
         let instant_now = Instant::now();
         self.key_overlay.update(instant_now);
+        self.key_overlay.take_pending_setting().map(|setting| {
+            let size = setting.window_setting.size();
+            let _ = self
+                .window
+                .request_inner_size(PhysicalSize::new(size.x, size.y));
+            self.window.set_resizable(setting.window_setting.resizable);
+            self.renderer.clear_color =
+                Color32::from(setting.background_color).to_normalized_gamma_f32();
+            self.key_overlay.reload(&self.renderer, &setting);
+        });
         self.key_overlay
             .need_redraw()
             .then(|| self.request_redraw());
@@ -172,20 +186,36 @@ impl ApplicationHandler for App {
 
         let cap = crate::CHANNEL_CAP;
         let (keys_sender, keys_receiver) = mpmc::queue::bounded(cap);
-        let window_1 = window.clone();
         let redraw_requested = Arc::new(AtomicBool::new(false));
-        let redraw_requested_1 = redraw_requested.clone();
-        let hook_shared = msg_hook::HookShared {
-            request_redraw: Box::new(move || {
+        let request_redraw = || {
+            let window_1 = window.clone();
+            let redraw_requested_1 = redraw_requested.clone();
+            Box::new(move || {
                 let redraw_requested = redraw_requested_1.swap(true, atomic::Ordering::Relaxed);
                 (!redraw_requested).then(|| window_1.request_redraw());
-            }),
+            })
+        };
+        let _net_input = NetInput::start_or_warn(
+            &setting.input_setting.network,
+            keys_sender.clone(),
+            request_redraw(),
+        );
+        let output_setting = setting.output_setting.clone();
+        let mut key_overlay = KeyOverlay::new(&renderer, setting, keys_receiver);
+        let hook_shared = msg_hook::HookShared {
+            request_redraw: request_redraw(),
+            send_motion: key_overlay.send_motion(),
+            key_forwarder: _net_input.as_ref().and_then(NetInput::forwarder),
         };
         let _global_listener = GlobalListener::new(
             msg_hook::create_msg_hook(keys_sender, hook_shared),
-            msg_hook::create_register_raw_input_hook(false),
+            msg_hook::create_register_raw_input_hook(false, true),
+        );
+        key_overlay.start_outputs(
+            &output_setting,
+            crate::key_overlay_setting_path(),
+            request_redraw(),
         );
-        let key_overlay = KeyOverlay::new(&renderer, setting, keys_receiver);
         window.set_visible(true);
         let inner = Inner {
             window,
@@ -193,6 +223,7 @@ impl ApplicationHandler for App {
             renderer,
             key_overlay,
             _global_listener,
+            _net_input,
         };
         self.inner = Some(inner);
     }
@@ -208,6 +239,11 @@ impl ApplicationHandler for App {
             WindowEvent::CloseRequested => {
                 event_loop.exit();
             }
+            WindowEvent::Resized(size) => {
+                let size = egui::vec2(size.width as f32, size.height as f32);
+                inner.key_overlay.relayout(&inner.renderer, size);
+                inner.request_redraw();
+            }
             WindowEvent::RedrawRequested => {
                 inner
                     .redraw_requested
@@ -0,0 +1,29 @@
+#version 460
+
+#include "../common.glsl"
+
+layout(location = 0) in vec4 in_color;
+layout(location = 1) in vec2 in_begin;
+layout(location = 2) in vec2 in_end;
+layout(location = 3) in float in_thickness;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    const vec2 segment = in_end - in_begin;
+    const float segment_length = length(segment);
+    const vec2 direction = segment_length > 0.0 ? segment / segment_length : vec2(1.0, 0.0);
+    const vec2 normal = vec2(-direction.y, direction.x) * (in_thickness * 0.5);
+
+    const vec2 vertices[4] = {
+        in_begin + normal,
+        in_begin - normal,
+        in_end + normal,
+        in_end - normal
+    };
+
+    const vec2 position = remap(vertices[gl_VertexIndex]);
+
+    gl_Position = vec4(position, 0.0, 1.0);
+    out_color = in_color;
+}
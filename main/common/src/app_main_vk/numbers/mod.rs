@@ -1,5 +1,6 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
+use egui::Color32;
 use sak_rs::{
     font::{
         FontFallbackList, LineLayout, SdfGenerator,
@@ -42,7 +43,8 @@ use vulkano::{
 };
 
 use crate::{
-    app_main_vk::key_overlay::ShaderInitResources, key_overlay_core::key_handler::KeyHandler,
+    app_main_vk::key_overlay::ShaderInitResources,
+    key_overlay_core::{key_handler::KeyHandler, mouse_motion::MouseMotion},
 };
 
 use super::shaders;
@@ -86,8 +88,8 @@ impl CachedLineLayoutLibrary for NumbersLayoutLibrary {
 #[repr(C)]
 #[derive(Debug, Clone, BufferContents, Vertex)]
 struct VertexInput {
-    #[format(R32_UINT)]
-    in_property_index: u32,
+    #[format(R32G32B32A32_SFLOAT)]
+    in_color: [f32; 4],
     #[format(R32_UINT)]
     in_char_index: u32,
     #[format(R32G32_SFLOAT)]
@@ -125,17 +127,12 @@ impl NumbersShader {
             screen_size,
             fonts,
             uniform_buffer,
-            properties_buffer,
             ..
         } = *r;
         let device = queue.device();
         let pipeline = Self::create_pipeline(device.clone(), render_pass.clone(), screen_size);
-        let descriptor_set = Self::create_descriptor_set(
-            allocators,
-            uniform_buffer.clone(),
-            properties_buffer.clone(),
-            pipeline.layout(),
-        );
+        let descriptor_set =
+            Self::create_descriptor_set(allocators, uniform_buffer.clone(), pipeline.layout());
         let sampler = Sampler::new(
             device.clone(),
             SamplerCreateInfo {
@@ -175,27 +172,74 @@ impl NumbersShader {
 
     pub fn add_commands(
         &mut self,
+        instant_now: Instant,
         key_handler: &KeyHandler,
+        mouse_motion: &MouseMotion,
     ) -> Option<impl FnOnce(&mut CommandBuilder) + use<>> {
         let screen_size = self.screen_size;
 
-        for (index, (property, cache)) in key_handler
+        let key_counters_iter = key_handler
             .key_properties()
             .iter()
             .zip(key_handler.key_draw_caches())
-            .enumerate()
-            .filter(|(_, (property, _))| property.key_counter.0)
-        {
-            let counter = &property.key_counter.1;
-            let font_size = counter.font_size;
+            .filter(|(property, _)| property.key_counter.enabled)
+            .map(|(property, cache)| {
+                let counter = &property.key_counter.value;
+                let center = property.position
+                    + egui::vec2(property.width / 2.0, property.height / 2.0)
+                    + counter.position.to_vec2();
+                let color = Color32::from(counter.text_color).to_normalized_gamma_f32();
+                (center, counter.font_size, color, cache.count)
+            });
+        let key_group_counters_iter =
+            key_handler
+                .key_group_counts_iter()
+                .map(|(key_group_property, count)| {
+                    let color =
+                        Color32::from(key_group_property.text_color).to_normalized_gamma_f32();
+                    (
+                        key_group_property.position,
+                        key_group_property.font_size,
+                        color,
+                        count,
+                    )
+                });
+
+        let speed = mouse_motion.speed(instant_now) as u64;
+        let total_distance = mouse_motion.total_distance() as u64;
+        let mouse_motion_readouts_iter =
+            mouse_motion
+                .mouse_motion_properties()
+                .iter()
+                .flat_map(|mouse_motion_property| {
+                    [
+                        (&mouse_motion_property.speed_readout, speed),
+                        (&mouse_motion_property.distance_readout, total_distance),
+                    ]
+                    .into_iter()
+                    .filter_map(|(readout, value)| readout.get().map(|readout| (readout, value)))
+                    .map(move |(readout, value)| {
+                        let color = Color32::from(readout.text_color).to_normalized_gamma_f32();
+                        (
+                            mouse_motion_property.position + readout.position.to_vec2(),
+                            readout.font_size,
+                            color,
+                            value,
+                        )
+                    })
+                });
+
+        let numbers_iter = key_counters_iter
+            .chain(key_group_counters_iter)
+            .map(|(center, font_size, color, count)| (center, font_size, color, count as u64))
+            .chain(mouse_motion_readouts_iter);
+        for (center, font_size, color, number) in numbers_iter {
             let mut layout = LineLayout::new(font_size);
-            layout.append(&*self.numbers_layout_library, cache.count.to_string());
+            layout.append(&*self.numbers_layout_library, number.to_string());
 
-            let frame_x_center = property.position.x + property.width / 2.0;
-            let frame_y_center = property.position.y + property.height / 2.0;
             let [x_center, y_center] = layout.center();
-            let dx = frame_x_center + counter.position.x - x_center;
-            let dy = frame_y_center + counter.position.y - y_center;
+            let dx = center.x - x_center;
+            let dy = center.y - y_center;
 
             for char_layout in layout.into_layout().into_iter() {
                 let Some(glyph_metrics) = self
@@ -206,7 +250,7 @@ impl NumbersShader {
                 };
                 let edge_padding = crate::sdf_edge_padding(font_size);
                 let vertex = VertexInput {
-                    in_property_index: index as u32,
+                    in_color: color,
                     in_char_index: char_layout.ch as u32 - b'0' as u32,
                     in_position: [
                         char_layout.x as f32 + dx - edge_padding,
@@ -313,20 +357,17 @@ impl NumbersShader {
         .expect("unreachable")
     }
 
+    /// colors come from the vertex input, so only the uniform buffer is bound
     fn create_descriptor_set(
         allocators: &Allocators,
         uniform_buffer: Subbuffer<shaders::ScreenSize>,
-        properties_buffer: Subbuffer<[shaders::Property]>,
         pipeline_layout: &PipelineLayout,
     ) -> Arc<DescriptorSet> {
         let descriptor_set_layout = pipeline_layout.set_layouts().get(0).expect("unreachable");
         DescriptorSet::new(
             allocators.descriptor_set().clone(),
             descriptor_set_layout.clone(),
-            [
-                WriteDescriptorSet::buffer(0, uniform_buffer),
-                WriteDescriptorSet::buffer(1, properties_buffer),
-            ],
+            [WriteDescriptorSet::buffer(0, uniform_buffer)],
             [],
         )
         .expect("unreachable")
@@ -2,7 +2,7 @@
 
 #include "../common.glsl"
 
-layout(location = 0) in uint in_property_index;
+layout(location = 0) in vec4 in_color;
 layout(location = 1) in uint in_char_index;
 layout(location = 2) in vec2 in_position;
 layout(location = 3) in vec2 in_size;
@@ -12,8 +12,6 @@ layout(location = 1) out vec2 out_size;
 layout(location = 2) out vec3 out_uvz;
 
 void main() {
-    const Property property = properties[in_property_index];
-
     const vec2 pos[4] = {
         vec2(0.0),
         vec2(0.0, in_size.y),
@@ -30,7 +28,7 @@ void main() {
     const int vertex_index = gl_VertexIndex;
 
     gl_Position = vec4(remap(in_position + pos[vertex_index]), 0.0, 1.0);
-    out_color = property.counter_text_color;
+    out_color = in_color;
     out_size = in_size;
     out_uvz = vec3(uv[vertex_index], z_index);
 }
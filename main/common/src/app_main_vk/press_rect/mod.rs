@@ -35,6 +35,8 @@ use super::shaders;
 struct VertexInput {
     #[format(R32_UINT)]
     in_property_index: u32,
+    #[format(R32_SFLOAT)]
+    in_fill: f32,
 }
 
 #[derive(Clone)]
@@ -93,8 +95,10 @@ impl PressRectShader {
                 .iter()
                 .enumerate()
                 .filter_map(|(index, cache)| {
-                    cache.begin_hold_instant.map(|_| VertexInput {
+                    let in_fill = cache.pressed_fill();
+                    (in_fill > 0.0).then(|| VertexInput {
                         in_property_index: index as u32,
+                        in_fill,
                     })
                 });
         self.vertex_input_buf.extend(vertex_input_iter);
@@ -3,6 +3,7 @@
 #include "../common.glsl"
 
 layout(location = 0) in uint in_property_index;
+layout(location = 1) in float in_fill;
 
 layout(location = 0) out vec4 out_color;
 
@@ -23,6 +24,6 @@ void main() {
     const vec2 position = remap(vertices[gl_VertexIndex]);
 
     gl_Position = vec4(position, 0.0, 1.0);
-    out_color = property.pressed_color;
+    out_color = property.pressed_color * in_fill;
 }
 
@@ -1,4 +1,4 @@
-pub use key_bar::vs::{Direction, Property, ScreenSize};
+pub use key_bar::vs::{Property, ScreenSize};
 
 pub mod key_bar {
     pub mod vs {
@@ -95,3 +95,19 @@ pub mod numbers {
         }
     }
 }
+
+pub mod mouse_trail {
+    pub mod vs {
+        vulkano_shaders::shader! {
+            ty: "vertex",
+            path: "./src/app_main_vk/mouse_trail/mouse_trail.vs"
+        }
+    }
+
+    pub mod fs {
+        vulkano_shaders::shader! {
+            ty: "fragment",
+            path: "./src/app_main_vk/mouse_trail/mouse_trail.fs"
+        }
+    }
+}
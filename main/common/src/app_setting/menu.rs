@@ -1,6 +1,12 @@
 use egui::Widget;
 
-use crate::{message_dialog, setting::Setting};
+use crate::{
+    html_export, importer,
+    key_overlay_core::key_property::KeyDirection,
+    layout_generator::{self, LayoutKind, LayoutOptions},
+    message_dialog, schema,
+    setting::{Setting, SettingFormat},
+};
 
 use super::AppSharedData;
 
@@ -23,6 +29,7 @@ impl Menu {
         egui::MenuBar::new().ui(ui, |ui| {
             ui.horizontal(|ui| {
                 ui.menu_button("文件", |ui| self.file.show(ui));
+                ui.menu_button("统计", |ui| Self::show_statistics(ui, app_shared_data));
                 let button = egui::Button::new("放弃所有修改");
                 ui.add_enabled(self.modified, button).clicked().then(|| {
                     let r = message_dialog::confirm("是否放弃所有修改？")
@@ -38,6 +45,30 @@ impl Menu {
         });
     }
 
+    /// press and chatter counts since the preview started, for spotting failing switches
+    fn show_statistics(ui: &mut egui::Ui, app_shared_data: &AppSharedData) {
+        let key_handler = app_shared_data.key_overlay.key_handler();
+        egui::Grid::new("statistics").striped(true).show(ui, |ui| {
+            egui::Label::new("按键").selectable(false).ui(ui);
+            egui::Label::new("按下次数").selectable(false).ui(ui);
+            egui::Label::new("抖动次数")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("被防抖过滤掉的按下次数，数值偏高说明按键轴体可能老化");
+            ui.end_row();
+            key_handler
+                .key_properties()
+                .iter()
+                .zip(key_handler.key_draw_caches())
+                .for_each(|(key_property, key_draw_cache)| {
+                    ui.label(key_property.key_bind.long_name());
+                    ui.label(key_draw_cache.count.to_string());
+                    ui.label(key_draw_cache.chatter_count.to_string());
+                    ui.end_row();
+                });
+        });
+    }
+
     pub fn update(&mut self, egui_ctx: &egui::Context, app_shared_data: &mut AppSharedData) {
         self.modified = app_shared_data.modified;
         self.handle_discard(app_shared_data);
@@ -100,16 +131,26 @@ enum FileResponse {
     SaveFileToSetting,
     SaveFile,
     SaveFileAs,
+    ExportHtml,
+    ExportSchema,
+    Import,
     LoadDefaultSetting(fn() -> Setting),
+    GenerateLayout(LayoutKind),
 }
 
 struct File {
     response: Option<FileResponse>,
+    layout_options: LayoutOptions,
+    mania_key_count: usize,
 }
 
 impl File {
     fn new() -> Self {
-        Self { response: None }
+        Self {
+            response: None,
+            layout_options: Default::default(),
+            mania_key_count: 4,
+        }
     }
 
     fn update(&mut self, app_shared_data: &mut AppSharedData) {
@@ -118,16 +159,25 @@ impl File {
             FileResponse::SaveFileToSetting | FileResponse::SaveFile | FileResponse::SaveFileAs => {
                 self.save_file(r, app_shared_data);
             }
+            FileResponse::ExportHtml => Self::export_html(app_shared_data),
+            FileResponse::ExportSchema => Self::export_schema(),
+            FileResponse::Import => Self::import(app_shared_data),
             FileResponse::LoadDefaultSetting(setting) => {
                 app_shared_data.pending_setting = Some(setting());
             }
+            FileResponse::GenerateLayout(kind) => {
+                app_shared_data.pending_setting = Some(layout_generator::generate_setting(
+                    kind,
+                    &self.layout_options,
+                ));
+            }
         });
     }
 
     fn file_dialog() -> rfd::FileDialog {
         rfd::FileDialog::new()
             .set_directory(crate::get_current_dir())
-            .add_filter("", &["json"])
+            .add_filter("", &SettingFormat::EXTENSIONS)
     }
 
     fn load_file(&mut self, app_shared_data: &mut AppSharedData) {
@@ -153,6 +203,36 @@ impl File {
         });
     }
 
+    fn import(app_shared_data: &mut AppSharedData) {
+        if app_shared_data.modified {
+            let r = message_dialog::confirm("当前配置未保存，是否继续导入？")
+                .set_level(rfd::MessageLevel::Warning)
+                .show();
+            if r == rfd::MessageDialogResult::Cancel {
+                return;
+            }
+        }
+        let Some(path) = rfd::FileDialog::new()
+            .set_directory(crate::get_current_dir())
+            .add_filter("", &["txt", "ini", "json"])
+            .pick_file()
+        else {
+            return;
+        };
+        let _ = importer::import_file(path)
+            .map(|import| {
+                app_shared_data.pending_setting = Some(import.setting);
+                if import.warnings.is_empty() {
+                    message_dialog::info("导入成功！").show();
+                } else {
+                    let warnings = import.warnings.join("\n");
+                    message_dialog::warning(format!("导入完成，以下内容无法导入：\n{warnings}"))
+                        .show();
+                }
+            })
+            .map_err(|err| message_dialog::warning(err).show());
+    }
+
     fn save_file(&mut self, r: FileResponse, app_shared_data: &mut AppSharedData) {
         let path = match r {
             FileResponse::SaveFileToSetting => Some(crate::get_current_dir().join("setting.json")),
@@ -165,7 +245,9 @@ impl File {
                         || "json".to_string(),
                         |extention| {
                             let extention = extention.to_str().unwrap_or_default().to_string();
-                            if extention == "json" {
+                            if SettingFormat::EXTENSIONS
+                                .contains(&extention.to_lowercase().as_str())
+                            {
                                 extention
                             } else {
                                 extention + ".json"
@@ -196,6 +278,51 @@ impl File {
             .map_err(|err| message_dialog::warning(err).show());
     }
 
+    fn export_html(app_shared_data: &AppSharedData) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_directory(crate::get_current_dir())
+            .add_filter("", &["html"])
+            .set_file_name("按键显示.html")
+            .save_file()
+        else {
+            return;
+        };
+        let _ = html_export::export_html_to_file(&app_shared_data.current_setting, path)
+            .map(|_| {
+                let hint = if app_shared_data
+                    .current_setting
+                    .output_setting
+                    .event_server
+                    .enabled
+                {
+                    "导出成功！在 OBS 中添加浏览器源并选择该文件即可"
+                } else {
+                    "导出成功！网页需要连接事件服务器，请在配置中启用事件服务器"
+                };
+                message_dialog::info(hint).show();
+            })
+            .map_err(|err| message_dialog::warning(err).show());
+    }
+
+    fn export_schema() {
+        let Some(path) = rfd::FileDialog::new()
+            .set_directory(crate::get_current_dir())
+            .add_filter("", &["json"])
+            .set_file_name("setting.schema.json")
+            .save_file()
+        else {
+            return;
+        };
+        let _ = schema::export_schema_to_file(path)
+            .map(|_| {
+                message_dialog::info(
+                    "导出成功！在编辑器中为配置文件关联该 Schema 即可获得补全和检查",
+                )
+                .show();
+            })
+            .map_err(|err| message_dialog::warning(err).show());
+    }
+
     fn show(&mut self, ui: &mut egui::Ui) {
         ui.button("打开...")
             .on_hover_text("打开一个配置文件")
@@ -225,6 +352,30 @@ impl File {
                 self.response = Some(FileResponse::SaveFileAs);
             });
 
+        ui.button("导出网页...")
+            .on_hover_text("将当前配置导出为网页，可用作 OBS 浏览器源")
+            .clicked()
+            .then(|| {
+                self.response = Some(FileResponse::ExportHtml);
+            });
+
+        ui.button("导出JSON Schema...")
+            .on_hover_text("导出配置文件的 JSON Schema，编辑器可据此补全和检查手写的配置")
+            .clicked()
+            .then(|| {
+                self.response = Some(FileResponse::ExportSchema);
+            });
+
+        ui.button("导入...")
+            .on_hover_text(concat!(
+                "导入其他按键显示软件的配置，\n",
+                "支持 osu! KeyOverlay 的 config.txt 和 NohBoard 的键盘定义（.json）"
+            ))
+            .clicked()
+            .then(|| {
+                self.response = Some(FileResponse::Import);
+            });
+
         ui.menu_button("加载默认配置", |ui| {
             ui.button("ZXC").clicked().then(|| {
                 self.response = Some(FileResponse::LoadDefaultSetting(Setting::default_zxc));
@@ -257,5 +408,85 @@ impl File {
                 ));
             });
         });
+
+        ui.menu_button("生成键盘布局", |ui| self.show_layout_generator(ui));
+    }
+
+    fn show_layout_generator(&mut self, ui: &mut egui::Ui) {
+        let LayoutOptions {
+            unit,
+            gap,
+            origin,
+            key_direction,
+        } = &mut self.layout_options;
+        egui::Grid::new(ui.next_auto_id()).show(ui, |ui| {
+            egui::Label::new("单位大小:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("一个字母键的宽度");
+            egui::Slider::new(unit, 10.0..=200.0).integer().ui(ui);
+            ui.end_row();
+
+            egui::Label::new("间距:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("相邻按键之间的距离");
+            egui::Slider::new(gap, 0.0..=50.0).integer().ui(ui);
+            ui.end_row();
+
+            egui::Label::new("起点:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("布局左上角的位置，也是窗口的边距");
+            ui.horizontal(|ui| {
+                egui::DragValue::new(&mut origin.x).prefix("x: ").ui(ui);
+                egui::DragValue::new(&mut origin.y).prefix("y: ").ui(ui);
+            });
+            ui.end_row();
+
+            egui::Label::new("按键条方向:").selectable(false).ui(ui);
+            ui.horizontal(|ui| {
+                [
+                    (KeyDirection::Up, "上"),
+                    (KeyDirection::Down, "下"),
+                    (KeyDirection::Left, "左"),
+                    (KeyDirection::Right, "右"),
+                ]
+                .into_iter()
+                .for_each(|(direction, text)| {
+                    ui.selectable_value(key_direction, direction, text);
+                });
+            });
+            ui.end_row();
+        });
+        ui.separator();
+
+        [
+            LayoutKind::Ansi,
+            LayoutKind::Iso,
+            LayoutKind::Tkl,
+            LayoutKind::Sixty,
+            LayoutKind::Numpad,
+            LayoutKind::Wasd,
+        ]
+        .into_iter()
+        .for_each(|kind| {
+            ui.button(kind.name()).clicked().then(|| {
+                self.response = Some(FileResponse::GenerateLayout(kind));
+            });
+        });
+        ui.horizontal(|ui| {
+            egui::DragValue::new(&mut self.mania_key_count)
+                .range(1..=LayoutKind::MAX_MANIA_KEYS)
+                .suffix("K")
+                .ui(ui);
+            ui.button("Mania")
+                .on_hover_text("按 osu!mania 的常用键位生成一排按键")
+                .clicked()
+                .then(|| {
+                    let kind = LayoutKind::Mania(self.mania_key_count);
+                    self.response = Some(FileResponse::GenerateLayout(kind));
+                });
+        });
     }
 }
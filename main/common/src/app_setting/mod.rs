@@ -1,4 +1,7 @@
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Instant,
+};
 
 use egui::ViewportBuilder;
 use sak_rs::{os::windows::input::GlobalListener, sync::mpmc};
@@ -83,20 +86,12 @@ impl App {
             request_redraw: Box::new(move || {
                 (!egui_ctx.has_requested_repaint()).then(|| egui_ctx.request_repaint());
             }),
+            send_motion: Arc::new(AtomicBool::new(true)),
+            key_forwarder: None,
         };
         let global_listener = GlobalListener::new(
             msg_hook::create_msg_hook(keys_sender, hook_shared),
-            |&hwnd| {
-                use sak_rs::os::windows::input::raw_input::device;
-                device::register(
-                    device::DeviceType::Keyboard,
-                    device::OptionType::inputsink(hwnd),
-                );
-                device::register(
-                    device::DeviceType::Mouse,
-                    device::OptionType::inputsink(hwnd),
-                );
-            },
+            msg_hook::create_register_raw_input_hook(false, false),
         );
         let key_overlay = KeyOverlay::new(cc, &cc.egui_ctx, setting.clone(), keys_receiver);
         let menu = menu::Menu::new();
@@ -180,9 +175,9 @@ impl App {
             .with_close_button(false)
             .with_icon(self.icon_data.clone())
             .with_title("预览")
-            .with_resizable(false)
+            .with_resizable(window_setting.resizable)
             .with_transparent(true)
-            .with_inner_size(egui::vec2(window_setting.width, window_setting.height));
+            .with_inner_size(window_setting.size());
         ui.ctx()
             .show_viewport_immediate(new_viewport_id, viewport_builder, |ctx, _vc| {
                 egui::CentralPanel::default()
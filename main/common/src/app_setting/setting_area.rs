@@ -5,13 +5,17 @@ use sak_rs::font::SystemFontsLoader;
 
 use crate::{
     app_main::key_overlay::KeyOverlay,
+    device::{self, DeviceInfo, DeviceKind},
     key::Key,
     key_overlay_core::{
-        key_message::KeyMessage,
-        key_property::{KeyDirection, KeyProperty},
+        key_message::{InputMessage, KeyMessage},
+        key_property::{
+            Anchor, BarAlign, BarShape, BarWidth, BindMode, DepthEffect, KeyCounterProperty,
+            KeyDirection, KeyGroupProperty, KeyProperty, KeyStyle, MouseMotionProperty, Toggle,
+        },
     },
     message_dialog,
-    setting::{Setting, WindowSetting},
+    setting::{InputSetting, NetMode, NetProtocol, OutputSetting, Palette, Setting, WindowSetting},
     ucolor32::UColor32,
 };
 
@@ -30,6 +34,8 @@ pub struct SettingArea {
     request_reload_setting: bool,
     window_setting_row: WindowSettingRow,
     key_property_setting_row: KeyPropertySettingRow,
+    key_group_setting_row: KeyGroupSettingRow,
+    mouse_motion_setting_row: MouseMotionSettingRow,
 }
 
 impl SettingArea {
@@ -38,12 +44,16 @@ impl SettingArea {
             request_reload_setting: false,
             window_setting_row: WindowSettingRow::new(setting),
             key_property_setting_row: KeyPropertySettingRow::new(setting),
+            key_group_setting_row: KeyGroupSettingRow::new(setting),
+            mouse_motion_setting_row: MouseMotionSettingRow::new(setting),
         }
     }
 
     pub fn reload(&mut self, setting: &Setting) {
         self.window_setting_row.reload(setting);
         self.key_property_setting_row.reload(setting);
+        self.key_group_setting_row.reload(setting);
+        self.mouse_motion_setting_row.reload(setting);
     }
 
     pub fn update(&mut self, app_shared_data: &mut AppSharedData) {
@@ -53,19 +63,38 @@ impl SettingArea {
             &mut self.request_reload_setting,
             app_shared_data.key_overlay.keys_receiver(),
         );
+        self.key_group_setting_row
+            .update(&mut self.request_reload_setting);
+        self.mouse_motion_setting_row
+            .update(&mut self.request_reload_setting);
         std::mem::take(&mut self.request_reload_setting).then(|| {
             let WindowSettingRow {
                 window_setting,
+                input_setting,
+                output_setting,
                 background_color,
                 current_font_family: current_font_name,
                 ..
             } = &self.window_setting_row;
-            let KeyPropertySettingRow { key_properties, .. } = &self.key_property_setting_row;
+            let KeyPropertySettingRow {
+                palette,
+                styles,
+                key_properties,
+                ..
+            } = &self.key_property_setting_row;
+            let KeyGroupSettingRow { key_groups, .. } = &self.key_group_setting_row;
+            let MouseMotionSettingRow { mouse_motions, .. } = &self.mouse_motion_setting_row;
             let setting = Setting {
                 window_setting: window_setting.clone(),
                 font_name: current_font_name.clone(),
                 background_color: *background_color,
+                palette: palette.clone(),
+                styles: styles.clone(),
                 key_properties: key_properties.clone(),
+                key_groups: key_groups.clone(),
+                mouse_motions: mouse_motions.clone(),
+                input_setting: input_setting.clone(),
+                output_setting: output_setting.clone(),
             };
             app_shared_data.pending_setting = Some(setting);
         });
@@ -76,12 +105,16 @@ impl SettingArea {
             self.window_setting_row.show(ui);
             ui.separator();
             self.key_property_setting_row.show(ui);
+            self.key_group_setting_row.show(ui);
+            self.mouse_motion_setting_row.show(ui);
         });
     }
 }
 
 struct WindowSettingRow {
     window_setting: WindowSetting,
+    input_setting: InputSetting,
+    output_setting: OutputSetting,
     background_color: UColor32,
     current_font_family: Box<str>,
     font_families: Box<[Box<str>]>,
@@ -106,6 +139,8 @@ impl WindowSettingRow {
         };
         Self {
             window_setting: setting.window_setting.clone(),
+            input_setting: setting.input_setting.clone(),
+            output_setting: setting.output_setting.clone(),
             background_color: setting.background_color,
             current_font_family: setting.font_name.clone(),
             font_families,
@@ -115,6 +150,8 @@ impl WindowSettingRow {
 
     fn reload(&mut self, setting: &Setting) {
         self.window_setting = setting.window_setting.clone();
+        self.input_setting = setting.input_setting.clone();
+        self.output_setting = setting.output_setting.clone();
         self.background_color = setting.background_color;
         self.current_font_family = setting.font_name.clone();
     }
@@ -176,6 +213,58 @@ impl WindowSettingRow {
                         .ui(ui)
                         .changed();
                 });
+                grid_new_row!(ui, {
+                    egui::Label::new("缩放:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text("窗口大小与所有按键的尺寸、坐标、字号等同时乘以该倍数");
+                    changed |= egui::Slider::new(&mut self.window_setting.scale, 0.25..=4.0)
+                        .logarithmic(true)
+                        .fixed_decimals(2)
+                        .ui(ui)
+                        .changed();
+                });
+                grid_new_row!(ui, {
+                    egui::Label::new("可调整大小:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text(concat!(
+                            "允许拖动调整KeyOverlay窗口的大小，布局会随之重新排列。\n",
+                            "按键按锚点与百分比坐标定位"
+                        ));
+                    changed |= egui::Checkbox::without_text(&mut self.window_setting.resizable)
+                        .ui(ui)
+                        .changed();
+                });
+                grid_new_row!(ui, {
+                    egui::Label::new("滚轮按下时长:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text("滚轮每滚动一格，对应的按键被按下的时长，单位为毫秒");
+                    changed |= egui::Slider::new(
+                        &mut self.input_setting.wheel_press_duration_ms,
+                        1.0..=1_000.0,
+                    )
+                    .integer()
+                    .logarithmic(true)
+                    .drag_value_speed(1.0)
+                    .ui(ui)
+                    .changed();
+                });
+                grid_new_row!(ui, {
+                    egui::Label::new("防抖时长:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text(concat!(
+                            "松开后在此时长内再次按下视为抖动，不计数，单位为毫秒。\n",
+                            "为0时不防抖，按键可以单独设置。"
+                        ));
+                    changed |= egui::Slider::new(&mut self.input_setting.debounce_ms, 0.0..=100.0)
+                        .integer()
+                        .drag_value_speed(1.0)
+                        .ui(ui)
+                        .changed();
+                });
             });
 
             ui.separator();
@@ -212,6 +301,187 @@ impl WindowSettingRow {
                         });
                     });
                 });
+                grid_new_row!(ui, {
+                    egui::Label::new("网络输入:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text(concat!(
+                            "接收：显示另一台电脑发送的按键，例如游戏和OBS不在同一台电脑上。\n",
+                            "发送：把本机的按键发送到另一台电脑。\n",
+                            "注意:该选项在修改/保存后不会立即生效"
+                        ));
+                    let network = &mut self.input_setting.network;
+                    let map = |mode: NetMode| match mode {
+                        NetMode::Off => "关闭",
+                        NetMode::Receive => "接收",
+                        NetMode::Send => "发送",
+                    };
+                    egui::ComboBox::from_id_salt(ui.next_auto_id())
+                        .selected_text(map(network.mode))
+                        .width(0.0)
+                        .show_ui(ui, |ui| {
+                            [NetMode::Off, NetMode::Receive, NetMode::Send]
+                                .into_iter()
+                                .for_each(|mode| {
+                                    changed |= ui
+                                        .selectable_value(&mut network.mode, mode, map(mode))
+                                        .changed();
+                                });
+                        });
+                });
+                grid_new_row!(ui, {
+                    egui::Label::new("网络协议:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text("UDP延迟更低，TCP不会丢失按键");
+                    let network = &mut self.input_setting.network;
+                    ui.add_enabled_ui(network.mode != NetMode::Off, |ui| {
+                        [(NetProtocol::Udp, "UDP"), (NetProtocol::Tcp, "TCP")]
+                            .into_iter()
+                            .for_each(|(protocol, text)| {
+                                changed |= ui
+                                    .radio_value(&mut network.protocol, protocol, text)
+                                    .changed();
+                            });
+                    });
+                });
+                grid_new_row!(ui, {
+                    egui::Label::new("网络地址:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text(concat!(
+                            "接收时为本机监听的地址，默认 127.0.0.1:23333 只接收本机，\n",
+                            "接收局域网内其他电脑需改为 0.0.0.0:23333\n",
+                            "发送时为接收方的地址，例如 192.168.1.2:23333"
+                        ));
+                    let network = &mut self.input_setting.network;
+                    changed |= ui
+                        .add_enabled(
+                            network.mode != NetMode::Off,
+                            egui::TextEdit::singleline(&mut network.address),
+                        )
+                        .changed();
+                });
+            });
+
+            ui.separator();
+
+            let grid_output = egui::Grid::new(ui.next_auto_id())
+                .min_col_width(0.0)
+                .striped(true);
+            grid_output.show(ui, |ui| {
+                let event_server = &mut self.output_setting.event_server;
+                grid_new_row!(ui, {
+                    egui::Label::new("事件服务器:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text(concat!(
+                            "以WebSocket广播按键事件和计数快照(JSON)，",
+                            "可供OBS浏览器源等网页使用。\n",
+                            "注意:该选项在修改/保存后不会立即生效"
+                        ));
+                    changed |= egui::Checkbox::without_text(&mut event_server.enabled)
+                        .ui(ui)
+                        .changed();
+                });
+                let enabled = event_server.enabled;
+                grid_new_row!(ui, {
+                    egui::Label::new("服务器地址:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text("例如 127.0.0.1:23334，连接地址为 ws://127.0.0.1:23334");
+                    changed |= ui
+                        .add_enabled(
+                            enabled,
+                            egui::TextEdit::singleline(&mut event_server.address),
+                        )
+                        .changed();
+                });
+                grid_new_row!(ui, {
+                    egui::Label::new("快照间隔:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text("广播计数和KPS的间隔，单位为毫秒");
+                    let slider =
+                        egui::Slider::new(&mut event_server.snapshot_interval_ms, 10.0..=10_000.0)
+                            .integer()
+                            .logarithmic(true)
+                            .drag_value_speed(1.0);
+                    changed |= ui.add_enabled(enabled, slider).changed();
+                });
+
+                let control_server = &mut self.output_setting.control_server;
+                grid_new_row!(ui, {
+                    egui::Label::new("控制接口:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text(concat!(
+                            "以HTTP JSON-RPC接收切换配置、重新加载、重置计数、暂停、查询计数等命令，",
+                            "可供Stream Deck等工具使用。\n",
+                            "注意:该选项在修改/保存后不会立即生效"
+                        ));
+                    changed |= egui::Checkbox::without_text(&mut control_server.enabled)
+                        .ui(ui)
+                        .changed();
+                });
+                let enabled = control_server.enabled;
+                grid_new_row!(ui, {
+                    egui::Label::new("接口地址:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text("例如 127.0.0.1:23335，请求地址为 http://127.0.0.1:23335");
+                    changed |= ui
+                        .add_enabled(
+                            enabled,
+                            egui::TextEdit::singleline(&mut control_server.address),
+                        )
+                        .changed();
+                });
+
+                let osc = &mut self.output_setting.osc;
+                grid_new_row!(ui, {
+                    egui::Label::new("OSC输出:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text(concat!(
+                            "以UDP发送OSC消息，每次按下/松开发送按键消息和KPS消息，",
+                            "可供VJ软件和可视化工具使用。\n",
+                            "注意:该选项在修改/保存后不会立即生效"
+                        ));
+                    changed |= egui::Checkbox::without_text(&mut osc.enabled)
+                        .ui(ui)
+                        .changed();
+                });
+                let enabled = osc.enabled;
+                grid_new_row!(ui, {
+                    egui::Label::new("目标地址:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text("接收OSC消息的地址，例如 127.0.0.1:9000");
+                    changed |= ui
+                        .add_enabled(enabled, egui::TextEdit::singleline(&mut osc.target))
+                        .changed();
+                });
+                grid_new_row!(ui, {
+                    egui::Label::new("按键地址:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text(
+                            "{key} 会被替换为按键名，例如 /key/KeyZ，参数按下为1松开为0，留空则不发送",
+                        );
+                    changed |= ui
+                        .add_enabled(enabled, egui::TextEdit::singleline(&mut osc.key_address))
+                        .changed();
+                });
+                grid_new_row!(ui, {
+                    egui::Label::new("KPS地址:")
+                        .selectable(false)
+                        .ui(ui)
+                        .on_hover_text("参数为最近一秒的按下次数，留空则不发送");
+                    changed |= ui
+                        .add_enabled(enabled, egui::TextEdit::singleline(&mut osc.kps_address))
+                        .changed();
+                });
             });
 
             changed
@@ -240,11 +510,14 @@ enum GlobalResponse {
 struct GlobalKeyPropertyCheckStates {
     global_operation: bool,
     key_bind: bool,
+    bind_mode: bool,
+    style: bool,
     key_text: bool,
     font_size: bool,
     position: bool,
     position_x: bool,
     position_y: bool,
+    anchor: bool,
     width: bool,
     height: bool,
     thickness: bool,
@@ -253,7 +526,12 @@ struct GlobalKeyPropertyCheckStates {
     pressed_color: bool,
     max_distance: bool,
     key_direction: bool,
+    bar_shape: bool,
     fade_length: bool,
+    actuation_point: bool,
+    depth_effect: bool,
+    device_filter: bool,
+    debounce_ms: bool,
     key_counter: bool,
     key_counter_position: bool,
     key_counter_position_x: bool,
@@ -267,11 +545,14 @@ macro_rules! match_global_key_property_check_states {
         let GlobalKeyPropertyCheckStates {
             global_operation: _,
             key_bind,
+            bind_mode,
+            style,
             key_text,
             font_size,
             position,
             position_x,
             position_y,
+            anchor,
             width,
             height,
             thickness,
@@ -280,7 +561,12 @@ macro_rules! match_global_key_property_check_states {
             pressed_color,
             max_distance,
             key_direction,
+            bar_shape,
             fade_length,
+            actuation_point,
+            depth_effect,
+            device_filter,
+            debounce_ms,
             key_counter,
             key_counter_position,
             key_counter_position_x,
@@ -289,28 +575,41 @@ macro_rules! match_global_key_property_check_states {
             key_counter_color,
         } = &$self_ident.global_key_property_check_states;
         key_bind.then(|| $macro_op!(key_bind));
-        key_text.then(|| $macro_op!(key_text, text_color));
+        bind_mode.then(|| $macro_op!(bind_mode));
+        style.then(|| $macro_op!(style));
+        key_text.then(|| $macro_op!(key_text, text_color, palette_refs.text_color));
         font_size.then(|| $macro_op!(font_size));
         position.then(|| {
             position_x.then(|| $macro_op!(position.x));
             position_y.then(|| $macro_op!(position.y));
         });
+        anchor.then(|| $macro_op!(anchor, percent_position));
         width.then(|| $macro_op!(width));
         height.then(|| $macro_op!(height));
         thickness.then(|| $macro_op!(thickness));
-        frame_color.then(|| $macro_op!(frame_color));
+        frame_color.then(|| $macro_op!(frame_color, palette_refs.frame_color));
         bar_speed.then(|| $macro_op!(bar_speed));
-        pressed_color.then(|| $macro_op!(pressed_color));
+        pressed_color.then(|| $macro_op!(pressed_color, palette_refs.pressed_color));
         max_distance.then(|| $macro_op!(max_distance));
         key_direction.then(|| $macro_op!(key_direction));
+        bar_shape.then(|| $macro_op!(bar_shape));
         fade_length.then(|| $macro_op!(fade_length));
-        key_counter.then(|| $macro_op!(key_counter.0));
+        actuation_point.then(|| $macro_op!(actuation_point));
+        depth_effect.then(|| $macro_op!(depth_effect));
+        device_filter.then(|| $macro_op!(device_filter));
+        debounce_ms.then(|| $macro_op!(debounce_ms));
+        key_counter.then(|| $macro_op!(key_counter.enabled));
         key_counter_position.then(|| {
-            key_counter_position_x.then(|| $macro_op!(key_counter.1.position.x));
-            key_counter_position_y.then(|| $macro_op!(key_counter.1.position.y));
+            key_counter_position_x.then(|| $macro_op!(key_counter.value.position.x));
+            key_counter_position_y.then(|| $macro_op!(key_counter.value.position.y));
+        });
+        key_counter_size.then(|| $macro_op!(key_counter.value.font_size));
+        key_counter_color.then(|| {
+            $macro_op!(
+                key_counter.value.text_color,
+                palette_refs.counter_text_color
+            )
         });
-        key_counter_size.then(|| $macro_op!(key_counter.1.font_size));
-        key_counter_color.then(|| $macro_op!(key_counter.1.text_color));
     };
 }
 
@@ -323,6 +622,9 @@ struct GlobalOperationCache {
 }
 
 struct KeyPropertySettingRow {
+    palette: Palette,
+    /// not edited here, kept so the keys can reference them
+    styles: Vec<KeyStyle>,
     key_properties: Vec<KeyProperty>,
     check_states: Vec<bool>,
     global_key_property: KeyProperty,
@@ -331,7 +633,7 @@ struct KeyPropertySettingRow {
     global_response: Option<GlobalResponse>,
     request_reload: bool,
     key_bind_menu_opened: bool,
-    key_binding: Option<Key>,
+    key_binding: Option<KeyMessage>,
 }
 
 impl KeyPropertySettingRow {
@@ -339,6 +641,8 @@ impl KeyPropertySettingRow {
 
     fn new(setting: &Setting) -> Self {
         Self {
+            palette: setting.palette.clone(),
+            styles: setting.styles.clone(),
             key_properties: setting.key_properties.clone(),
             check_states: vec![false; setting.key_properties.len()],
             global_key_property: Default::default(),
@@ -352,19 +656,21 @@ impl KeyPropertySettingRow {
     }
 
     fn reload(&mut self, setting: &Setting) {
+        self.palette = setting.palette.clone();
+        self.styles = setting.styles.clone();
         self.key_properties = setting.key_properties.clone();
         self.check_states.resize(self.key_properties.len(), false);
     }
 
-    fn update(&mut self, request_reload: &mut bool, keys_receiver: &MpscReceiver<KeyMessage>) {
+    fn update(&mut self, request_reload: &mut bool, keys_receiver: &MpscReceiver<InputMessage>) {
         self.handle_global_response();
         *request_reload |= std::mem::take(&mut self.request_reload);
         if self.key_bind_menu_opened {
             self.key_binding = keys_receiver
                 .try_iter()
+                .filter_map(InputMessage::key)
                 .find(|key_message| key_message.is_pressed)
-                .map(|key_message| key_message.key)
-                .filter(|key| *key != Key::MouseLeft);
+                .filter(|key_message| key_message.key != Key::MouseLeft);
         } else {
             self.key_binding.take();
         };
@@ -389,6 +695,14 @@ impl KeyPropertySettingRow {
         });
     }
 
+    /// like choosing the style for the key, the fields the style sets overwrite the key's
+    fn apply_style(styles: &[KeyStyle], style: Option<&str>, key_property: &mut KeyProperty) {
+        match style.and_then(|name| styles.iter().find(|style| style.name == name)) {
+            Some(style) => style.apply(key_property),
+            None => key_property.style = None,
+        }
+    }
+
     fn handle_global_response_write(&mut self) {
         let iter = self
             .check_states
@@ -397,6 +711,13 @@ impl KeyPropertySettingRow {
             .filter(|&(c, _)| *c);
         iter.for_each(|(_, key_property)| {
             macro_rules! write {
+                (style) => {
+                    Self::apply_style(
+                        &self.styles,
+                        self.global_key_property.style.as_deref(),
+                        key_property,
+                    )
+                };
                 ($($($token: tt).*),*) => {{
                     $(key_property.$($token).* = self.global_key_property.$($token).*.clone());*
                 }};
@@ -439,6 +760,13 @@ impl KeyPropertySettingRow {
             let index = self.check_states.iter().take_while(|&c| !*c).count();
             let key_property = self.key_properties.get_mut(index).expect("unreachable");
             macro_rules! write_one {
+                (style) => {
+                    Self::apply_style(
+                        &self.styles,
+                        self.global_key_property.style.as_deref(),
+                        key_property,
+                    )
+                };
                 ($($($token: tt).*),*) => {{
                     $(key_property.$($token).* = self.global_key_property.$($token).*.clone());*
                 }};
@@ -464,6 +792,13 @@ impl KeyPropertySettingRow {
     fn handle_global_response_create_one(&mut self) {
         let mut new_key_property = KeyProperty::default();
         macro_rules! create_one {
+            (style) => {
+                Self::apply_style(
+                    &self.styles,
+                    self.global_key_property.style.as_deref(),
+                    &mut new_key_property,
+                )
+            };
             ($($($token: tt).*),*) => {{
                 $(new_key_property.$($token).* = self.global_key_property.$($token).*.clone());*
             }};
@@ -524,6 +859,13 @@ impl KeyPropertySettingRow {
         let iter = (0..self.global_operation_cache.create_count).map(|_| {
             let mut new_key_property = KeyProperty::default();
             macro_rules! create_one {
+                    (style) => {
+                        Self::apply_style(
+                            &self.styles,
+                            self.global_key_property.style.as_deref(),
+                            &mut new_key_property,
+                        )
+                    };
                     ($($($token: tt).*),*) => {{
                         $(new_key_property.$($token).* =
                             self.global_key_property.$($token).*.clone());*
@@ -559,6 +901,8 @@ impl KeyPropertySettingRow {
 
         self.key_bind_menu_opened = false;
 
+        ui.horizontal(|ui| self.request_reload |= self.show_palette(ui));
+
         let len = self.key_properties.len();
         main_layout(ui, len, |ui, range| {
             let scroll_index = self.global_operation_cache.scroll_index;
@@ -608,6 +952,61 @@ impl KeyPropertySettingRow {
         ui.separator();
     }
 
+    fn show_palette(&mut self, ui: &mut egui::Ui) -> bool {
+        egui::Label::new("调色板:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text(concat!(
+                "颜色可以引用调色板中的颜色，切换调色板时引用它的颜色会一起改变。\n",
+                "在颜色旁边的选项中选择引用的颜色。"
+            ));
+        let mut palette = None;
+        let selected_text = match self.palette.name.as_str() {
+            "" if self.palette.colors.is_empty() => "无",
+            "" => "自定义",
+            name => name,
+        };
+        egui::ComboBox::from_id_salt(ui.next_auto_id())
+            .selected_text(selected_text)
+            .width(0.0)
+            .show_ui(ui, |ui| {
+                Palette::builtin().into_iter().for_each(|builtin| {
+                    let selected = self.palette == builtin;
+                    if ui.selectable_label(selected, &builtin.name).clicked() {
+                        palette = Some(builtin);
+                    }
+                });
+            });
+        let mut colors = self.palette.colors.clone();
+        let colors_changed = colors
+            .iter_mut()
+            .map(|(name, color)| {
+                egui::Label::new(name).selectable(false).ui(ui);
+                ui.color_edit_button_srgba_unmultiplied(&mut color.0)
+                    .changed()
+            })
+            .fold(false, |changed, c| changed | c);
+        colors_changed.then(|| {
+            palette = Some(Palette {
+                colors,
+                ..self.palette.clone()
+            })
+        });
+        palette
+            .map(|palette| {
+                self.palette = palette;
+                let palette = &self.palette;
+                self.key_properties
+                    .iter_mut()
+                    .chain(std::iter::once(&mut self.global_key_property))
+                    .for_each(|key_property| key_property.apply_palette(palette));
+                self.styles
+                    .iter_mut()
+                    .for_each(|style| style.apply_palette(palette));
+            })
+            .is_some()
+    }
+
     fn grid_key_bind_common(&mut self, ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
         egui::Label::new("按键绑定:")
             .selectable(false)
@@ -617,19 +1016,25 @@ impl KeyPropertySettingRow {
                 "当然，你也可以在展开的选项中选一个。"
             ));
         let mut changed = false;
+        let prev_key_bind = key_property.key_bind;
         egui::ComboBox::from_id_salt(ui.next_auto_id())
-            .selected_text(key_property.key_bind.to_string())
+            .selected_text(key_property.key_bind.long_name())
             .width(0.0)
             .close_behavior(PopupCloseBehavior::IgnoreClicks)
             .show_ui(ui, |ui| {
                 self.key_bind_menu_opened = true;
-                let key_to_scroll = self.key_binding.take().inspect(|key| {
-                    key_property.key_bind = *key;
-                    changed = true;
-                });
+                let key_to_scroll = self
+                    .key_binding
+                    .take()
+                    .map(|key_message| key_message.bound_key(key_property.bind_mode))
+                    .filter(|key| *key != Key::Unknown)
+                    .inspect(|key| {
+                        key_property.key_bind = *key;
+                        changed = true;
+                    });
                 Key::iter().for_each(|key| {
                     let response =
-                        ui.selectable_value(&mut key_property.key_bind, key, key.to_string());
+                        ui.selectable_value(&mut key_property.key_bind, key, key.long_name());
                     changed |= response.changed();
                     key_to_scroll
                         .filter(|&key_to_scroll| key_to_scroll == key)
@@ -641,12 +1046,18 @@ impl KeyPropertySettingRow {
                 "点击我，然后按下一个按键！\n",
                 "但是鼠标左键你得自己选"
             ));
+        // only replace the key text that was not edited by the user
+        (changed
+            && (key_property.key_text.is_empty()
+                || key_property.key_text == prev_key_bind.label()))
+        .then(|| key_property.key_text = key_property.key_bind.label().into());
         changed
     }
 
     fn grid_key_text_and_text_color_common(
         ui: &mut egui::Ui,
         key_property: &mut KeyProperty,
+        palette: &Palette,
     ) -> bool {
         egui::Label::new("按键文本:")
             .selectable(false)
@@ -657,9 +1068,12 @@ impl KeyPropertySettingRow {
             ));
         let mut changed = false;
         ui.horizontal(|ui| {
-            changed |= ui
-                .color_edit_button_srgba_unmultiplied(&mut key_property.text_color.0)
-                .changed();
+            changed |= palette_color_edit(
+                ui,
+                &mut key_property.text_color,
+                &mut key_property.palette_refs.text_color,
+                palette,
+            );
             changed |= egui::TextEdit::singleline(&mut key_property.key_text)
                 .show(ui)
                 .response
@@ -690,7 +1104,7 @@ impl KeyPropertySettingRow {
         egui::Label::new("位置:")
             .selectable(false)
             .ui(ui)
-            .on_hover_text("按键边框左上角的坐标");
+            .on_hover_text("按键锚点相对窗口锚点的坐标，默认锚点为左上角");
         let mut changed = false;
         ui.vertical(|ui| {
             ui.horizontal(|ui| changed |= x_add_contents(ui, key_property));
@@ -719,6 +1133,44 @@ impl KeyPropertySettingRow {
             .changed()
     }
 
+    fn grid_anchor_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("锚点:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text(concat!(
+                "按键的该点对齐窗口的同一点，再偏移位置坐标。\n",
+                "勾选百分比时，位置坐标为窗口宽高的百分比，调整窗口大小时随之移动。"
+            ));
+        let map = |anchor: Anchor| match anchor {
+            Anchor::TopLeft => "左上",
+            Anchor::Top => "上",
+            Anchor::TopRight => "右上",
+            Anchor::Left => "左",
+            Anchor::Center => "中",
+            Anchor::Right => "右",
+            Anchor::BottomLeft => "左下",
+            Anchor::Bottom => "下",
+            Anchor::BottomRight => "右下",
+        };
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(ui.next_auto_id())
+                .selected_text(map(key_property.anchor))
+                .width(0.0)
+                .show_ui(ui, |ui| {
+                    Anchor::ALL.into_iter().for_each(|anchor| {
+                        changed |= ui
+                            .selectable_value(&mut key_property.anchor, anchor, map(anchor))
+                            .changed();
+                    });
+                });
+            changed |= ui
+                .checkbox(&mut key_property.percent_position, "百分比")
+                .changed();
+        });
+        changed
+    }
+
     fn grid_width_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
         egui::Label::new("宽度:")
             .selectable(false)
@@ -761,13 +1213,21 @@ impl KeyPropertySettingRow {
         .changed()
     }
 
-    fn grid_frame_color_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+    fn grid_frame_color_common(
+        ui: &mut egui::Ui,
+        key_property: &mut KeyProperty,
+        palette: &Palette,
+    ) -> bool {
         egui::Label::new("边框颜色:")
             .selectable(false)
             .ui(ui)
             .on_hover_text("设置边框的颜色");
-        ui.color_edit_button_srgba_unmultiplied(&mut key_property.frame_color.0)
-            .changed()
+        palette_color_edit(
+            ui,
+            &mut key_property.frame_color,
+            &mut key_property.palette_refs.frame_color,
+            palette,
+        )
     }
 
     fn grid_bar_speed_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
@@ -783,13 +1243,21 @@ impl KeyPropertySettingRow {
             .changed()
     }
 
-    fn grid_pressed_color_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+    fn grid_pressed_color_common(
+        ui: &mut egui::Ui,
+        key_property: &mut KeyProperty,
+        palette: &Palette,
+    ) -> bool {
         egui::Label::new("按键条颜色:")
             .selectable(false)
             .ui(ui)
             .on_hover_text("设置按键条的颜色");
-        ui.color_edit_button_srgba_unmultiplied(&mut key_property.pressed_color.0)
-            .changed()
+        palette_color_edit(
+            ui,
+            &mut key_property.pressed_color,
+            &mut key_property.palette_refs.pressed_color,
+            palette,
+        )
     }
 
     fn grid_max_distance_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
@@ -803,15 +1271,15 @@ impl KeyPropertySettingRow {
             ));
         let mut changed = false;
         ui.horizontal(|ui| {
-            changed |= egui::Checkbox::without_text(&mut key_property.max_distance.0)
+            changed |= egui::Checkbox::without_text(&mut key_property.max_distance.enabled)
                 .ui(ui)
                 .changed();
-            let slider = egui::Slider::new(&mut key_property.max_distance.1, 1.0..=10_000.0)
+            let slider = egui::Slider::new(&mut key_property.max_distance.value, 1.0..=10_000.0)
                 .integer()
                 .logarithmic(true)
                 .drag_value_speed(1.0);
             changed |= ui
-                .add_enabled(key_property.max_distance.0, slider)
+                .add_enabled(key_property.max_distance.enabled, slider)
                 .changed();
         });
         changed
@@ -821,75 +1289,347 @@ impl KeyPropertySettingRow {
         egui::Label::new("按键方向:")
             .selectable(false)
             .ui(ui)
-            .on_hover_text("按键条的前进方向");
+            .on_hover_text(concat!(
+                "按键条的前进方向。\n",
+                "角度以向上为0°，顺时针增加，可用于倾斜或环形布局。"
+            ));
         let map = |direction: KeyDirection| match direction {
             KeyDirection::Up => "上",
             KeyDirection::Down => "下",
             KeyDirection::Left => "左",
             KeyDirection::Right => "右",
+            KeyDirection::Angle(_) => "角度",
         };
         let mut changed = false;
-        egui::ComboBox::from_id_salt(ui.next_auto_id())
-            .selected_text(map(key_property.key_direction))
-            .width(0.0)
-            .show_ui(ui, |ui| {
-                use KeyDirection::*;
-                [(Up, "上"), (Down, "下"), (Left, "左"), (Right, "右")]
-                    .into_iter()
-                    .for_each(|(direction, name)| {
-                        changed |= ui
-                            .selectable_value(&mut key_property.key_direction, direction, name)
-                            .changed();
-                    });
-            });
+        ui.horizontal(|ui| {
+            let angle = KeyDirection::Angle(key_property.key_direction.angle());
+            egui::ComboBox::from_id_salt(ui.next_auto_id())
+                .selected_text(map(key_property.key_direction))
+                .width(0.0)
+                .show_ui(ui, |ui| {
+                    use KeyDirection::*;
+                    [Up, Down, Left, Right, angle]
+                        .into_iter()
+                        .for_each(|direction| {
+                            changed |= ui
+                                .selectable_value(
+                                    &mut key_property.key_direction,
+                                    direction,
+                                    map(direction),
+                                )
+                                .changed();
+                        });
+                });
+            if let KeyDirection::Angle(angle) = &mut key_property.key_direction {
+                changed |= egui::DragValue::new(angle)
+                    .range(0.0..=360.0)
+                    .suffix("°")
+                    .speed(1.0)
+                    .ui(ui)
+                    .changed();
+            }
+        });
         changed
     }
 
-    fn grid_fade_length_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
-        egui::Label::new("渐隐距离:")
+    fn grid_bar_shape_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("按键条形状:")
             .selectable(false)
             .ui(ui)
-            .on_hover_text(concat!("渐隐效果的距离。\n", "勾选以启用渐隐效果。"));
+            .on_hover_text(concat!(
+                "按键条的宽度、对齐、与按键边框的间距和圆角半径。\n",
+                "宽度可以是像素，也可以是按键在垂直于按键条方向上的宽度的比例；\n",
+                "对齐的左右以沿按键条前进方向看为准。"
+            ));
+        let BarShape {
+            width,
+            align,
+            gap,
+            corner_radius,
+        } = &mut key_property.bar_shape;
         let mut changed = false;
-        ui.horizontal(|ui| {
-            changed |= egui::Checkbox::without_text(&mut key_property.fade_length.0)
-                .ui(ui)
-                .changed();
-            changed |= egui::Slider::new(&mut key_property.fade_length.1, 1.0..=10_000.0)
-                .integer()
-                .logarithmic(true)
-                .drag_value_speed(1.0)
-                .ui(ui)
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                egui::Label::new("宽度:").selectable(false).ui(ui);
+                let absolute = matches!(width, BarWidth::Absolute(_));
+                let mut set_absolute = absolute;
+                changed |= ui.checkbox(&mut set_absolute, "像素").changed();
+                (set_absolute != absolute).then(|| {
+                    *width = match set_absolute {
+                        true => BarWidth::Absolute(key_property.width),
+                        false => BarWidth::Fraction(1.0),
+                    }
+                });
+                changed |= match width {
+                    BarWidth::Absolute(width) => egui::Slider::new(width, 1.0..=10_000.0)
+                        .integer()
+                        .logarithmic(true)
+                        .drag_value_speed(1.0)
+                        .ui(ui),
+                    BarWidth::Fraction(fraction) => egui::Slider::new(fraction, 0.0..=1.0)
+                        .fixed_decimals(2)
+                        .ui(ui),
+                }
                 .changed();
+            });
+            ui.horizontal(|ui| {
+                egui::Label::new("对齐:").selectable(false).ui(ui);
+                [
+                    (BarAlign::Left, "左"),
+                    (BarAlign::Center, "中"),
+                    (BarAlign::Right, "右"),
+                ]
+                .into_iter()
+                .for_each(|(value, text)| {
+                    changed |= ui.selectable_value(align, value, text).changed();
+                });
+            });
+            ui.horizontal(|ui| {
+                egui::Label::new("间距:").selectable(false).ui(ui);
+                changed |= egui::Slider::new(gap, 0.0..=1_000.0)
+                    .integer()
+                    .drag_value_speed(1.0)
+                    .ui(ui)
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                egui::Label::new("圆角:").selectable(false).ui(ui);
+                changed |= egui::Slider::new(corner_radius, 0.0..=100.0)
+                    .drag_value_speed(0.5)
+                    .ui(ui)
+                    .changed();
+            });
         });
         changed
     }
 
-    fn grid_key_counter_enable_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
-        egui::Label::new("启用计数器:")
+    fn grid_actuation_point_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("触发深度:")
             .selectable(false)
             .ui(ui)
-            .on_hover_text("勾选以启用计数器");
-        egui::Checkbox::without_text(&mut key_property.key_counter.0)
+            .on_hover_text(concat!(
+                "模拟按键按下的深度达到此值时视为按下。\n",
+                "0为未按下，1为按到底，对普通按键无效。"
+            ));
+        egui::Slider::new(&mut key_property.actuation_point, 0.01..=1.0)
+            .fixed_decimals(2)
             .ui(ui)
             .changed()
     }
 
-    fn grid_key_counter_position_common(
-        ui: &mut egui::Ui,
-        key_property: &mut KeyProperty,
-        x_add_contents: impl FnOnce(&mut egui::Ui, &mut KeyProperty) -> bool,
-        y_add_contents: impl FnOnce(&mut egui::Ui, &mut KeyProperty) -> bool,
-    ) -> bool {
-        egui::Label::new("计数器位置:")
+    fn grid_bind_mode_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("绑定方式:")
             .selectable(false)
             .ui(ui)
-            .on_hover_text("计数器中心点相对于按键边框中心点的位置");
+            .on_hover_text(concat!(
+                "按键码：绑定键盘布局输出的按键。\n",
+                "扫描码：绑定按键的物理位置，切换键盘布局也不受影响，",
+                "按键名称以美式布局中同一位置的按键为准。"
+            ));
+        let map = |bind_mode: BindMode| match bind_mode {
+            BindMode::VirtualKey => "按键码",
+            BindMode::ScanCode => "扫描码",
+        };
         let mut changed = false;
-        ui.vertical(|ui| {
-            ui.horizontal(|ui| changed |= x_add_contents(ui, key_property));
-            ui.horizontal(|ui| changed |= y_add_contents(ui, key_property));
-        });
+        egui::ComboBox::from_id_salt(ui.next_auto_id())
+            .selected_text(map(key_property.bind_mode))
+            .width(0.0)
+            .show_ui(ui, |ui| {
+                [BindMode::VirtualKey, BindMode::ScanCode]
+                    .into_iter()
+                    .for_each(|bind_mode| {
+                        changed |= ui
+                            .selectable_value(
+                                &mut key_property.bind_mode,
+                                bind_mode,
+                                map(bind_mode),
+                            )
+                            .changed();
+                    });
+            });
+        changed
+    }
+
+    fn grid_style_common(
+        ui: &mut egui::Ui,
+        key_property: &mut KeyProperty,
+        styles: &[KeyStyle],
+    ) -> bool {
+        egui::Label::new("样式:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text(concat!(
+                "选择样式会用样式中的属性覆盖当前属性。\n",
+                "保存时与样式相同的属性不会写入配置文件，修改样式后按键会随之改变。"
+            ));
+        let mut changed = false;
+        egui::ComboBox::from_id_salt(ui.next_auto_id())
+            .selected_text(key_property.style.as_deref().unwrap_or("无"))
+            .width(0.0)
+            .show_ui(ui, |ui| {
+                if ui
+                    .selectable_label(key_property.style.is_none(), "无")
+                    .clicked()
+                {
+                    changed |= key_property.style.take().is_some();
+                }
+                styles.iter().for_each(|style| {
+                    let selected = key_property.style.as_deref() == Some(style.name.as_str());
+                    if ui.selectable_label(selected, &style.name).clicked() {
+                        style.apply(key_property);
+                        changed = true;
+                    }
+                });
+            });
+        changed
+    }
+
+    fn grid_depth_effect_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("深度效果:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text(concat!(
+                "模拟按键按下深度的显示方式。\n",
+                "普通按键的深度只有0和1。"
+            ));
+        let map = |depth_effect: DepthEffect| match depth_effect {
+            DepthEffect::None => "无",
+            DepthEffect::PressedFill => "按下填充",
+            DepthEffect::BarWidth => "按键条宽度",
+        };
+        let mut changed = false;
+        egui::ComboBox::from_id_salt(ui.next_auto_id())
+            .selected_text(map(key_property.depth_effect))
+            .width(0.0)
+            .show_ui(ui, |ui| {
+                [
+                    DepthEffect::None,
+                    DepthEffect::PressedFill,
+                    DepthEffect::BarWidth,
+                ]
+                .into_iter()
+                .for_each(|depth_effect| {
+                    changed |= ui
+                        .selectable_value(
+                            &mut key_property.depth_effect,
+                            depth_effect,
+                            map(depth_effect),
+                        )
+                        .changed();
+                });
+            });
+        changed
+    }
+
+    fn grid_device_filter_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("输入设备:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text(concat!(
+                "只显示来自此设备的输入。\n",
+                "可用于区分多个键盘，或者屏蔽用于宏的小键盘。"
+            ));
+        let any_device = "任意设备";
+        let selected_text = key_property
+            .device_filter
+            .as_deref()
+            .map_or(any_device.into(), DeviceInfo::short_name);
+        let mut changed = false;
+        egui::ComboBox::from_id_salt(ui.next_auto_id())
+            .selected_text(selected_text)
+            .width(0.0)
+            .show_ui(ui, |ui| {
+                changed |= ui
+                    .selectable_value(&mut key_property.device_filter, None, any_device)
+                    .changed();
+                // only enumerated while the list is open
+                device::list_devices().into_iter().for_each(|device_info| {
+                    let kind = match device_info.kind {
+                        DeviceKind::Keyboard => "键盘",
+                        DeviceKind::Mouse => "鼠标",
+                    };
+                    let text = format!("{} ({kind})", DeviceInfo::short_name(&device_info.name));
+                    changed |= ui
+                        .selectable_value(
+                            &mut key_property.device_filter,
+                            Some(device_info.name.clone()),
+                            text,
+                        )
+                        .on_hover_text(&device_info.name)
+                        .changed();
+                });
+            });
+        changed
+    }
+
+    fn grid_debounce_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("防抖时长:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text(concat!(
+                "松开后在此时长内再次按下视为抖动，不计数，单位为毫秒。\n",
+                "勾选以覆盖全局防抖时长。"
+            ));
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= egui::Checkbox::without_text(&mut key_property.debounce_ms.enabled)
+                .ui(ui)
+                .changed();
+            let slider = egui::Slider::new(&mut key_property.debounce_ms.value, 0.0..=100.0)
+                .integer()
+                .drag_value_speed(1.0);
+            changed |= ui
+                .add_enabled(key_property.debounce_ms.enabled, slider)
+                .changed();
+        });
+        changed
+    }
+
+    fn grid_fade_length_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("渐隐距离:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text(concat!("渐隐效果的距离。\n", "勾选以启用渐隐效果。"));
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= egui::Checkbox::without_text(&mut key_property.fade_length.enabled)
+                .ui(ui)
+                .changed();
+            changed |= egui::Slider::new(&mut key_property.fade_length.value, 1.0..=10_000.0)
+                .integer()
+                .logarithmic(true)
+                .drag_value_speed(1.0)
+                .ui(ui)
+                .changed();
+        });
+        changed
+    }
+
+    fn grid_key_counter_enable_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+        egui::Label::new("启用计数器:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text("勾选以启用计数器");
+        egui::Checkbox::without_text(&mut key_property.key_counter.enabled)
+            .ui(ui)
+            .changed()
+    }
+
+    fn grid_key_counter_position_common(
+        ui: &mut egui::Ui,
+        key_property: &mut KeyProperty,
+        x_add_contents: impl FnOnce(&mut egui::Ui, &mut KeyProperty) -> bool,
+        y_add_contents: impl FnOnce(&mut egui::Ui, &mut KeyProperty) -> bool,
+    ) -> bool {
+        egui::Label::new("计数器位置:")
+            .selectable(false)
+            .ui(ui)
+            .on_hover_text("计数器中心点相对于按键边框中心点的位置");
+        let mut changed = false;
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| changed |= x_add_contents(ui, key_property));
+            ui.horizontal(|ui| changed |= y_add_contents(ui, key_property));
+        });
         changed
     }
 
@@ -899,7 +1639,7 @@ impl KeyPropertySettingRow {
     ) -> bool {
         egui::Label::new("x:").selectable(false).ui(ui);
         egui::Slider::new(
-            &mut key_property.key_counter.1.position.x,
+            &mut key_property.key_counter.value.position.x,
             -10_000.0..=10_000.0,
         )
         .integer()
@@ -915,7 +1655,7 @@ impl KeyPropertySettingRow {
     ) -> bool {
         egui::Label::new("y:").selectable(false).ui(ui);
         egui::Slider::new(
-            &mut key_property.key_counter.1.position.y,
+            &mut key_property.key_counter.value.position.y,
             -10_000.0..=10_000.0,
         )
         .integer()
@@ -931,7 +1671,7 @@ impl KeyPropertySettingRow {
             .ui(ui)
             .on_hover_text("计数器字体的大小");
         egui::Slider::new(
-            &mut key_property.key_counter.1.font_size,
+            &mut key_property.key_counter.value.font_size,
             1.0..=Self::MAX_FONT_SIZE,
         )
         .integer()
@@ -941,13 +1681,21 @@ impl KeyPropertySettingRow {
         .changed()
     }
 
-    fn grid_key_counter_color_common(ui: &mut egui::Ui, key_property: &mut KeyProperty) -> bool {
+    fn grid_key_counter_color_common(
+        ui: &mut egui::Ui,
+        key_property: &mut KeyProperty,
+        palette: &Palette,
+    ) -> bool {
         egui::Label::new("计数器颜色:")
             .selectable(false)
             .ui(ui)
             .on_hover_text("计数器文本的颜色");
-        ui.color_edit_button_srgba_unmultiplied(&mut key_property.key_counter.1.text_color.0)
-            .changed()
+        palette_color_edit(
+            ui,
+            &mut key_property.key_counter.value.text_color,
+            &mut key_property.palette_refs.counter_text_color,
+            palette,
+        )
     }
 
     fn show_column(&mut self, index: usize, ui: &mut egui::Ui) -> bool {
@@ -986,9 +1734,19 @@ impl KeyPropertySettingRow {
             changed |= self.grid_key_bind_common(ui, key_property);
         });
 
+        // bind_mode
+        grid_new_row!(ui, {
+            changed |= Self::grid_bind_mode_common(ui, key_property);
+        });
+
+        // style
+        grid_new_row!(ui, {
+            changed |= Self::grid_style_common(ui, key_property, &self.styles);
+        });
+
         // key_text & text_color
         grid_new_row!(ui, {
-            changed |= Self::grid_key_text_and_text_color_common(ui, key_property);
+            changed |= Self::grid_key_text_and_text_color_common(ui, key_property, &self.palette);
         });
 
         // font_size
@@ -1006,6 +1764,11 @@ impl KeyPropertySettingRow {
             );
         });
 
+        // anchor
+        grid_new_row!(ui, {
+            changed |= Self::grid_anchor_common(ui, key_property);
+        });
+
         // width
         grid_new_row!(ui, {
             changed |= Self::grid_width_common(ui, key_property);
@@ -1023,7 +1786,7 @@ impl KeyPropertySettingRow {
 
         // frame_color
         grid_new_row!(ui, {
-            changed |= Self::grid_frame_color_common(ui, key_property);
+            changed |= Self::grid_frame_color_common(ui, key_property, &self.palette);
         });
 
         // bar_speed
@@ -1033,7 +1796,7 @@ impl KeyPropertySettingRow {
 
         // pressed_color
         grid_new_row!(ui, {
-            changed |= Self::grid_pressed_color_common(ui, key_property);
+            changed |= Self::grid_pressed_color_common(ui, key_property, &self.palette);
         });
 
         // max_distance
@@ -1046,11 +1809,36 @@ impl KeyPropertySettingRow {
             changed |= Self::grid_key_direction_common(ui, key_property);
         });
 
+        // bar_shape
+        grid_new_row!(ui, {
+            changed |= Self::grid_bar_shape_common(ui, key_property);
+        });
+
         // fade_length
         grid_new_row!(ui, {
             changed |= Self::grid_fade_length_common(ui, key_property);
         });
 
+        // actuation_point
+        grid_new_row!(ui, {
+            changed |= Self::grid_actuation_point_common(ui, key_property);
+        });
+
+        // depth_effect
+        grid_new_row!(ui, {
+            changed |= Self::grid_depth_effect_common(ui, key_property);
+        });
+
+        // device_filter
+        grid_new_row!(ui, {
+            changed |= Self::grid_device_filter_common(ui, key_property);
+        });
+
+        // debounce_ms
+        grid_new_row!(ui, {
+            changed |= Self::grid_debounce_common(ui, key_property);
+        });
+
         // key_counter enable
         grid_new_row!(ui, {
             changed |= Self::grid_key_counter_enable_common(ui, key_property);
@@ -1073,7 +1861,7 @@ impl KeyPropertySettingRow {
 
         // key_counter color
         grid_new_row!(ui, {
-            changed |= Self::grid_key_counter_color_common(ui, key_property);
+            changed |= Self::grid_key_counter_color_common(ui, key_property, &self.palette);
         });
 
         changed
@@ -1087,11 +1875,14 @@ impl KeyPropertySettingRow {
         let GlobalKeyPropertyCheckStates {
             global_operation,
             key_bind,
+            bind_mode,
+            style,
             key_text,
             font_size,
             position,
             position_x,
             position_y,
+            anchor,
             width,
             height,
             thickness,
@@ -1100,7 +1891,12 @@ impl KeyPropertySettingRow {
             pressed_color,
             max_distance,
             key_direction,
+            bar_shape,
             fade_length,
+            actuation_point,
+            depth_effect,
+            device_filter,
+            debounce_ms,
             key_counter,
             key_counter_position,
             key_counter_position_x,
@@ -1122,11 +1918,14 @@ impl KeyPropertySettingRow {
                 .then(|| {
                     let v = *global_operation;
                     *key_bind = v;
+                    *bind_mode = v;
+                    *style = v;
                     *key_text = v;
                     *font_size = v;
                     *position = v;
                     *position_x = v;
                     *position_y = v;
+                    *anchor = v;
                     *width = v;
                     *height = v;
                     *thickness = v;
@@ -1135,7 +1934,12 @@ impl KeyPropertySettingRow {
                     *pressed_color = v;
                     *max_distance = v;
                     *key_direction = v;
+                    *bar_shape = v;
                     *fade_length = v;
+                    *actuation_point = v;
+                    *depth_effect = v;
+                    *device_filter = v;
+                    *debounce_ms = v;
                     *key_counter = v;
                     *key_counter_position = v;
                     *key_counter_position_x = v;
@@ -1274,10 +2078,22 @@ impl KeyPropertySettingRow {
             self.grid_key_bind_common(ui, key_property);
         });
 
+        // bind_mode
+        grid_new_row!(ui, {
+            common_checkbox(ui, bind_mode);
+            Self::grid_bind_mode_common(ui, key_property);
+        });
+
+        // style
+        grid_new_row!(ui, {
+            common_checkbox(ui, style);
+            Self::grid_style_common(ui, key_property, &self.styles);
+        });
+
         // key_text & text_color
         grid_new_row!(ui, {
             common_checkbox(ui, key_text);
-            Self::grid_key_text_and_text_color_common(ui, key_property);
+            Self::grid_key_text_and_text_color_common(ui, key_property, &self.palette);
         });
 
         // font_size
@@ -1303,6 +2119,12 @@ impl KeyPropertySettingRow {
             );
         });
 
+        // anchor
+        grid_new_row!(ui, {
+            common_checkbox(ui, anchor);
+            Self::grid_anchor_common(ui, key_property);
+        });
+
         // width
         grid_new_row!(ui, {
             common_checkbox(ui, width);
@@ -1324,7 +2146,7 @@ impl KeyPropertySettingRow {
         // frame_color
         grid_new_row!(ui, {
             common_checkbox(ui, frame_color);
-            Self::grid_frame_color_common(ui, key_property);
+            Self::grid_frame_color_common(ui, key_property, &self.palette);
         });
 
         // bar_speed
@@ -1336,7 +2158,7 @@ impl KeyPropertySettingRow {
         // pressed_color
         grid_new_row!(ui, {
             common_checkbox(ui, pressed_color);
-            Self::grid_pressed_color_common(ui, key_property);
+            Self::grid_pressed_color_common(ui, key_property, &self.palette);
         });
 
         // max_distance
@@ -1351,12 +2173,42 @@ impl KeyPropertySettingRow {
             Self::grid_key_direction_common(ui, key_property);
         });
 
+        // bar_shape
+        grid_new_row!(ui, {
+            common_checkbox(ui, bar_shape);
+            Self::grid_bar_shape_common(ui, key_property);
+        });
+
         // fade_length
         grid_new_row!(ui, {
             common_checkbox(ui, fade_length);
             Self::grid_fade_length_common(ui, key_property);
         });
 
+        // actuation_point
+        grid_new_row!(ui, {
+            common_checkbox(ui, actuation_point);
+            Self::grid_actuation_point_common(ui, key_property);
+        });
+
+        // depth_effect
+        grid_new_row!(ui, {
+            common_checkbox(ui, depth_effect);
+            Self::grid_depth_effect_common(ui, key_property);
+        });
+
+        // device_filter
+        grid_new_row!(ui, {
+            common_checkbox(ui, device_filter);
+            Self::grid_device_filter_common(ui, key_property);
+        });
+
+        // debounce_ms
+        grid_new_row!(ui, {
+            common_checkbox(ui, debounce_ms);
+            Self::grid_debounce_common(ui, key_property);
+        });
+
         // key_counter enable
         grid_new_row!(ui, {
             common_checkbox(ui, key_counter);
@@ -1389,9 +2241,467 @@ impl KeyPropertySettingRow {
         // key_counter color
         grid_new_row!(ui, {
             common_checkbox(ui, key_counter_color);
-            Self::grid_key_counter_color_common(ui, key_property);
+            Self::grid_key_counter_color_common(ui, key_property, &self.palette);
         });
 
         response
     }
 }
+
+struct KeyGroupSettingRow {
+    key_groups: Vec<KeyGroupProperty>,
+    request_reload: bool,
+}
+
+impl KeyGroupSettingRow {
+    fn new(setting: &Setting) -> Self {
+        Self {
+            key_groups: setting.key_groups.clone(),
+            request_reload: false,
+        }
+    }
+
+    fn reload(&mut self, setting: &Setting) {
+        self.key_groups = setting.key_groups.clone();
+    }
+
+    fn update(&mut self, request_reload: &mut bool) {
+        *request_reload |= std::mem::take(&mut self.request_reload);
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut index_to_remove = None;
+
+        ui.horizontal_wrapped(|ui| {
+            self.key_groups
+                .iter_mut()
+                .enumerate()
+                .for_each(|(index, key_group)| {
+                    let frame = egui::Frame::default()
+                        .inner_margin(egui::Margin::same(5))
+                        .stroke(ui.visuals().noninteractive().bg_stroke);
+                    frame.show(ui, |ui| {
+                        egui::Grid::new(ui.next_auto_id())
+                            .striped(true)
+                            .show(ui, |ui| {
+                                changed |= Self::show_column(ui, key_group);
+                                grid_new_row!(ui, {
+                                    ui.button("删除分组")
+                                        .clicked()
+                                        .then(|| index_to_remove = Some(index));
+                                });
+                            });
+                    });
+                });
+        });
+
+        ui.button("新建分组")
+            .on_hover_text("新建一个按键分组，它的计数器显示组内所有按键的按下次数之和")
+            .clicked()
+            .then(|| {
+                self.key_groups.push(KeyGroupProperty::default());
+                changed = true;
+            });
+
+        index_to_remove.map(|index| {
+            self.key_groups.remove(index);
+            changed = true;
+        });
+
+        ui.separator();
+
+        self.request_reload |= changed;
+    }
+
+    fn show_column(ui: &mut egui::Ui, key_group: &mut KeyGroupProperty) -> bool {
+        let mut changed = false;
+
+        grid_new_row!(ui, {
+            egui::Label::new("分组名称:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("仅用于区分不同的分组");
+            changed |= egui::TextEdit::singleline(&mut key_group.name)
+                .show(ui)
+                .response
+                .changed();
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("全部按键:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("勾选以统计所有已绑定按键的总按下次数");
+            changed |= egui::Checkbox::without_text(&mut key_group.all_keys)
+                .ui(ui)
+                .changed();
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("组内按键:").selectable(false).ui(ui);
+            ui.add_enabled_ui(!key_group.all_keys, |ui| {
+                ui.vertical(|ui| {
+                    let mut key_index_to_remove = None;
+                    key_group
+                        .keys
+                        .iter_mut()
+                        .enumerate()
+                        .for_each(|(key_index, key)| {
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_salt(ui.next_auto_id())
+                                    .selected_text(key.long_name())
+                                    .width(0.0)
+                                    .show_ui(ui, |ui| {
+                                        Key::iter().for_each(|k| {
+                                            changed |= ui
+                                                .selectable_value(key, k, k.long_name())
+                                                .changed();
+                                        });
+                                    });
+                                ui.button("移除")
+                                    .clicked()
+                                    .then(|| key_index_to_remove = Some(key_index));
+                            });
+                        });
+                    key_index_to_remove.map(|key_index| {
+                        key_group.keys.remove(key_index);
+                        changed = true;
+                    });
+                    ui.button("添加按键").clicked().then(|| {
+                        key_group.keys.push(Key::default());
+                        changed = true;
+                    });
+                });
+            });
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("计数器位置:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("计数器中心点在窗口中的坐标");
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    egui::Label::new("x:").selectable(false).ui(ui);
+                    changed |= egui::Slider::new(&mut key_group.position.x, -10_000.0..=10_000.0)
+                        .integer()
+                        .logarithmic(true)
+                        .drag_value_speed(1.0)
+                        .ui(ui)
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    egui::Label::new("y:").selectable(false).ui(ui);
+                    changed |= egui::Slider::new(&mut key_group.position.y, -10_000.0..=10_000.0)
+                        .integer()
+                        .logarithmic(true)
+                        .drag_value_speed(1.0)
+                        .ui(ui)
+                        .changed();
+                });
+            });
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("计数器大小:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("计数器字体的大小");
+            changed |= egui::Slider::new(
+                &mut key_group.font_size,
+                1.0..=KeyPropertySettingRow::MAX_FONT_SIZE,
+            )
+            .integer()
+            .logarithmic(true)
+            .drag_value_speed(1.0)
+            .ui(ui)
+            .changed();
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("计数器颜色:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("计数器文本的颜色");
+            changed |= ui
+                .color_edit_button_srgba_unmultiplied(&mut key_group.text_color.0)
+                .changed();
+        });
+
+        changed
+    }
+}
+
+struct MouseMotionSettingRow {
+    mouse_motions: Vec<MouseMotionProperty>,
+    request_reload: bool,
+}
+
+impl MouseMotionSettingRow {
+    fn new(setting: &Setting) -> Self {
+        Self {
+            mouse_motions: setting.mouse_motions.clone(),
+            request_reload: false,
+        }
+    }
+
+    fn reload(&mut self, setting: &Setting) {
+        self.mouse_motions = setting.mouse_motions.clone();
+    }
+
+    fn update(&mut self, request_reload: &mut bool) {
+        *request_reload |= std::mem::take(&mut self.request_reload);
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut index_to_remove = None;
+
+        ui.horizontal_wrapped(|ui| {
+            self.mouse_motions
+                .iter_mut()
+                .enumerate()
+                .for_each(|(index, mouse_motion)| {
+                    let frame = egui::Frame::default()
+                        .inner_margin(egui::Margin::same(5))
+                        .stroke(ui.visuals().noninteractive().bg_stroke);
+                    frame.show(ui, |ui| {
+                        egui::Grid::new(ui.next_auto_id())
+                            .striped(true)
+                            .show(ui, |ui| {
+                                changed |= Self::show_column(ui, mouse_motion);
+                                grid_new_row!(ui, {
+                                    ui.button("删除鼠标轨迹")
+                                        .clicked()
+                                        .then(|| index_to_remove = Some(index));
+                                });
+                            });
+                    });
+                });
+        });
+
+        ui.button("新建鼠标轨迹")
+            .on_hover_text("新建一个鼠标轨迹，显示鼠标最近的移动、移动速度和累计移动距离")
+            .clicked()
+            .then(|| {
+                self.mouse_motions.push(MouseMotionProperty::default());
+                changed = true;
+            });
+
+        index_to_remove.map(|index| {
+            self.mouse_motions.remove(index);
+            changed = true;
+        });
+
+        ui.separator();
+
+        self.request_reload |= changed;
+    }
+
+    fn show_column(ui: &mut egui::Ui, mouse_motion: &mut MouseMotionProperty) -> bool {
+        let mut changed = false;
+
+        grid_new_row!(ui, {
+            egui::Label::new("轨迹位置:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("轨迹头部在窗口中的坐标");
+            changed |= Self::show_position(ui, &mut mouse_motion.position);
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("轨迹缩放:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("鼠标每移动一个单位，轨迹延伸的像素数");
+            changed |= egui::Slider::new(&mut mouse_motion.trail_scale, 0.001..=10.0)
+                .logarithmic(true)
+                .ui(ui)
+                .changed();
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("轨迹时长:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("轨迹保留最近多长时间的移动，单位为毫秒");
+            changed |= egui::Slider::new(&mut mouse_motion.trail_duration_ms, 1.0..=10_000.0)
+                .integer()
+                .logarithmic(true)
+                .drag_value_speed(1.0)
+                .ui(ui)
+                .changed();
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("轨迹粗细:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("轨迹线的粗细");
+            changed |= egui::Slider::new(&mut mouse_motion.trail_thickness, 1.0..=100.0)
+                .integer()
+                .logarithmic(true)
+                .drag_value_speed(1.0)
+                .ui(ui)
+                .changed();
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new("轨迹颜色:")
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("轨迹线的颜色，越旧的部分越透明");
+            changed |= ui
+                .color_edit_button_srgba_unmultiplied(&mut mouse_motion.trail_color.0)
+                .changed();
+        });
+
+        changed |= Self::show_readout(
+            ui,
+            "速度",
+            "鼠标每秒移动的单位数。\n勾选以显示。",
+            &mut mouse_motion.speed_readout,
+        );
+        changed |= Self::show_readout(
+            ui,
+            "距离",
+            "鼠标累计移动的单位数。\n勾选以显示。",
+            &mut mouse_motion.distance_readout,
+        );
+
+        changed
+    }
+
+    fn show_readout(
+        ui: &mut egui::Ui,
+        name: &str,
+        hover_text: &str,
+        readout: &mut Toggle<KeyCounterProperty>,
+    ) -> bool {
+        let mut changed = false;
+
+        grid_new_row!(ui, {
+            egui::Label::new(format!("{name}:"))
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text(hover_text);
+            changed |= egui::Checkbox::without_text(&mut readout.enabled)
+                .ui(ui)
+                .changed();
+        });
+
+        let Toggle {
+            enabled,
+            value: readout,
+        } = readout;
+        grid_new_row!(ui, {
+            egui::Label::new(format!("{name}位置:"))
+                .selectable(false)
+                .ui(ui)
+                .on_hover_text("相对于轨迹头部的坐标");
+            ui.add_enabled_ui(*enabled, |ui| {
+                changed |= Self::show_position(ui, &mut readout.position);
+            });
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new(format!("{name}大小:"))
+                .selectable(false)
+                .ui(ui);
+            ui.add_enabled_ui(*enabled, |ui| {
+                changed |= egui::Slider::new(
+                    &mut readout.font_size,
+                    1.0..=KeyPropertySettingRow::MAX_FONT_SIZE,
+                )
+                .integer()
+                .logarithmic(true)
+                .drag_value_speed(1.0)
+                .ui(ui)
+                .changed();
+            });
+        });
+
+        grid_new_row!(ui, {
+            egui::Label::new(format!("{name}颜色:"))
+                .selectable(false)
+                .ui(ui);
+            ui.add_enabled_ui(*enabled, |ui| {
+                changed |= ui
+                    .color_edit_button_srgba_unmultiplied(&mut readout.text_color.0)
+                    .changed();
+            });
+        });
+
+        changed
+    }
+
+    fn show_position(ui: &mut egui::Ui, position: &mut egui::Pos2) -> bool {
+        let mut changed = false;
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                egui::Label::new("x:").selectable(false).ui(ui);
+                changed |= egui::Slider::new(&mut position.x, -10_000.0..=10_000.0)
+                    .integer()
+                    .logarithmic(true)
+                    .drag_value_speed(1.0)
+                    .ui(ui)
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                egui::Label::new("y:").selectable(false).ui(ui);
+                changed |= egui::Slider::new(&mut position.y, -10_000.0..=10_000.0)
+                    .integer()
+                    .logarithmic(true)
+                    .drag_value_speed(1.0)
+                    .ui(ui)
+                    .changed();
+            });
+        });
+        changed
+    }
+}
+
+/// a color that can follow a palette color instead, editing the color stops following
+fn palette_color_edit(
+    ui: &mut egui::Ui,
+    color: &mut UColor32,
+    palette_ref: &mut Option<String>,
+    palette: &Palette,
+) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        if ui
+            .color_edit_button_srgba_unmultiplied(&mut color.0)
+            .changed()
+        {
+            palette_ref.take();
+            changed = true;
+        }
+        if palette.colors.is_empty() && palette_ref.is_none() {
+            return;
+        }
+        egui::ComboBox::from_id_salt(ui.next_auto_id())
+            .selected_text(palette_ref.as_deref().unwrap_or("自定义"))
+            .width(0.0)
+            .show_ui(ui, |ui| {
+                if ui
+                    .selectable_label(palette_ref.is_none(), "自定义")
+                    .clicked()
+                {
+                    changed |= palette_ref.take().is_some();
+                }
+                palette.colors.iter().for_each(|(name, palette_color)| {
+                    let selected = palette_ref.as_deref() == Some(name.as_str());
+                    if ui.selectable_label(selected, name).clicked() {
+                        *palette_ref = Some(name.clone());
+                        *color = *palette_color;
+                        changed = true;
+                    }
+                });
+            })
+            .response
+            .on_hover_text("引用调色板中的颜色");
+    });
+    changed
+}
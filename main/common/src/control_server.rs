@@ -0,0 +1,515 @@
+//! a local http endpoint taking json-rpc 2.0 commands, so tools can control the overlay
+//! without focusing its window
+//!
+//! `POST /` with `Content-Type: application/json` and a body like
+//! `{"jsonrpc":"2.0","method":"reset_counters","id":1}`, methods:
+//! - `switch_profile` `{"name":"..."}`: loads another setting file in the directory of the
+//!   current one, by its file name
+//! - `reload`: loads the current setting file again
+//! - `reset_counters`
+//! - `pause` `{"paused":true}`: presses are dropped while paused, toggles without `paused`
+//! - `query`: `{"paused":..,"kps":..,"keys":[..],"groups":[..]}`
+//!
+//! the input and output settings of a loaded setting take effect after a restart,
+//! every connection is served on a thread of its own so a slow client can not hold up the others
+
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    report,
+    setting::{ControlServerSetting, Setting},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+/// the overlay handles commands when it updates
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+const MAX_BODY_SIZE: usize = 64 * 1024;
+/// connections past this are closed right away
+const MAX_CONNECTIONS: usize = 8;
+
+/// what the overlay is asked to do
+#[derive(Debug)]
+pub enum ControlCommand {
+    LoadSetting(Box<Setting>),
+    ResetCounters,
+    /// toggles if `None`
+    SetPaused(Option<bool>),
+    Query,
+}
+
+/// a command waiting for its result
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: ControlReply,
+}
+
+pub struct ControlReply(mpsc::Sender<serde_json::Value>);
+
+impl ControlReply {
+    pub fn send(self, result: serde_json::Value) {
+        let _ = self.0.send(result);
+    }
+}
+
+/// the running server, stopped on drop
+pub struct ControlServer {
+    request_receiver: mpsc::Receiver<ControlRequest>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    local_addr: SocketAddr,
+}
+
+impl ControlServer {
+    /// `None` if disabled, `request_redraw` wakes the overlay up to handle a command
+    pub fn start(
+        control_server_setting: &ControlServerSetting,
+        profile_path: PathBuf,
+        request_redraw: Box<dyn FnMut() + Send>,
+    ) -> io::Result<Option<Self>> {
+        if !control_server_setting.enabled {
+            return Ok(None);
+        }
+        let listener = TcpListener::bind(&*control_server_setting.address)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let (request_sender, request_receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let running_1 = running.clone();
+        let dispatcher = Dispatcher {
+            request_sender,
+            request_redraw,
+            profile_dir: profile_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            profile_path,
+        };
+        let thread = std::thread::spawn(move || {
+            Dispatcher::run(Arc::new(Mutex::new(dispatcher)), &listener, &running_1)
+        });
+        Ok(Some(Self {
+            request_receiver,
+            running,
+            thread: Some(thread),
+            local_addr,
+        }))
+    }
+
+    /// like [`Self::start`], a failure is reported by [`report::warning`]
+    pub fn start_or_warn(
+        control_server_setting: &ControlServerSetting,
+        profile_path: PathBuf,
+        request_redraw: Box<dyn FnMut() + Send>,
+    ) -> Option<Self> {
+        Self::start(control_server_setting, profile_path, request_redraw).unwrap_or_else(|err| {
+            report::warning(format!("控制接口启动失败: {err}"));
+            None
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// commands received since the last call, never blocks
+    #[inline]
+    pub fn try_iter(&self) -> impl Iterator<Item = ControlRequest> {
+        self.request_receiver.try_iter()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        self.thread.take().map(JoinHandle::join);
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+    #[serde(default)]
+    id: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+    id: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcError {
+    const PARSE_ERROR: i32 = -32700;
+    const METHOD_NOT_FOUND: i32 = -32601;
+    const INVALID_PARAMS: i32 = -32602;
+    const INTERNAL_ERROR: i32 = -32603;
+
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+struct Dispatcher {
+    request_sender: mpsc::Sender<ControlRequest>,
+    request_redraw: Box<dyn FnMut() + Send>,
+    /// the setting file `reload` loads
+    profile_path: PathBuf,
+    /// where `switch_profile` looks for setting files
+    profile_dir: PathBuf,
+}
+
+impl Dispatcher {
+    fn run(dispatcher: Arc<Mutex<Self>>, listener: &TcpListener, running: &AtomicBool) {
+        let connections = Arc::new(AtomicUsize::new(0));
+        while running.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if connections.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                        connections.fetch_sub(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    let dispatcher = dispatcher.clone();
+                    let connections = connections.clone();
+                    std::thread::spawn(move || {
+                        if let Err(err) = Self::serve(&dispatcher, stream) {
+                            report::warning(format!("控制请求失败: {err}"));
+                        }
+                        connections.fetch_sub(1, Ordering::Relaxed);
+                    });
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(POLL_INTERVAL)
+                }
+                Err(err) => {
+                    report::warning(format!("无法接受控制连接: {err}"));
+                    std::thread::sleep(POLL_INTERVAL)
+                }
+            }
+        }
+    }
+
+    /// one request per connection
+    fn serve(dispatcher: &Mutex<Self>, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        stream.set_write_timeout(Some(READ_TIMEOUT))?;
+
+        let (head, mut body) = read_head(&mut stream)?;
+        let mut lines = head.lines();
+        let request_line = lines.next().unwrap_or_default();
+        let headers: Vec<_> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim()))
+            .collect();
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find_map(|(header_name, value)| (header_name == name).then_some(*value))
+        };
+
+        if !request_line.starts_with("POST ") {
+            return write_response(&mut stream, "405 Method Not Allowed", "");
+        }
+        // browsers can not send it to another origin without a preflight, which is refused
+        if !header("content-type").is_some_and(|value| value.starts_with("application/json")) {
+            return write_response(&mut stream, "415 Unsupported Media Type", "");
+        }
+        let content_length: usize = header("content-length")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0);
+        if content_length > MAX_BODY_SIZE {
+            return write_response(&mut stream, "413 Payload Too Large", "");
+        }
+        while body.len() < content_length {
+            let mut buf = [0; 1024];
+            let len = stream.read(&mut buf)?;
+            if len == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            body.extend_from_slice(&buf[..len]);
+        }
+        body.truncate(content_length);
+
+        let response = match serde_json::from_slice::<RpcRequest>(&body) {
+            Ok(RpcRequest { method, params, id }) => {
+                let (result, error) = match Self::dispatch(dispatcher, &method, &params) {
+                    Ok(result) => (Some(result), None),
+                    Err(error) => (None, Some(error)),
+                };
+                RpcResponse {
+                    jsonrpc: "2.0",
+                    result,
+                    error,
+                    id,
+                }
+            }
+            Err(err) => RpcResponse {
+                jsonrpc: "2.0",
+                result: None,
+                error: Some(RpcError::new(RpcError::PARSE_ERROR, err.to_string())),
+                id: serde_json::Value::Null,
+            },
+        };
+        let response = serde_json::to_string(&response).expect("unreachable");
+        write_response(&mut stream, "200 OK", &response)
+    }
+
+    /// the lock is let go while the overlay handles the command
+    fn dispatch(
+        dispatcher: &Mutex<Self>,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<serde_json::Value, RpcError> {
+        let mut dispatcher = dispatcher.lock();
+        let command = match method {
+            "switch_profile" => {
+                let name = params
+                    .get("name")
+                    .and_then(serde_json::Value::as_str)
+                    .ok_or_else(|| RpcError::new(RpcError::INVALID_PARAMS, "missing `name`"))?;
+                let path = dispatcher.profile(name)?;
+                let setting = Self::load_setting(path.clone())?;
+                dispatcher.profile_path = path;
+                ControlCommand::LoadSetting(setting)
+            }
+            "reload" => {
+                ControlCommand::LoadSetting(Self::load_setting(dispatcher.profile_path.clone())?)
+            }
+            "reset_counters" => ControlCommand::ResetCounters,
+            "pause" => {
+                ControlCommand::SetPaused(params.get("paused").and_then(serde_json::Value::as_bool))
+            }
+            "query" => ControlCommand::Query,
+            _ => {
+                return Err(RpcError::new(
+                    RpcError::METHOD_NOT_FOUND,
+                    format!("unknown method `{method}`"),
+                ));
+            }
+        };
+        let (reply_sender, reply_receiver) = mpsc::channel();
+        dispatcher
+            .request_sender
+            .send(ControlRequest {
+                command,
+                reply: ControlReply(reply_sender),
+            })
+            .map_err(|_| RpcError::new(RpcError::INTERNAL_ERROR, "overlay closed"))?;
+        (dispatcher.request_redraw)();
+        drop(dispatcher);
+        reply_receiver
+            .recv_timeout(REPLY_TIMEOUT)
+            .map_err(|_| RpcError::new(RpcError::INTERNAL_ERROR, "overlay not responding"))
+    }
+
+    /// a setting file in the profile directory, only a file name is taken
+    fn profile(&self, name: &str) -> Result<PathBuf, RpcError> {
+        let is_file_name = Path::new(name).file_name() == Some(name.as_ref());
+        match is_file_name {
+            true => Ok(self.profile_dir.join(name)),
+            false => Err(RpcError::new(
+                RpcError::INVALID_PARAMS,
+                format!("`{name}` is not a file name in the profile directory"),
+            )),
+        }
+    }
+
+    /// loaded here, the overlay must not block on the file
+    fn load_setting(path: PathBuf) -> Result<Box<Setting>, RpcError> {
+        Setting::from_file(&path)
+            .map(Box::new)
+            .map_err(|err| RpcError::new(RpcError::INVALID_PARAMS, format!("{path:?}: {err}")))
+    }
+}
+
+/// the request line and headers, and the part of the body read with them
+fn read_head(stream: &mut TcpStream) -> io::Result<(String, Vec<u8>)> {
+    let mut request = vec![];
+    let mut buf = [0; 1024];
+    loop {
+        if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            let body = request.split_off(end + 4);
+            return Ok((String::from_utf8_lossy(&request).into_owned(), body));
+        }
+        let len = stream.read(&mut buf)?;
+        if len == 0 || request.len() + len > MAX_HEADER_SIZE + MAX_BODY_SIZE {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        request.extend_from_slice(&buf[..len]);
+    }
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    let response = format!(
+        concat!(
+            "HTTP/1.1 {}\r\n",
+            "Content-Type: application/json\r\n",
+            "Content-Length: {}\r\n",
+            "Connection: close\r\n\r\n",
+            "{}"
+        ),
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// for [`report::log_to`], read by the test
+    #[derive(Clone, Default)]
+    struct SharedLog(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedLog {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn post(addr: SocketAddr, content_type: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).expect("unreachable");
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).expect("unreachable");
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("unreachable");
+        response
+    }
+
+    #[test]
+    fn rpc_round_trip() {
+        let setting = ControlServerSetting::default()
+            .with_enabled(true)
+            .with_address("127.0.0.1:0");
+        let control_server = ControlServer::start(&setting, PathBuf::new(), Box::new(|| ()))
+            .expect("unreachable")
+            .expect("unreachable");
+        let addr = control_server.local_addr();
+
+        // plays the overlay, which replies when it updates
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        let overlay = std::thread::spawn(move || {
+            while stop_receiver
+                .recv_timeout(Duration::from_millis(10))
+                .is_err()
+            {
+                control_server
+                    .try_iter()
+                    .for_each(|ControlRequest { command, reply }| {
+                        let result = match command {
+                            ControlCommand::SetPaused(paused) => serde_json::json!(paused),
+                            _ => serde_json::json!("ok"),
+                        };
+                        reply.send(result);
+                    });
+            }
+        });
+
+        // a client that never sends its request does not hold up the others
+        let _silent = TcpStream::connect(addr).expect("unreachable");
+        let instant = std::time::Instant::now();
+        let response = post(
+            addr,
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"pause","params":{"paused":true},"id":7}"#,
+        );
+        assert!(instant.elapsed() < READ_TIMEOUT / 2);
+        assert!(response.starts_with("HTTP/1.1 200"));
+        let body = &response[response.find("\r\n\r\n").expect("unreachable") + 4..];
+        let body: serde_json::Value = serde_json::from_str(body).expect("unreachable");
+        assert_eq!(body["result"], true);
+        assert_eq!(body["id"], 7);
+
+        let response = post(
+            addr,
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"jump","id":8}"#,
+        );
+        assert!(response.contains(&RpcError::METHOD_NOT_FOUND.to_string()));
+
+        // only setting files next to the current one
+        let response = post(
+            addr,
+            "application/json",
+            r#"{"jsonrpc":"2.0","method":"switch_profile","params":{"name":"../setting.json"},"id":9}"#,
+        );
+        assert!(response.contains(&RpcError::INVALID_PARAMS.to_string()));
+        assert!(response.contains("not a file name"));
+
+        let response = post(addr, "text/plain", r#"{"method":"reset_counters"}"#);
+        assert!(response.starts_with("HTTP/1.1 415"));
+
+        let _ = stop_sender.send(());
+        overlay.join().expect("unreachable");
+    }
+
+    #[test]
+    fn failure_reaches_log() {
+        // the only test setting the log, it is set once per process
+        let log = SharedLog::default();
+        report::log_to(Box::new(log.clone()));
+        let setting = ControlServerSetting::default()
+            .with_enabled(true)
+            .with_address("127.0.0.1:0");
+        let control_server = ControlServer::start(&setting, PathBuf::new(), Box::new(|| ()))
+            .expect("unreachable")
+            .expect("unreachable");
+
+        // closed halfway through the request
+        let mut stream = TcpStream::connect(control_server.local_addr()).expect("unreachable");
+        stream
+            .write_all(b"POST / HTTP/1.1\r\n")
+            .expect("unreachable");
+        drop(stream);
+
+        let is_logged =
+            || String::from_utf8_lossy(&log.0.lock()).contains("[warning] 控制请求失败");
+        let dead_line = std::time::Instant::now() + Duration::from_secs(5);
+        while !is_logged() && std::time::Instant::now() < dead_line {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(is_logged());
+    }
+}
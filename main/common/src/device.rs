@@ -0,0 +1,108 @@
+use windows::Win32::{
+    Foundation::HANDLE,
+    UI::Input::{
+        GetRawInputDeviceInfoW, GetRawInputDeviceList, RAWINPUTDEVICELIST, RIDI_DEVICENAME,
+        RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
+    },
+};
+
+/// identifies a physical input device across runs, derived from its device interface name
+///
+/// [`DeviceId::UNKNOWN`] is used for input without a source device, e.g. injected input
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceId(pub u64);
+
+impl DeviceId {
+    pub const UNKNOWN: Self = Self(0);
+
+    /// FNV-1a over the case-insensitive name, stable between runs unlike the raw input handle
+    pub fn from_name(name: &str) -> Self {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        let hash = name
+            .bytes()
+            .map(|byte| byte.to_ascii_lowercase())
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(PRIME)
+            });
+        Self(hash.max(1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Keyboard,
+    Mouse,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub kind: DeviceKind,
+}
+
+impl DeviceInfo {
+    /// `VID_046D PID_C52B`, or the whole name if it has no vendor and product ids
+    pub fn short_name(name: &str) -> String {
+        let upper = name.to_ascii_uppercase();
+        let find_id = |prefix: &str| {
+            let begin = upper.find(prefix)?;
+            upper.get(begin..begin + prefix.len() + 4)
+        };
+        match (find_id("VID_"), find_id("PID_")) {
+            (Some(vid), Some(pid)) => format!("{vid} {pid}"),
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// device interface name of a raw input device handle
+pub fn device_name(device: HANDLE) -> Option<String> {
+    let mut len = 0u32;
+    // Safety: querying the size only
+    unsafe { GetRawInputDeviceInfoW(Some(device), RIDI_DEVICENAME, None, &mut len) };
+    if len == 0 {
+        return None;
+    }
+    let mut buf = vec![0u16; len as usize];
+    // Safety: `buf` holds `len` characters
+    let copied = unsafe {
+        GetRawInputDeviceInfoW(
+            Some(device),
+            RIDI_DEVICENAME,
+            Some(buf.as_mut_ptr().cast()),
+            &mut len,
+        )
+    };
+    if copied == u32::MAX {
+        return None;
+    }
+    let end = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    Some(String::from_utf16_lossy(&buf[..end]))
+}
+
+/// keyboards and mice currently attached
+pub fn list_devices() -> Vec<DeviceInfo> {
+    let entry_size = size_of::<RAWINPUTDEVICELIST>() as u32;
+    let mut count = 0u32;
+    // Safety: querying the count only
+    unsafe { GetRawInputDeviceList(None, &mut count, entry_size) };
+    let mut list = vec![RAWINPUTDEVICELIST::default(); count as usize];
+    // Safety: `list` holds `count` entries
+    let copied = unsafe { GetRawInputDeviceList(Some(list.as_mut_ptr()), &mut count, entry_size) };
+    if copied == u32::MAX {
+        return vec![];
+    }
+    list.truncate(copied as usize);
+    list.into_iter()
+        .filter_map(|entry| {
+            let kind = match entry.dwType {
+                RIM_TYPEKEYBOARD => DeviceKind::Keyboard,
+                RIM_TYPEMOUSE => DeviceKind::Mouse,
+                _ => return None,
+            };
+            let name = device_name(entry.hDevice)?;
+            Some(DeviceInfo { name, kind })
+        })
+        .collect()
+}
@@ -0,0 +1,771 @@
+//! a websocket server broadcasting the input as json, for OBS browser sources and web uis
+//!
+//! fed by [`KeyOverlayCore`], so it sees the presses the overlay counts,
+//! every press and release is sent as
+//! `{"type":"key","key":"KeyZ","label":"Z","pressed":true,"time_ms":1234.5}`,
+//! counters and kps are sent every [`EventServerSetting::snapshot_interval_ms`] as
+//! `{"type":"snapshot","time_ms":..,"kps":..,"presses":..,"keys":[..],"groups":[..]}`
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    key::Key,
+    key_overlay_core::{KeyOverlayCore, key_handler::KeyHandler, key_message::KeyMessage},
+    report,
+    setting::EventServerSetting,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+/// clients only send control frames, anything larger is not a client of ours
+const MAX_CLIENT_FRAME_SIZE: usize = 8 * 1024;
+/// a client with more unsent than this can not keep up and is dropped
+const MAX_UNSENT_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyCount {
+    pub key: Key,
+    pub text: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupCount {
+    pub name: String,
+    pub count: u32,
+}
+
+/// counts of every key and key group, in the order of the setting
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Counts {
+    pub keys: Vec<KeyCount>,
+    pub groups: Vec<GroupCount>,
+}
+
+impl Counts {
+    pub fn new(key_handler: &KeyHandler) -> Self {
+        let keys = key_handler
+            .key_properties()
+            .iter()
+            .zip(key_handler.key_draw_caches())
+            .map(|(key_property, key_draw_cache)| KeyCount {
+                key: key_property.key_bind,
+                text: key_property.key_text.clone(),
+                count: key_draw_cache.count,
+            })
+            .collect();
+        let groups = key_handler
+            .key_group_counts_iter()
+            .map(|(key_group_property, count)| GroupCount {
+                name: key_group_property.name.clone(),
+                count,
+            })
+            .collect();
+        Self { keys, groups }
+    }
+}
+
+enum ServerMessage {
+    Key(KeyMessage),
+    Counts {
+        counts: Counts,
+        /// within [`KeyOverlayCore::KPS_WINDOW`] when sent, the kps falls on its own while idle
+        press_instants: Vec<Instant>,
+        presses: u64,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event<'a> {
+    Key {
+        key: Key,
+        label: &'a str,
+        pressed: bool,
+        time_ms: f64,
+    },
+    Snapshot {
+        time_ms: f64,
+        kps: u32,
+        presses: u64,
+        keys: &'a [KeyCount],
+        groups: &'a [GroupCount],
+    },
+}
+
+/// the running server, stopped on drop, feeding it never blocks
+pub struct EventServer {
+    sender: mpsc::Sender<ServerMessage>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    local_addr: SocketAddr,
+}
+
+impl EventServer {
+    /// `None` if disabled
+    pub fn start(event_server_setting: &EventServerSetting) -> io::Result<Option<Self>> {
+        if !event_server_setting.enabled {
+            return Ok(None);
+        }
+        let listener = TcpListener::bind(&*event_server_setting.address)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let (sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let running_1 = running.clone();
+        let snapshot_interval = event_server_setting.snapshot_interval();
+        let thread = std::thread::spawn(move || {
+            Broadcaster::new(listener, snapshot_interval).run(&receiver, &running_1)
+        });
+        Ok(Some(Self {
+            sender,
+            running,
+            thread: Some(thread),
+            local_addr,
+        }))
+    }
+
+    /// like [`Self::start`], a failure is reported by [`report::warning`]
+    pub fn start_or_warn(event_server_setting: &EventServerSetting) -> Option<Self> {
+        Self::start(event_server_setting).unwrap_or_else(|err| {
+            report::warning(format!("事件服务器启动失败: {err}"));
+            None
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// call when a key went down or up, repeats of a held key are not events
+    #[inline]
+    pub fn send_key(&self, key_message: KeyMessage) {
+        let _ = self.sender.send(ServerMessage::Key(key_message));
+    }
+
+    /// call after the counts changed, `press_instants` are the presses for the kps
+    pub fn send_counts(
+        &self,
+        key_handler: &KeyHandler,
+        press_instants: &VecDeque<Instant>,
+        presses: u64,
+    ) {
+        let _ = self.sender.send(ServerMessage::Counts {
+            counts: Counts::new(key_handler),
+            press_instants: press_instants.iter().copied().collect(),
+            presses,
+        });
+    }
+}
+
+impl Drop for EventServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        self.thread.take().map(JoinHandle::join);
+    }
+}
+
+/// a websocket connection, its socket never blocks so a silent client can not hold up the others
+struct Client {
+    stream: TcpStream,
+    /// the request until the handshake, then frames from the client, either may arrive in parts
+    received: Vec<u8>,
+    /// what the socket did not take yet, sent on later polls
+    unsent: Vec<u8>,
+    /// of the handshake, `None` once it is done
+    dead_line: Option<Instant>,
+}
+
+impl Client {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            received: vec![],
+            unsent: vec![],
+            dead_line: Some(Instant::now() + HANDSHAKE_TIMEOUT),
+        })
+    }
+
+    #[inline]
+    fn is_open(&self) -> bool {
+        self.dead_line.is_none()
+    }
+
+    /// sends what it can right away and keeps the rest, `Err` if the client is to be dropped
+    fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.unsent.len() + data.len() > MAX_UNSENT_SIZE {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        self.unsent.extend_from_slice(data);
+        self.flush()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.unsent.is_empty() {
+            match self.stream.write(&self.unsent) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => drop(self.unsent.drain(..len)),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// reads what the client sent and sends what is left, `Err` if it is to be dropped
+    fn poll(&mut self, instant_now: Instant) -> io::Result<()> {
+        let Some(handshake_dead_line) = self.dead_line else {
+            read_available(&mut self.stream, &mut self.received, MAX_CLIENT_FRAME_SIZE)?;
+            while let Some((opcode, payload, len)) = parse_frame(&self.received) {
+                self.received.drain(..len);
+                match opcode {
+                    OPCODE_CLOSE => {
+                        // echoes the status code, then the connection is done
+                        let _ = self.send(&frame(OPCODE_CLOSE, &payload));
+                        return Err(io::ErrorKind::ConnectionAborted.into());
+                    }
+                    OPCODE_PING => self.send(&frame(OPCODE_PONG, &payload))?,
+                    _ => (),
+                }
+            }
+            return self.flush();
+        };
+        read_available(&mut self.stream, &mut self.received, MAX_REQUEST_SIZE)?;
+        if !self.received.ends_with(b"\r\n\r\n") {
+            return match handshake_dead_line <= instant_now {
+                true => Err(io::ErrorKind::TimedOut.into()),
+                false => Ok(()),
+            };
+        }
+        match handshake_response(&self.received) {
+            Ok(response) => self.send(response.as_bytes())?,
+            Err(response) => {
+                let _ = self.send(response.as_bytes());
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+        }
+        self.received.clear();
+        self.dead_line = None;
+        Ok(())
+    }
+}
+
+/// appends what can be read without blocking, `Err` on a closed connection or past `max_len`
+fn read_available(stream: &mut TcpStream, buf: &mut Vec<u8>, max_len: usize) -> io::Result<()> {
+    let mut chunk = [0; 1024];
+    loop {
+        match stream.read(&mut chunk) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(len) if buf.len() + len > max_len => return Err(io::ErrorKind::InvalidData.into()),
+            Ok(len) => buf.extend_from_slice(&chunk[..len]),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+struct Broadcaster {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    base: Instant,
+    snapshot_interval: Duration,
+    /// the last ones sent by [`EventServer::send_counts`]
+    counts: Counts,
+    press_instants: Vec<Instant>,
+    presses: u64,
+}
+
+impl Broadcaster {
+    fn new(listener: TcpListener, snapshot_interval: Duration) -> Self {
+        Self {
+            listener,
+            clients: vec![],
+            base: Instant::now(),
+            snapshot_interval,
+            counts: Default::default(),
+            press_instants: vec![],
+            presses: 0,
+        }
+    }
+
+    fn run(mut self, receiver: &mpsc::Receiver<ServerMessage>, running: &AtomicBool) {
+        let mut next_snapshot = Instant::now();
+        while running.load(Ordering::Relaxed) {
+            let timeout = next_snapshot
+                .saturating_duration_since(Instant::now())
+                .min(POLL_INTERVAL);
+            match receiver.recv_timeout(timeout) {
+                Ok(ServerMessage::Key(key_message)) => self.broadcast_key(key_message),
+                Ok(ServerMessage::Counts {
+                    counts,
+                    press_instants,
+                    presses,
+                }) => {
+                    self.counts = counts;
+                    self.press_instants = press_instants;
+                    self.presses = presses;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => (),
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            self.accept_clients();
+            let instant_now = Instant::now();
+            self.poll_clients(instant_now);
+            if next_snapshot <= instant_now {
+                self.broadcast_snapshot(instant_now);
+                next_snapshot = instant_now + self.snapshot_interval;
+            }
+        }
+    }
+
+    fn time_ms(&self, instant: Instant) -> f64 {
+        instant.saturating_duration_since(self.base).as_secs_f64() * 1_000.0
+    }
+
+    fn broadcast_key(&mut self, key_message: KeyMessage) {
+        let KeyMessage {
+            key,
+            is_pressed,
+            instant,
+            ..
+        } = key_message;
+        self.broadcast(&Event::Key {
+            key,
+            label: key.label(),
+            pressed: is_pressed,
+            time_ms: self.time_ms(instant),
+        });
+    }
+
+    fn broadcast_snapshot(&mut self, instant_now: Instant) {
+        let dead_line = instant_now.checked_sub(KeyOverlayCore::KPS_WINDOW);
+        let kps = self
+            .press_instants
+            .iter()
+            .filter(|&&instant| dead_line.is_none_or(|dead_line| instant >= dead_line))
+            .count();
+        let Counts { keys, groups } = std::mem::take(&mut self.counts);
+        self.broadcast(&Event::Snapshot {
+            time_ms: self.time_ms(instant_now),
+            kps: kps as u32,
+            presses: self.presses,
+            keys: &keys,
+            groups: &groups,
+        });
+        self.counts = Counts { keys, groups };
+    }
+
+    fn broadcast(&mut self, event: &Event) {
+        if self.clients.is_empty() {
+            return;
+        }
+        let Ok(payload) = serde_json::to_vec(event) else {
+            return;
+        };
+        let frame = frame(OPCODE_TEXT, &payload);
+        // a client that can not keep up is dropped
+        self.clients
+            .retain_mut(|client| !client.is_open() || client.send(&frame).is_ok());
+    }
+
+    fn poll_clients(&mut self, instant_now: Instant) {
+        self.clients
+            .retain_mut(|client| match client.poll(instant_now) {
+                Ok(()) => true,
+                Err(err) => {
+                    if !client.is_open() {
+                        report::warning(format!("WebSocket握手失败: {err}"));
+                    }
+                    false
+                }
+            });
+    }
+
+    fn accept_clients(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => match Client::new(stream) {
+                    Ok(client) => self.clients.push(client),
+                    Err(err) => report::warning(format!("无法建立WebSocket连接: {err}")),
+                },
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return,
+                Err(err) => {
+                    report::warning(format!("无法接受WebSocket连接: {err}"));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// the response to a complete upgrade request, `Err` with a bad request response otherwise
+fn handshake_response(request: &[u8]) -> Result<String, &'static str> {
+    let request = String::from_utf8_lossy(request);
+    let key = request
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("sec-websocket-key")
+                .then(|| value.trim())
+        })
+        .ok_or("HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+    Ok(format!(
+        concat!(
+            "HTTP/1.1 101 Switching Protocols\r\n",
+            "Upgrade: websocket\r\n",
+            "Connection: Upgrade\r\n",
+            "Sec-WebSocket-Accept: {}\r\n\r\n"
+        ),
+        accept_key(key)
+    ))
+}
+
+/// `Sec-WebSocket-Accept` of a `Sec-WebSocket-Key`, see RFC 6455
+fn accept_key(key: &str) -> String {
+    const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+    base64(&sha1(format!("{key}{GUID}").as_bytes()))
+}
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// a single unmasked frame, servers never mask
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    const FIN: u8 = 0x80;
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(FIN | opcode);
+    match payload.len() {
+        len @ 0..126 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend((len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// `(opcode, payload, frame length)` of the first frame if it is complete,
+/// client frames are always masked
+fn parse_frame(buf: &[u8]) -> Option<(u8, Vec<u8>, usize)> {
+    let [first, second, ..] = *buf else {
+        return None;
+    };
+    let opcode = first & 0x0F;
+    let is_masked = second & 0x80 != 0;
+    let (payload_len, mut offset) = match second & 0x7F {
+        126 => (
+            u16::from_be_bytes(buf.get(2..4)?.try_into().ok()?) as usize,
+            4,
+        ),
+        127 => (
+            u64::from_be_bytes(buf.get(2..10)?.try_into().ok()?) as usize,
+            10,
+        ),
+        len => (len as usize, 2),
+    };
+    let mask = match is_masked {
+        true => {
+            let mask: [u8; 4] = buf.get(offset..offset + 4)?.try_into().ok()?;
+            offset += 4;
+            mask
+        }
+        false => [0; 4],
+    };
+    let len = offset.checked_add(payload_len)?;
+    let payload = buf
+        .get(offset..len)?
+        .iter()
+        .zip(mask.iter().cycle())
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    Some((opcode, payload, len))
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64).wrapping_mul(8).to_be_bytes());
+
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    message.chunks_exact(64).for_each(|chunk| {
+        let mut w = [0u32; 80];
+        w.iter_mut()
+            .zip(chunk.chunks_exact(4))
+            .for_each(|(w, bytes)| *w = u32::from_be_bytes(bytes.try_into().expect("unreachable")));
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        w.iter().enumerate().for_each(|(i, &w)| {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5A827999),
+                20..40 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        });
+        h.iter_mut()
+            .zip([a, b, c, d, e])
+            .for_each(|(h, v)| *h = h.wrapping_add(v));
+    });
+
+    let mut digest = [0; 20];
+    digest
+        .chunks_exact_mut(4)
+        .zip(h)
+        .for_each(|(bytes, h)| bytes.copy_from_slice(&h.to_be_bytes()));
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    data.chunks(3)
+        .flat_map(|chunk| {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+            (0..4).map(move |i| {
+                if i <= chunk.len() {
+                    TABLE[(n >> (18 - 6 * i) & 0x3F) as usize] as char
+                } else {
+                    '='
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{key_overlay_core::key_property::KeyProperty, setting::Setting};
+
+    use super::*;
+
+    #[test]
+    fn handshake_accept_key() {
+        // the example of RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"a"), "YQ==");
+    }
+
+    fn start_server() -> EventServer {
+        let setting = EventServerSetting::default()
+            .with_enabled(true)
+            .with_address("127.0.0.1:0");
+        EventServer::start(&setting)
+            .expect("unreachable")
+            .expect("unreachable")
+    }
+
+    /// a client after the handshake
+    fn connect(event_server: &EventServer) -> TcpStream {
+        let mut client = TcpStream::connect(event_server.local_addr()).expect("unreachable");
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .expect("unreachable");
+        client
+            .write_all(
+                concat!(
+                    "GET / HTTP/1.1\r\n",
+                    "Host: localhost\r\n",
+                    "Upgrade: websocket\r\n",
+                    "Connection: Upgrade\r\n",
+                    "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n",
+                    "Sec-WebSocket-Version: 13\r\n\r\n"
+                )
+                .as_bytes(),
+            )
+            .expect("unreachable");
+        let mut response = vec![];
+        let mut byte = [0];
+        while !response.ends_with(b"\r\n\r\n") {
+            client.read_exact(&mut byte).expect("unreachable");
+            response.push(byte[0]);
+        }
+        let response = String::from_utf8(response).expect("unreachable");
+        assert!(response.starts_with("HTTP/1.1 101"));
+        assert!(response.contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+        client
+    }
+
+    /// `(opcode, payload)` of the next frame from the server, which never masks
+    fn read_frame(client: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut header = [0; 2];
+        client.read_exact(&mut header).expect("unreachable");
+        assert_eq!(header[0] & 0x80, 0x80);
+        let len = match header[1] {
+            126 => {
+                let mut len = [0; 2];
+                client.read_exact(&mut len).expect("unreachable");
+                u16::from_be_bytes(len) as usize
+            }
+            127 => {
+                let mut len = [0; 8];
+                client.read_exact(&mut len).expect("unreachable");
+                u64::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut payload = vec![0; len];
+        client.read_exact(&mut payload).expect("unreachable");
+        (header[0] & 0x0F, payload)
+    }
+
+    fn read_key_event(client: &mut TcpStream) -> serde_json::Value {
+        loop {
+            let (opcode, payload) = read_frame(client);
+            assert_eq!(opcode, OPCODE_TEXT);
+            let event: serde_json::Value = serde_json::from_slice(&payload).expect("unreachable");
+            if event["type"] == "key" {
+                break event;
+            }
+        }
+    }
+
+    /// client frames are masked
+    fn masked_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+        frame.extend(mask);
+        frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+        frame
+    }
+
+    #[test]
+    fn broadcast_key_events() {
+        let event_server = start_server();
+        let mut client = connect(&event_server);
+
+        let instant = Instant::now();
+        [true, false].into_iter().for_each(|is_pressed| {
+            event_server.send_key(KeyMessage::new(Key::KeyZ, is_pressed, instant));
+        });
+        let press = read_key_event(&mut client);
+        assert_eq!(press["key"], "KeyZ");
+        assert_eq!(press["label"], "Z");
+        assert_eq!(press["pressed"], true);
+        let release = read_key_event(&mut client);
+        assert_eq!(release["pressed"], false);
+    }
+
+    #[test]
+    fn large_snapshot_arrives_whole() {
+        let event_server = start_server();
+        let mut client = connect(&event_server);
+
+        // larger than what a socket takes at once, sent over several polls
+        let key_properties = (0..10_000)
+            .map(|_| {
+                KeyProperty::default()
+                    .with_key_bind(Key::KeyZ)
+                    .with_key_text("Z".repeat(32))
+            })
+            .collect();
+        let key_handler = KeyHandler::new(Setting {
+            key_properties,
+            ..Setting::default()
+        });
+        event_server.send_counts(&key_handler, &VecDeque::new(), 0);
+        let snapshot = loop {
+            let (opcode, payload) = read_frame(&mut client);
+            assert_eq!(opcode, OPCODE_TEXT);
+            let event: serde_json::Value = serde_json::from_slice(&payload).expect("unreachable");
+            if event["keys"]
+                .as_array()
+                .is_some_and(|keys| !keys.is_empty())
+            {
+                break event;
+            }
+        };
+        assert_eq!(snapshot["keys"].as_array().map(Vec::len), Some(10_000));
+    }
+
+    #[test]
+    fn silent_client_does_not_stall() {
+        let event_server = start_server();
+        let _silent = TcpStream::connect(event_server.local_addr()).expect("unreachable");
+        let mut client = connect(&event_server);
+
+        let instant = Instant::now();
+        event_server.send_key(KeyMessage::new(Key::KeyZ, true, instant));
+        assert_eq!(read_key_event(&mut client)["pressed"], true);
+        assert!(instant.elapsed() < HANDSHAKE_TIMEOUT / 2);
+    }
+
+    #[test]
+    fn ping_and_close() {
+        let event_server = start_server();
+        let mut client = connect(&event_server);
+
+        client
+            .write_all(&masked_frame(OPCODE_PING, b"hi"))
+            .expect("unreachable");
+        let pong = loop {
+            match read_frame(&mut client) {
+                (OPCODE_TEXT, _) => continue,
+                frame => break frame,
+            }
+        };
+        assert_eq!(pong, (OPCODE_PONG, b"hi".to_vec()));
+
+        let status = 1000u16.to_be_bytes();
+        client
+            .write_all(&masked_frame(OPCODE_CLOSE, &status))
+            .expect("unreachable");
+        let close = loop {
+            match read_frame(&mut client) {
+                (OPCODE_TEXT, _) => continue,
+                frame => break frame,
+            }
+        };
+        assert_eq!(close, (OPCODE_CLOSE, status.to_vec()));
+        let mut byte = [0];
+        assert_eq!(client.read(&mut byte).expect("unreachable"), 0);
+    }
+}
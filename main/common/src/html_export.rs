@@ -0,0 +1,204 @@
+//! a self-contained html page drawing a setting in a canvas, for OBS browser sources,
+//! fed by the [`crate::event_server`] of a running overlay
+//!
+//! mouse motions are not exported, the event server only publishes keys
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    key::Key,
+    key_overlay_core::key_property::{KeyGroupProperty, KeyProperty},
+    setting::{EventServerSetting, Setting},
+    ucolor32::UColor32,
+};
+
+const TEMPLATE: &str = include_str!("html_export.html");
+const LAYOUT_PLACEHOLDER: &str = "/*LAYOUT*/null/*LAYOUT*/";
+const EVENT_SERVER_URL_PLACEHOLDER: &str = "/*EVENT_SERVER_URL*/\"\"";
+
+/// what the page draws, flattened so the page does not depend on the setting file format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Layout {
+    width: f32,
+    height: f32,
+    font_name: String,
+    background_color: UColor32,
+    keys: Vec<LayoutKey>,
+    groups: Vec<LayoutGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LayoutKey {
+    key: Key,
+    text: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    thickness: f32,
+    font_size: f32,
+    bar_speed: f32,
+    text_color: UColor32,
+    frame_color: UColor32,
+    pressed_color: UColor32,
+    fade_length: Option<f32>,
+    counter: Option<LayoutCounter>,
+    bar: LayoutBar,
+}
+
+/// the bars travel along `dx, dy` from `start` past `x, y`, level with the key center,
+/// see [`crate::key_overlay_core::key_property::BarAxes`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LayoutBar {
+    x: f32,
+    y: f32,
+    dx: f32,
+    dy: f32,
+    start: f32,
+    half_width: f32,
+    corner_radius: f32,
+    max_distance: f32,
+}
+
+/// relative to the center of the key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LayoutCounter {
+    x: f32,
+    y: f32,
+    font_size: f32,
+    text_color: UColor32,
+}
+
+/// counts come from the snapshots of the event server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LayoutGroup {
+    x: f32,
+    y: f32,
+    font_size: f32,
+    text_color: UColor32,
+}
+
+impl Layout {
+    /// laid out at the window size, see [`Setting::laid_out`]
+    fn new(setting: &Setting) -> Self {
+        let setting = &setting.laid_out(setting.window_setting.size());
+        Self {
+            width: setting.window_setting.width,
+            height: setting.window_setting.height,
+            font_name: setting.font_name.to_string(),
+            background_color: setting.background_color,
+            keys: setting
+                .key_properties
+                .iter()
+                .map(|key_property| LayoutKey::new(key_property, setting.window_setting.size()))
+                .collect(),
+            groups: setting.key_groups.iter().map(LayoutGroup::new).collect(),
+        }
+    }
+}
+
+impl LayoutKey {
+    fn new(key_property: &KeyProperty, window_size: egui::Vec2) -> Self {
+        let bar_axes = key_property.bar_axes();
+        Self {
+            key: key_property.key_bind,
+            text: key_property.key_text.clone(),
+            x: key_property.position.x,
+            y: key_property.position.y,
+            width: key_property.width,
+            height: key_property.height,
+            thickness: key_property.thickness,
+            font_size: key_property.font_size,
+            bar_speed: key_property.bar_speed,
+            text_color: key_property.text_color,
+            frame_color: key_property.frame_color,
+            pressed_color: key_property.pressed_color,
+            fade_length: key_property.fade_length.get().copied(),
+            counter: key_property.key_counter.get().map(|counter| LayoutCounter {
+                x: counter.position.x,
+                y: counter.position.y,
+                font_size: counter.font_size,
+                text_color: counter.text_color,
+            }),
+            bar: LayoutBar {
+                x: bar_axes.origin.x,
+                y: bar_axes.origin.y,
+                dx: bar_axes.direction.x,
+                dy: bar_axes.direction.y,
+                start: bar_axes.start,
+                half_width: bar_axes.half_width,
+                corner_radius: bar_axes.corner_radius,
+                max_distance: key_property.bar_max_distance(window_size),
+            },
+        }
+    }
+}
+
+impl LayoutGroup {
+    fn new(key_group_property: &KeyGroupProperty) -> Self {
+        Self {
+            x: key_group_property.position.x,
+            y: key_group_property.position.y,
+            font_size: key_group_property.font_size,
+            text_color: key_group_property.text_color,
+        }
+    }
+}
+
+/// the websocket url of the event server, a wildcard address is reached through localhost
+pub fn event_server_url(event_server_setting: &EventServerSetting) -> String {
+    let address = &event_server_setting.address;
+    let address = ["0.0.0.0:", "[::]:"]
+        .iter()
+        .find_map(|wildcard| address.strip_prefix(wildcard))
+        .map_or_else(|| address.clone(), |port| format!("127.0.0.1:{port}"));
+    format!("ws://{address}")
+}
+
+/// the page, `?server=ws://host:port` in its url overrides the event server
+pub fn export_html(setting: &Setting) -> String {
+    let layout = serde_json::to_string(&Layout::new(setting)).expect("unreachable");
+    let url = serde_json::to_string(&event_server_url(&setting.output_setting.event_server))
+        .expect("unreachable");
+    // a key text of `</script>` must not end the script
+    let escape = |json: String| json.replace("</", "<\\/");
+    TEMPLATE
+        .replace(LAYOUT_PLACEHOLDER, &escape(layout))
+        .replace(EVENT_SERVER_URL_PLACEHOLDER, &escape(url))
+}
+
+pub fn export_html_to_file(
+    setting: &Setting,
+    path: impl AsRef<std::path::Path>,
+) -> Result<(), &'static str> {
+    std::fs::write(path, export_html(setting)).map_err(|_| "无法写入文件")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_layout() {
+        let mut setting = Setting::default_26k();
+        setting.key_properties[0].key_text = "</script>".into();
+        let html = export_html(&setting);
+        assert!(!html.contains(LAYOUT_PLACEHOLDER));
+        assert!(!html.contains(EVENT_SERVER_URL_PLACEHOLDER));
+        assert!(!html.contains("</script>\""));
+
+        let begin = html.find("const LAYOUT = ").expect("unreachable") + "const LAYOUT = ".len();
+        let end = begin + html[begin..].find(";\n").expect("unreachable");
+        let layout: Layout = serde_json::from_str(&html[begin..end]).expect("unreachable");
+        assert_eq!(layout, Layout::new(&setting));
+        assert_eq!(layout.keys[0].text, "</script>");
+    }
+
+    #[test]
+    fn wildcard_event_server_url() {
+        let setting = EventServerSetting::default().with_address("0.0.0.0:23334");
+        assert_eq!(event_server_url(&setting), "ws://127.0.0.1:23334");
+        let setting = EventServerSetting::default().with_address("192.168.1.2:23334");
+        assert_eq!(event_server_url(&setting), "ws://192.168.1.2:23334");
+    }
+}
@@ -0,0 +1,432 @@
+//! imports the layouts of other key overlays, anything that can not be represented is reported
+//!
+//! - `config.txt` of the osu! KeyOverlay, an ini file with `[General]` and `[Colors]`
+//! - keyboard definitions of NohBoard, json with an `Elements` array
+
+use std::str::FromStr;
+
+use egui::pos2;
+use serde_json::Value;
+use windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
+
+use crate::{
+    key::Key,
+    key_overlay_core::key_property::{KeyCounterProperty, KeyProperty},
+    setting::{Setting, WindowSetting},
+    ucolor32::UColor32,
+};
+
+/// an imported setting and what was lost on the way
+#[derive(Debug)]
+pub struct Import {
+    pub setting: Setting,
+    pub warnings: Vec<String>,
+}
+
+/// picks the format by the extension, `.json` for NohBoard and ini for the rest
+pub fn import_file(path: impl AsRef<std::path::Path>) -> Result<Import, String> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(|err| format!("无法读取文件: {err}"))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    if is_json {
+        import_nohboard(&text)
+    } else {
+        import_key_overlay(&text)
+    }
+}
+
+/// `section.key` in lowercase to its value, in the order of the file
+fn parse_ini(text: &str) -> Vec<(String, String)> {
+    let mut section = String::new();
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', ';']))
+        .filter_map(|line| {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_lowercase();
+                return None;
+            }
+            let (key, value) = line.split_once('=')?;
+            Some((
+                format!("{section}.{}", key.trim().to_lowercase()),
+                value.trim().to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// `r,g,b` or `r,g,b,a`
+fn parse_color(s: &str) -> Option<UColor32> {
+    let channels: Vec<u8> = s
+        .split(',')
+        .map(|channel| channel.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match channels[..] {
+        [r, g, b] => Some(UColor32::from_rgb(r, g, b)),
+        [r, g, b, a] => Some(UColor32::new(r, g, b, a)),
+        _ => None,
+    }
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
+        "yes" | "true" | "1" => Some(true),
+        "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// SFML key names used by the osu! KeyOverlay, falls back to [`Key::from_str`]
+fn parse_sfml_key(s: &str) -> Option<Key> {
+    use Key::*;
+    let lowercase = s.trim().to_lowercase();
+    let digit = |rest: &str| -> Option<usize> { rest.parse().ok().filter(|d| *d <= 9) };
+    if let Some(d) = lowercase.strip_prefix("num").and_then(digit) {
+        return Some([Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9][d]);
+    }
+    if let Some(d) = lowercase.strip_prefix("numpad").and_then(digit) {
+        let numpad = [
+            Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8,
+            Numpad9,
+        ];
+        return Some(numpad[d]);
+    }
+    let key = match lowercase.as_str() {
+        "lcontrol" => LeftControl,
+        "rcontrol" => RightControl,
+        "lsystem" => LeftWin,
+        "rsystem" => RightWin,
+        "menu" => Apps,
+        "lbracket" => LeftSquareBracket,
+        "rbracket" => RightSquareBracket,
+        "semicolon" => Semicolon,
+        "quote" => Apostrophe,
+        "slash" => ForwardSlash,
+        "backslash" => BackwardSlash,
+        "tilde" | "grave" => BackTick,
+        "hyphen" | "dash" => Minus,
+        "equal" => Equal,
+        "return" => Enter,
+        "add" => NumpadPlus,
+        "subtract" => NumpadMinus,
+        "multiply" => NumpadMultiply,
+        "divide" => NumpadDivide,
+        "mleft" | "m1" => MouseLeft,
+        "mright" | "m2" => MouseRight,
+        "mmiddle" | "m3" => MouseMiddle,
+        _ => return Key::from_str(s).ok().filter(|key| *key != Unknown),
+    };
+    Some(key)
+}
+
+/// the keys are placed in a row at the bottom of the window, bars go up
+pub fn import_key_overlay(text: &str) -> Result<Import, String> {
+    let entries = parse_ini(text);
+    let mut warnings = vec![];
+    let get = |name: &str| {
+        entries
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let mut float = |name: &str, default: f32| {
+        get(name).map_or(default, |value| {
+            value.parse().unwrap_or_else(|_| {
+                warnings.push(format!("{name} 的值无效: {value}"));
+                default
+            })
+        })
+    };
+    let key_size = float("general.keysize", 70.0);
+    let bar_speed = float("general.barspeed", 600.0);
+    let margin = float("general.margin", 25.0);
+    let outline_thickness = float("general.outlinethickness", 5.0);
+    let window_height = float("general.height", WindowSetting::DEFAULT_HEIGHT);
+
+    let key_amount = get("general.keyamount")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or_else(|| {
+            (1..)
+                .take_while(|i| get(&format!("general.key{i}")).is_some())
+                .count()
+        });
+    if key_amount == 0 {
+        return Err("没有找到按键".into());
+    }
+    let fading = get("general.fading").and_then(parse_bool).unwrap_or(true);
+    let counter = get("general.counter")
+        .or_else(|| get("general.keycounter"))
+        .and_then(parse_bool)
+        .unwrap_or(true);
+    let background_color = get("general.backgroundcolor")
+        .and_then(parse_color)
+        .unwrap_or(UColor32::TRANSPARENT);
+
+    let key_properties: Vec<_> = (1..=key_amount)
+        .map(|i| {
+            let name = get(&format!("general.key{i}")).unwrap_or_default();
+            let key = parse_sfml_key(name).unwrap_or_else(|| {
+                warnings.push(format!("无法识别按键 key{i}={name}"));
+                Key::Unknown
+            });
+            let key_text = get(&format!("general.displaykey{i}"))
+                .filter(|text| !text.is_empty())
+                .map_or_else(|| key.label().to_string(), str::to_string);
+            let color = get(&format!("colors.key{i}"))
+                .or_else(|| get(&format!("colors.key{i}color")))
+                .and_then(parse_color)
+                .unwrap_or(UColor32::WHITE);
+            let x = margin + (key_size + margin) * (i - 1) as f32;
+            let y = window_height - margin - key_size;
+            KeyProperty::default()
+                .with_key_bind(key)
+                .with_key_text(key_text)
+                .with_position(pos2(x, y))
+                .with_width(key_size)
+                .with_height(key_size)
+                .with_thickness(outline_thickness)
+                .with_font_size(key_size * 0.4)
+                .with_bar_speed(bar_speed)
+                .with_frame_color(color)
+                .with_pressed_color(color)
+                .with_fade_length(fading.then_some(KeyProperty::DEFAULT_FADE_LENGTH.value))
+                .with_key_counter(counter.then(|| {
+                    KeyCounterProperty::default()
+                        .with_position(pos2(0.0, -key_size))
+                        .with_font_size(key_size * 0.3)
+                }))
+        })
+        .collect();
+
+    entries
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .filter(|key| !is_known_key_overlay_entry(key))
+        .for_each(|key| warnings.push(format!("不支持的设置: {key}")));
+
+    let setting = Setting {
+        window_setting: WindowSetting::default()
+            .with_width(margin + (key_size + margin) * key_amount as f32)
+            .with_height(window_height),
+        background_color,
+        key_properties,
+        ..Setting::default_zxc()
+    };
+    Ok(Import { setting, warnings })
+}
+
+fn is_known_key_overlay_entry(key: &str) -> bool {
+    let indexed = |prefix: &str, suffix: &str| {
+        key.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(suffix))
+            .is_some_and(|index| index.parse::<usize>().is_ok())
+    };
+    matches!(
+        key,
+        "general.keysize"
+            | "general.barspeed"
+            | "general.margin"
+            | "general.outlinethickness"
+            | "general.height"
+            | "general.keyamount"
+            | "general.fading"
+            | "general.counter"
+            | "general.keycounter"
+            | "general.backgroundcolor"
+    ) || indexed("general.key", "")
+        || indexed("general.displaykey", "")
+        || indexed("colors.key", "")
+        || indexed("colors.key", "color")
+}
+
+/// keyboard and mouse keys are imported by their bounding box, styles are not imported
+pub fn import_nohboard(text: &str) -> Result<Import, String> {
+    let definition: Value = serde_json::from_str(text).map_err(|err| format!("格式错误: {err}"))?;
+    let elements = definition
+        .get("Elements")
+        .and_then(Value::as_array)
+        .ok_or("格式错误: 没有 `Elements` 数组")?;
+    let mut warnings = vec!["NohBoard 的样式保存在单独的文件中，颜色未导入".to_string()];
+
+    let key_properties: Vec<_> = elements
+        .iter()
+        .filter_map(|element| {
+            let element_type = element.get("__type").and_then(Value::as_str)?;
+            let codes: Vec<u64> = element
+                .get("KeyCodes")
+                .and_then(Value::as_array)
+                .map(|codes| codes.iter().filter_map(Value::as_u64).collect())
+                .unwrap_or_default();
+            let text = ["ShiftText", "Text"]
+                .into_iter()
+                .filter(|_| element.get("ChangeOnCaps").and_then(Value::as_bool) == Some(true))
+                .chain(["Text"])
+                .find_map(|field| element.get(field).and_then(Value::as_str))
+                .unwrap_or_default()
+                .to_string();
+            let key = match (element_type, codes.first()) {
+                ("KeyboardKeyDefinition", Some(&code)) if code < 0x100 => {
+                    Key::from_virtual_key(VIRTUAL_KEY(code as u16), false)
+                }
+                ("MouseKeyDefinition", Some(&code)) => [
+                    Key::MouseLeft,
+                    Key::MouseMiddle,
+                    Key::MouseRight,
+                    Key::MouseX1,
+                    Key::MouseX2,
+                ]
+                .get(code as usize)
+                .copied()
+                .unwrap_or_default(),
+                ("MouseScrollDefinition", Some(&code)) => [
+                    Key::WheelUp,
+                    Key::WheelDown,
+                    Key::WheelRight,
+                    Key::WheelLeft,
+                ]
+                .get(code as usize)
+                .copied()
+                .unwrap_or_default(),
+                _ => {
+                    warnings.push(format!("不支持的元素: {element_type} {text}"));
+                    return None;
+                }
+            };
+            if key == Key::Unknown {
+                warnings.push(format!("无法识别按键 {text} {codes:?}"));
+            }
+            (codes.len() > 1).then(|| {
+                warnings.push(format!("{text} 绑定了多个按键，只导入了第一个"));
+            });
+
+            let points: Vec<(f32, f32)> = element
+                .get("Boundaries")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|point| {
+                    let coordinate = |axis| point.get(axis).and_then(Value::as_f64);
+                    Some((coordinate("X")? as f32, coordinate("Y")? as f32))
+                })
+                .collect();
+            let (min, max) = points.iter().fold(
+                (pos2(f32::MAX, f32::MAX), pos2(f32::MIN, f32::MIN)),
+                |(min, max), &(x, y)| (min.min(pos2(x, y)), max.max(pos2(x, y))),
+            );
+            if points.len() > 4 {
+                warnings.push(format!("{text} 不是矩形，使用了它的外接矩形"));
+            }
+            if points.is_empty() {
+                warnings.push(format!("{text} 没有边界，已跳过"));
+                return None;
+            }
+            let size = max - min;
+            Some(
+                KeyProperty::default()
+                    .with_key_bind(key)
+                    .with_key_text(text)
+                    .with_position(min)
+                    .with_width(size.x)
+                    .with_height(size.y)
+                    .with_font_size((size.y * 0.4).max(1.0)),
+            )
+        })
+        .collect();
+    if key_properties.is_empty() {
+        return Err("没有找到按键".into());
+    }
+
+    let dimension = |field, default| {
+        definition
+            .get(field)
+            .and_then(Value::as_f64)
+            .map_or(default, |value| value as f32)
+    };
+    let setting = Setting {
+        window_setting: WindowSetting::default()
+            .with_width(dimension("Width", WindowSetting::DEFAULT_WIDTH))
+            .with_height(dimension("Height", WindowSetting::DEFAULT_HEIGHT)),
+        key_properties,
+        ..Setting::default_zxc()
+    };
+    Ok(Import { setting, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_overlay_config() {
+        let config = "\
+[General]
+keyAmount=2
+key1=Z
+key2=Num1
+displayKey1=
+displayKey2=一
+keySize=70
+barSpeed=600
+backgroundColor=0,0,0,255
+margin=25
+outlineThickness=5
+fading=yes
+counter=no
+fps=60
+[Colors]
+key1=255,0,0,255
+key2=0,0,255
+";
+        let Import { setting, warnings } = import_key_overlay(config).expect("unreachable");
+        assert_eq!(warnings, ["不支持的设置: general.fps"]);
+        assert_eq!(setting.window_setting.width, 215.0);
+        assert_eq!(setting.background_color, UColor32::BLACK);
+        let [z, one] = &setting.key_properties[..] else {
+            panic!("{:?}", setting.key_properties);
+        };
+        assert_eq!((z.key_bind, z.key_text.as_str()), (Key::KeyZ, "Z"));
+        assert_eq!((one.key_bind, one.key_text.as_str()), (Key::Key1, "一"));
+        assert_eq!(z.frame_color, UColor32::RED);
+        assert_eq!(one.pressed_color, UColor32::BLUE);
+        assert_eq!(one.position.x, 120.0);
+        assert_eq!(z.bar_speed, 600.0);
+        assert!(z.fade_length.enabled);
+        assert!(!z.key_counter.enabled);
+    }
+
+    #[test]
+    fn nohboard_definition() {
+        let definition = r#"{
+            "Width": 300, "Height": 100,
+            "Elements": [
+                {
+                    "__type": "KeyboardKeyDefinition",
+                    "Boundaries": [{"X": 10, "Y": 10}, {"X": 60, "Y": 10}, {"X": 60, "Y": 60}, {"X": 10, "Y": 60}],
+                    "KeyCodes": [81], "Text": "q", "ShiftText": "Q", "ChangeOnCaps": true
+                },
+                {
+                    "__type": "MouseKeyDefinition",
+                    "Boundaries": [{"X": 70, "Y": 10}, {"X": 120, "Y": 10}, {"X": 120, "Y": 60}, {"X": 70, "Y": 60}],
+                    "KeyCodes": [2], "Text": "RMB"
+                },
+                { "__type": "MouseSpeedIndicatorDefinition", "Text": "" }
+            ]
+        }"#;
+        let Import { setting, warnings } = import_nohboard(definition).expect("unreachable");
+        assert_eq!(setting.window_setting.width, 300.0);
+        let [q, right] = &setting.key_properties[..] else {
+            panic!("{:?}", setting.key_properties);
+        };
+        assert_eq!((q.key_bind, q.key_text.as_str()), (Key::KeyQ, "Q"));
+        assert_eq!((q.position, q.width), (pos2(10.0, 10.0), 50.0));
+        assert_eq!(right.key_bind, Key::MouseRight);
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.contains("MouseSpeedIndicatorDefinition"))
+        );
+    }
+}
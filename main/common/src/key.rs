@@ -1,8 +1,12 @@
+use std::{str::FromStr, sync::OnceLock};
+
 use serde::{Deserialize, Serialize};
 use windows::Win32::UI::Input::KeyboardAndMouse::{self, VIRTUAL_KEY};
 
 /// [see also](https://docs.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes)
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize, schemars::JsonSchema,
+)]
 pub enum Key {
     Escape,
     F1,
@@ -131,6 +135,52 @@ pub enum Key {
     MouseMiddle,
     MouseX1,
     MouseX2,
+    /// one notch forward, see [`Key::is_wheel`]
+    WheelUp,
+    /// one notch backward, see [`Key::is_wheel`]
+    WheelDown,
+    /// one notch tilted left, see [`Key::is_wheel`]
+    WheelLeft,
+    /// one notch tilted right, see [`Key::is_wheel`]
+    WheelRight,
+
+    // Extended keys, appended to keep the order of `Key::iter`
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaNextTrack,
+    MediaPrevTrack,
+    MediaStop,
+    MediaPlayPause,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    /// `カタカナ/ひらがな` key on a Japanese keyboard
+    Kana,
+    /// `한/영` key on a Korean keyboard, shares the virtual key with [`Key::Kana`]
+    Hangul,
+    /// `変換` key on a Japanese keyboard
+    Convert,
+    /// `無変換` key on a Japanese keyboard
+    NonConvert,
+    /// the key between left shift and `Z` on an ISO keyboard
+    IntlBackslash,
 
     #[default]
     Unknown,
@@ -282,6 +332,50 @@ impl Key {
             lut[VK_XBUTTON1.0 as usize] = MouseX1;
             lut[VK_XBUTTON2.0 as usize] = MouseX2;
 
+            lut[VK_F13.0 as usize] = F13;
+            lut[VK_F14.0 as usize] = F14;
+            lut[VK_F15.0 as usize] = F15;
+            lut[VK_F16.0 as usize] = F16;
+            lut[VK_F17.0 as usize] = F17;
+            lut[VK_F18.0 as usize] = F18;
+            lut[VK_F19.0 as usize] = F19;
+            lut[VK_F20.0 as usize] = F20;
+            lut[VK_F21.0 as usize] = F21;
+            lut[VK_F22.0 as usize] = F22;
+            lut[VK_F23.0 as usize] = F23;
+            lut[VK_F24.0 as usize] = F24;
+
+            // media and browser keys are usually extended
+            let media_and_browser_keys = [
+                (VK_VOLUME_MUTE, VolumeMute),
+                (VK_VOLUME_DOWN, VolumeDown),
+                (VK_VOLUME_UP, VolumeUp),
+                (VK_MEDIA_NEXT_TRACK, MediaNextTrack),
+                (VK_MEDIA_PREV_TRACK, MediaPrevTrack),
+                (VK_MEDIA_STOP, MediaStop),
+                (VK_MEDIA_PLAY_PAUSE, MediaPlayPause),
+                (VK_BROWSER_BACK, BrowserBack),
+                (VK_BROWSER_FORWARD, BrowserForward),
+                (VK_BROWSER_REFRESH, BrowserRefresh),
+                (VK_BROWSER_STOP, BrowserStop),
+                (VK_BROWSER_SEARCH, BrowserSearch),
+                (VK_BROWSER_FAVORITES, BrowserFavorites),
+                (VK_BROWSER_HOME, BrowserHome),
+            ];
+            let mut i = 0;
+            while i < media_and_browser_keys.len() {
+                let (virtual_key, key) = media_and_browser_keys[i];
+                lut[virtual_key.0 as usize] = key;
+                lut[virtual_key.0 as usize + EXT] = key;
+                i += 1;
+            }
+
+            // `VK_HANGUL` has the same value, `msg_hook` tells them apart by the scan code
+            lut[VK_KANA.0 as usize] = Kana;
+            lut[VK_CONVERT.0 as usize] = Convert;
+            lut[VK_NONCONVERT.0 as usize] = NonConvert;
+            lut[VK_OEM_102.0 as usize] = IntlBackslash;
+
             lut
         };
         let index = virtual_key.0 as usize + ((is_extend as usize) << 8);
@@ -405,13 +499,648 @@ impl Key {
             MouseX1 => VK_XBUTTON1,
             MouseX2 => VK_XBUTTON2,
 
-            Unknown => Default::default(),
+            F13 => VK_F13,
+            F14 => VK_F14,
+            F15 => VK_F15,
+            F16 => VK_F16,
+            F17 => VK_F17,
+            F18 => VK_F18,
+            F19 => VK_F19,
+            F20 => VK_F20,
+            F21 => VK_F21,
+            F22 => VK_F22,
+            F23 => VK_F23,
+            F24 => VK_F24,
+            VolumeMute => VK_VOLUME_MUTE,
+            VolumeDown => VK_VOLUME_DOWN,
+            VolumeUp => VK_VOLUME_UP,
+            MediaNextTrack => VK_MEDIA_NEXT_TRACK,
+            MediaPrevTrack => VK_MEDIA_PREV_TRACK,
+            MediaStop => VK_MEDIA_STOP,
+            MediaPlayPause => VK_MEDIA_PLAY_PAUSE,
+            BrowserBack => VK_BROWSER_BACK,
+            BrowserForward => VK_BROWSER_FORWARD,
+            BrowserRefresh => VK_BROWSER_REFRESH,
+            BrowserStop => VK_BROWSER_STOP,
+            BrowserSearch => VK_BROWSER_SEARCH,
+            BrowserFavorites => VK_BROWSER_FAVORITES,
+            BrowserHome => VK_BROWSER_HOME,
+            Kana => VK_KANA,
+            Hangul => VK_HANGUL,
+            Convert => VK_CONVERT,
+            NonConvert => VK_NONCONVERT,
+            IntlBackslash => VK_OEM_102,
+
+            WheelUp | WheelDown | WheelLeft | WheelRight | Unknown => Default::default(),
         }
     }
 
+    /// `(key, set 1 scan code, HID usage)`, extended scan codes are prefixed with `0xE0`,
+    /// HID usages are `page << 16 | id`, named after the key at the same position on an US layout
+    const PHYSICAL_KEY_TABLE: [(Key, u16, u32); 136] = {
+        use Key::*;
+        const KEYBOARD: u32 = 0x07 << 16;
+        const CONSUMER: u32 = 0x0C << 16;
+        [
+            (Escape, 0x01, KEYBOARD | 0x29),
+            (F1, 0x3B, KEYBOARD | 0x3A),
+            (F2, 0x3C, KEYBOARD | 0x3B),
+            (F3, 0x3D, KEYBOARD | 0x3C),
+            (F4, 0x3E, KEYBOARD | 0x3D),
+            (F5, 0x3F, KEYBOARD | 0x3E),
+            (F6, 0x40, KEYBOARD | 0x3F),
+            (F7, 0x41, KEYBOARD | 0x40),
+            (F8, 0x42, KEYBOARD | 0x41),
+            (F9, 0x43, KEYBOARD | 0x42),
+            (F10, 0x44, KEYBOARD | 0x43),
+            (F11, 0x57, KEYBOARD | 0x44),
+            (F12, 0x58, KEYBOARD | 0x45),
+            (PrintScreen, 0xE037, KEYBOARD | 0x46),
+            (ScrollLock, 0x46, KEYBOARD | 0x47),
+            (Pause, 0xE11D, KEYBOARD | 0x48),
+            (BackTick, 0x29, KEYBOARD | 0x35),
+            (Key1, 0x02, KEYBOARD | 0x1E),
+            (Key2, 0x03, KEYBOARD | 0x1F),
+            (Key3, 0x04, KEYBOARD | 0x20),
+            (Key4, 0x05, KEYBOARD | 0x21),
+            (Key5, 0x06, KEYBOARD | 0x22),
+            (Key6, 0x07, KEYBOARD | 0x23),
+            (Key7, 0x08, KEYBOARD | 0x24),
+            (Key8, 0x09, KEYBOARD | 0x25),
+            (Key9, 0x0A, KEYBOARD | 0x26),
+            (Key0, 0x0B, KEYBOARD | 0x27),
+            (Minus, 0x0C, KEYBOARD | 0x2D),
+            (Equal, 0x0D, KEYBOARD | 0x2E),
+            (Backspace, 0x0E, KEYBOARD | 0x2A),
+            (Insert, 0xE052, KEYBOARD | 0x49),
+            (Delete, 0xE053, KEYBOARD | 0x4C),
+            (Home, 0xE047, KEYBOARD | 0x4A),
+            (End, 0xE04F, KEYBOARD | 0x4D),
+            (PageUp, 0xE049, KEYBOARD | 0x4B),
+            (PageDown, 0xE051, KEYBOARD | 0x4E),
+            (KeyA, 0x1E, KEYBOARD | 0x04),
+            (KeyB, 0x30, KEYBOARD | 0x05),
+            (KeyC, 0x2E, KEYBOARD | 0x06),
+            (KeyD, 0x20, KEYBOARD | 0x07),
+            (KeyE, 0x12, KEYBOARD | 0x08),
+            (KeyF, 0x21, KEYBOARD | 0x09),
+            (KeyG, 0x22, KEYBOARD | 0x0A),
+            (KeyH, 0x23, KEYBOARD | 0x0B),
+            (KeyI, 0x17, KEYBOARD | 0x0C),
+            (KeyJ, 0x24, KEYBOARD | 0x0D),
+            (KeyK, 0x25, KEYBOARD | 0x0E),
+            (KeyL, 0x26, KEYBOARD | 0x0F),
+            (KeyM, 0x32, KEYBOARD | 0x10),
+            (KeyN, 0x31, KEYBOARD | 0x11),
+            (KeyO, 0x18, KEYBOARD | 0x12),
+            (KeyP, 0x19, KEYBOARD | 0x13),
+            (KeyQ, 0x10, KEYBOARD | 0x14),
+            (KeyR, 0x13, KEYBOARD | 0x15),
+            (KeyS, 0x1F, KEYBOARD | 0x16),
+            (KeyT, 0x14, KEYBOARD | 0x17),
+            (KeyU, 0x16, KEYBOARD | 0x18),
+            (KeyV, 0x2F, KEYBOARD | 0x19),
+            (KeyW, 0x11, KEYBOARD | 0x1A),
+            (KeyX, 0x2D, KEYBOARD | 0x1B),
+            (KeyY, 0x15, KEYBOARD | 0x1C),
+            (KeyZ, 0x2C, KEYBOARD | 0x1D),
+            (LeftSquareBracket, 0x1A, KEYBOARD | 0x2F),
+            (RightSquareBracket, 0x1B, KEYBOARD | 0x30),
+            (BackwardSlash, 0x2B, KEYBOARD | 0x31),
+            (Semicolon, 0x27, KEYBOARD | 0x33),
+            (Apostrophe, 0x28, KEYBOARD | 0x34),
+            (Comma, 0x33, KEYBOARD | 0x36),
+            (Period, 0x34, KEYBOARD | 0x37),
+            (ForwardSlash, 0x35, KEYBOARD | 0x38),
+            (Enter, 0x1C, KEYBOARD | 0x28),
+            (Space, 0x39, KEYBOARD | 0x2C),
+            (LeftControl, 0x1D, KEYBOARD | 0xE0),
+            (RightControl, 0xE01D, KEYBOARD | 0xE4),
+            (LeftShift, 0x2A, KEYBOARD | 0xE1),
+            (RightShift, 0x36, KEYBOARD | 0xE5),
+            (LeftAlt, 0x38, KEYBOARD | 0xE2),
+            (RightAlt, 0xE038, KEYBOARD | 0xE6),
+            (LeftWin, 0xE05B, KEYBOARD | 0xE3),
+            (RightWin, 0xE05C, KEYBOARD | 0xE7),
+            (Apps, 0xE05D, KEYBOARD | 0x65),
+            (Tab, 0x0F, KEYBOARD | 0x2B),
+            (CapsLock, 0x3A, KEYBOARD | 0x39),
+            (Up, 0xE048, KEYBOARD | 0x52),
+            (Down, 0xE050, KEYBOARD | 0x51),
+            (Left, 0xE04B, KEYBOARD | 0x50),
+            (Right, 0xE04D, KEYBOARD | 0x4F),
+            (NumLock, 0x45, KEYBOARD | 0x53),
+            (Numpad1, 0x4F, KEYBOARD | 0x59),
+            (Numpad2, 0x50, KEYBOARD | 0x5A),
+            (Numpad3, 0x51, KEYBOARD | 0x5B),
+            (Numpad4, 0x4B, KEYBOARD | 0x5C),
+            (Numpad5, 0x4C, KEYBOARD | 0x5D),
+            (Numpad6, 0x4D, KEYBOARD | 0x5E),
+            (Numpad7, 0x47, KEYBOARD | 0x5F),
+            (Numpad8, 0x48, KEYBOARD | 0x60),
+            (Numpad9, 0x49, KEYBOARD | 0x61),
+            (Numpad0, 0x52, KEYBOARD | 0x62),
+            (NumpadPlus, 0x4E, KEYBOARD | 0x57),
+            (NumpadMinus, 0x4A, KEYBOARD | 0x56),
+            (NumpadMultiply, 0x37, KEYBOARD | 0x55),
+            (NumpadDivide, 0xE035, KEYBOARD | 0x54),
+            (NumpadSeparator, 0x7E, KEYBOARD | 0x85),
+            (NumpadDot, 0x53, KEYBOARD | 0x63),
+            (NumpadEnter, 0xE01C, KEYBOARD | 0x58),
+            (F13, 0x64, KEYBOARD | 0x68),
+            (F14, 0x65, KEYBOARD | 0x69),
+            (F15, 0x66, KEYBOARD | 0x6A),
+            (F16, 0x67, KEYBOARD | 0x6B),
+            (F17, 0x68, KEYBOARD | 0x6C),
+            (F18, 0x69, KEYBOARD | 0x6D),
+            (F19, 0x6A, KEYBOARD | 0x6E),
+            (F20, 0x6B, KEYBOARD | 0x6F),
+            (F21, 0x6C, KEYBOARD | 0x70),
+            (F22, 0x6D, KEYBOARD | 0x71),
+            (F23, 0x6E, KEYBOARD | 0x72),
+            (F24, 0x76, KEYBOARD | 0x73),
+            (VolumeMute, 0xE020, CONSUMER | 0xE2),
+            (VolumeDown, 0xE02E, CONSUMER | 0xEA),
+            (VolumeUp, 0xE030, CONSUMER | 0xE9),
+            (MediaNextTrack, 0xE019, CONSUMER | 0xB5),
+            (MediaPrevTrack, 0xE010, CONSUMER | 0xB6),
+            (MediaStop, 0xE024, CONSUMER | 0xB7),
+            (MediaPlayPause, 0xE022, CONSUMER | 0xCD),
+            (BrowserBack, 0xE06A, CONSUMER | 0x224),
+            (BrowserForward, 0xE069, CONSUMER | 0x225),
+            (BrowserRefresh, 0xE067, CONSUMER | 0x227),
+            (BrowserStop, 0xE068, CONSUMER | 0x226),
+            (BrowserSearch, 0xE065, CONSUMER | 0x221),
+            (BrowserFavorites, 0xE066, CONSUMER | 0x22A),
+            (BrowserHome, 0xE032, CONSUMER | 0x223),
+            (Kana, 0x70, KEYBOARD | 0x88),
+            (Hangul, 0x72, KEYBOARD | 0x90),
+            (Convert, 0x79, KEYBOARD | 0x8A),
+            (NonConvert, 0x7B, KEYBOARD | 0x8B),
+            (IntlBackslash, 0x56, KEYBOARD | 0x64),
+        ]
+    };
+
+    /// the key at this physical position regardless of the keyboard layout,
+    /// `make_code` is the set 1 scan code reported by raw input
+    pub fn from_scan_code(make_code: u16, has_e0: bool) -> Self {
+        const LUT: [Key; 0x200] = {
+            const EXT: usize = 0x100;
+            let mut lut = [Key::Unknown; 0x200];
+            let mut i = 0;
+            while i < Key::PHYSICAL_KEY_TABLE.len() {
+                let (key, scan_code, _) = Key::PHYSICAL_KEY_TABLE[i];
+                let index = (scan_code & 0xFF) as usize;
+                match scan_code >> 8 {
+                    0x00 => lut[index] = key,
+                    0xE0 => lut[index + EXT] = key,
+                    // `Pause` is reported with an `0xE1` prefix, see `msg_hook`
+                    _ => (),
+                }
+                i += 1;
+            }
+            lut
+        };
+        let index = (make_code & 0xFF) as usize + ((has_e0 as usize) << 8);
+        unsafe { *LUT.get_unchecked(index) }
+    }
+
+    /// set 1 scan code, extended ones are prefixed with `0xE0`,
+    /// `None` for mouse and wheel keys
+    #[cfg(test)]
+    pub fn to_scan_code(self) -> Option<u16> {
+        Self::PHYSICAL_KEY_TABLE
+            .iter()
+            .find(|(key, ..)| *key == self)
+            .map(|&(_, scan_code, _)| scan_code)
+    }
+
+    /// `usage` is `page << 16 | id`, keys are on the keyboard/keypad page (`0x07`)
+    /// or the consumer page (`0x0C`)
+    #[cfg(test)]
+    pub fn from_hid_usage(usage: u32) -> Self {
+        Self::PHYSICAL_KEY_TABLE
+            .iter()
+            .find(|(_, _, hid_usage)| *hid_usage == usage)
+            .map_or(Self::Unknown, |&(key, ..)| key)
+    }
+
+    /// `page << 16 | id`, see [`Key::from_hid_usage`], `None` for mouse and wheel keys
+    #[cfg(test)]
+    pub fn to_hid_usage(self) -> Option<u32> {
+        Self::PHYSICAL_KEY_TABLE
+            .iter()
+            .find(|(key, ..)| *key == self)
+            .map(|&(.., hid_usage)| hid_usage)
+    }
+
+    /// wheel keys have no physical release, each notch is a press followed by a release
+    /// after `InputSetting::wheel_press_duration_ms`
+    #[inline]
+    pub const fn is_wheel(self) -> bool {
+        matches!(
+            self,
+            Self::WheelUp | Self::WheelDown | Self::WheelLeft | Self::WheelRight
+        )
+    }
+
     pub fn iter() -> impl DoubleEndedIterator<Item = Self> + Clone {
         (0..=Self::LAST_KEY as u8).map(|v| unsafe { std::mem::transmute::<u8, Self>(v) })
     }
+
+    /// short label, the default `key_text` of a newly bound key
+    pub fn label(self) -> &'static str {
+        use Key::*;
+        match self {
+            Escape => "Esc",
+            F1 => "F1",
+            F2 => "F2",
+            F3 => "F3",
+            F4 => "F4",
+            F5 => "F5",
+            F6 => "F6",
+            F7 => "F7",
+            F8 => "F8",
+            F9 => "F9",
+            F10 => "F10",
+            F11 => "F11",
+            F12 => "F12",
+            PrintScreen => "PrtSc",
+            ScrollLock => "ScrLk",
+            Pause => "Pause",
+            BackTick => "`",
+            Key1 => "1",
+            Key2 => "2",
+            Key3 => "3",
+            Key4 => "4",
+            Key5 => "5",
+            Key6 => "6",
+            Key7 => "7",
+            Key8 => "8",
+            Key9 => "9",
+            Key0 => "0",
+            Minus => "-",
+            Equal => "=",
+            Backspace => "Bksp",
+            Insert => "Ins",
+            Delete => "Del",
+            Home => "Home",
+            End => "End",
+            PageUp => "PgUp",
+            PageDown => "PgDn",
+            KeyA => "A",
+            KeyB => "B",
+            KeyC => "C",
+            KeyD => "D",
+            KeyE => "E",
+            KeyF => "F",
+            KeyG => "G",
+            KeyH => "H",
+            KeyI => "I",
+            KeyJ => "J",
+            KeyK => "K",
+            KeyL => "L",
+            KeyM => "M",
+            KeyN => "N",
+            KeyO => "O",
+            KeyP => "P",
+            KeyQ => "Q",
+            KeyR => "R",
+            KeyS => "S",
+            KeyT => "T",
+            KeyU => "U",
+            KeyV => "V",
+            KeyW => "W",
+            KeyX => "X",
+            KeyY => "Y",
+            KeyZ => "Z",
+            LeftSquareBracket => "[",
+            RightSquareBracket => "]",
+            BackwardSlash => "\\",
+            Semicolon => ";",
+            Apostrophe => "'",
+            Comma => ",",
+            Period => ".",
+            ForwardSlash => "/",
+            Enter => "Enter",
+            Space => "Space",
+            LeftControl => "LCtrl",
+            RightControl => "RCtrl",
+            LeftShift => "LShift",
+            RightShift => "RShift",
+            LeftAlt => "LAlt",
+            RightAlt => "RAlt",
+            LeftWin => "LWin",
+            RightWin => "RWin",
+            Apps => "Menu",
+            Tab => "Tab",
+            CapsLock => "Caps",
+            Up => "↑",
+            Down => "↓",
+            Left => "←",
+            Right => "→",
+            NumLock => "NumLk",
+            Numpad1 => "Num1",
+            Numpad2 => "Num2",
+            Numpad3 => "Num3",
+            Numpad4 => "Num4",
+            Numpad5 => "Num5",
+            Numpad6 => "Num6",
+            Numpad7 => "Num7",
+            Numpad8 => "Num8",
+            Numpad9 => "Num9",
+            Numpad0 => "Num0",
+            NumpadPlus => "Num+",
+            NumpadMinus => "Num-",
+            NumpadMultiply => "Num*",
+            NumpadDivide => "Num/",
+            NumpadSeparator => "NumSep",
+            NumpadDot => "Num.",
+            NumpadEnter => "NumEnter",
+            MouseLeft => "LMB",
+            MouseRight => "RMB",
+            MouseMiddle => "MMB",
+            MouseX1 => "M4",
+            MouseX2 => "M5",
+            WheelUp => "W↑",
+            WheelDown => "W↓",
+            WheelLeft => "W←",
+            WheelRight => "W→",
+            F13 => "F13",
+            F14 => "F14",
+            F15 => "F15",
+            F16 => "F16",
+            F17 => "F17",
+            F18 => "F18",
+            F19 => "F19",
+            F20 => "F20",
+            F21 => "F21",
+            F22 => "F22",
+            F23 => "F23",
+            F24 => "F24",
+            VolumeMute => "Mute",
+            VolumeDown => "Vol-",
+            VolumeUp => "Vol+",
+            MediaNextTrack => "Next",
+            MediaPrevTrack => "Prev",
+            MediaStop => "Stop",
+            MediaPlayPause => "Play",
+            BrowserBack => "WebBack",
+            BrowserForward => "WebFwd",
+            BrowserRefresh => "WebRefresh",
+            BrowserStop => "WebStop",
+            BrowserSearch => "WebSearch",
+            BrowserFavorites => "WebFav",
+            BrowserHome => "WebHome",
+            Kana => "かな",
+            Hangul => "한/영",
+            Convert => "変換",
+            NonConvert => "無変換",
+            IntlBackslash => "<>",
+            Unknown => "?",
+        }
+    }
+
+    /// localized name shown in the editor
+    pub fn long_name(self) -> &'static str {
+        use Key::*;
+        match self {
+            Escape => "Esc 键",
+            F1 => "F1 键",
+            F2 => "F2 键",
+            F3 => "F3 键",
+            F4 => "F4 键",
+            F5 => "F5 键",
+            F6 => "F6 键",
+            F7 => "F7 键",
+            F8 => "F8 键",
+            F9 => "F9 键",
+            F10 => "F10 键",
+            F11 => "F11 键",
+            F12 => "F12 键",
+            PrintScreen => "打印屏幕键",
+            ScrollLock => "滚动锁定键",
+            Pause => "暂停键",
+            BackTick => "反引号键",
+            Key1 => "数字 1 键",
+            Key2 => "数字 2 键",
+            Key3 => "数字 3 键",
+            Key4 => "数字 4 键",
+            Key5 => "数字 5 键",
+            Key6 => "数字 6 键",
+            Key7 => "数字 7 键",
+            Key8 => "数字 8 键",
+            Key9 => "数字 9 键",
+            Key0 => "数字 0 键",
+            Minus => "减号键",
+            Equal => "等号键",
+            Backspace => "退格键",
+            Insert => "插入键",
+            Delete => "删除键",
+            Home => "Home 键",
+            End => "End 键",
+            PageUp => "向上翻页键",
+            PageDown => "向下翻页键",
+            KeyA => "A 键",
+            KeyB => "B 键",
+            KeyC => "C 键",
+            KeyD => "D 键",
+            KeyE => "E 键",
+            KeyF => "F 键",
+            KeyG => "G 键",
+            KeyH => "H 键",
+            KeyI => "I 键",
+            KeyJ => "J 键",
+            KeyK => "K 键",
+            KeyL => "L 键",
+            KeyM => "M 键",
+            KeyN => "N 键",
+            KeyO => "O 键",
+            KeyP => "P 键",
+            KeyQ => "Q 键",
+            KeyR => "R 键",
+            KeyS => "S 键",
+            KeyT => "T 键",
+            KeyU => "U 键",
+            KeyV => "V 键",
+            KeyW => "W 键",
+            KeyX => "X 键",
+            KeyY => "Y 键",
+            KeyZ => "Z 键",
+            LeftSquareBracket => "左方括号键",
+            RightSquareBracket => "右方括号键",
+            BackwardSlash => "反斜杠键",
+            Semicolon => "分号键",
+            Apostrophe => "引号键",
+            Comma => "逗号键",
+            Period => "句号键",
+            ForwardSlash => "斜杠键",
+            Enter => "回车键",
+            Space => "空格键",
+            LeftControl => "左 Ctrl 键",
+            RightControl => "右 Ctrl 键",
+            LeftShift => "左 Shift 键",
+            RightShift => "右 Shift 键",
+            LeftAlt => "左 Alt 键",
+            RightAlt => "右 Alt 键",
+            LeftWin => "左 Win 键",
+            RightWin => "右 Win 键",
+            Apps => "菜单键",
+            Tab => "制表键",
+            CapsLock => "大写锁定键",
+            Up => "上方向键",
+            Down => "下方向键",
+            Left => "左方向键",
+            Right => "右方向键",
+            NumLock => "数字锁定键",
+            Numpad1 => "数字键盘 1 键",
+            Numpad2 => "数字键盘 2 键",
+            Numpad3 => "数字键盘 3 键",
+            Numpad4 => "数字键盘 4 键",
+            Numpad5 => "数字键盘 5 键",
+            Numpad6 => "数字键盘 6 键",
+            Numpad7 => "数字键盘 7 键",
+            Numpad8 => "数字键盘 8 键",
+            Numpad9 => "数字键盘 9 键",
+            Numpad0 => "数字键盘 0 键",
+            NumpadPlus => "数字键盘加号键",
+            NumpadMinus => "数字键盘减号键",
+            NumpadMultiply => "数字键盘乘号键",
+            NumpadDivide => "数字键盘除号键",
+            NumpadSeparator => "数字键盘分隔符键",
+            NumpadDot => "数字键盘小数点键",
+            NumpadEnter => "数字键盘回车键",
+            MouseLeft => "鼠标左键",
+            MouseRight => "鼠标右键",
+            MouseMiddle => "鼠标中键",
+            MouseX1 => "鼠标侧键 1",
+            MouseX2 => "鼠标侧键 2",
+            WheelUp => "滚轮向上",
+            WheelDown => "滚轮向下",
+            WheelLeft => "滚轮向左",
+            WheelRight => "滚轮向右",
+            F13 => "F13 键",
+            F14 => "F14 键",
+            F15 => "F15 键",
+            F16 => "F16 键",
+            F17 => "F17 键",
+            F18 => "F18 键",
+            F19 => "F19 键",
+            F20 => "F20 键",
+            F21 => "F21 键",
+            F22 => "F22 键",
+            F23 => "F23 键",
+            F24 => "F24 键",
+            VolumeMute => "静音键",
+            VolumeDown => "音量减键",
+            VolumeUp => "音量加键",
+            MediaNextTrack => "下一曲键",
+            MediaPrevTrack => "上一曲键",
+            MediaStop => "停止播放键",
+            MediaPlayPause => "播放/暂停键",
+            BrowserBack => "浏览器后退键",
+            BrowserForward => "浏览器前进键",
+            BrowserRefresh => "浏览器刷新键",
+            BrowserStop => "浏览器停止键",
+            BrowserSearch => "浏览器搜索键",
+            BrowserFavorites => "浏览器收藏夹键",
+            BrowserHome => "浏览器主页键",
+            Kana => "假名键",
+            Hangul => "韩/英切换键",
+            Convert => "变换键",
+            NonConvert => "无变换键",
+            IntlBackslash => "ISO 额外键",
+            Unknown => "未知按键",
+        }
+    }
+
+    /// other names accepted by [`Key::from_str`], lowercase
+    fn aliases(self) -> &'static [&'static str] {
+        use Key::*;
+        match self {
+            Escape => &["esc"],
+            PrintScreen => &["prtsc", "printscr", "prtscr", "snapshot"],
+            ScrollLock => &["scrlk", "scroll"],
+            Pause => &["break"],
+            BackTick => &["grave", "tilde", "~"],
+            Minus => &["dash"],
+            Equal => &["equals", "plus"],
+            Backspace => &["back", "bs"],
+            Insert => &["ins"],
+            Delete => &["del"],
+            PageUp => &["pgup"],
+            PageDown => &["pgdn"],
+            LeftSquareBracket => &["lbracket"],
+            RightSquareBracket => &["rbracket"],
+            BackwardSlash => &["backslash"],
+            Apostrophe => &["quote"],
+            Period => &["dot"],
+            ForwardSlash => &["slash"],
+            Enter => &["return"],
+            Space => &["spacebar"],
+            LeftControl => &["lctrl", "lcontrol", "ctrl", "control"],
+            RightControl => &["rctrl", "rcontrol"],
+            LeftShift => &["lshift", "shift"],
+            RightShift => &["rshift"],
+            LeftAlt => &["lalt", "alt"],
+            RightAlt => &["ralt", "altgr"],
+            LeftWin => &["lwin", "win", "super", "meta"],
+            RightWin => &["rwin"],
+            Apps => &["menu", "application"],
+            CapsLock => &["caps"],
+            Up => &["uparrow", "arrowup"],
+            Down => &["downarrow", "arrowdown"],
+            Left => &["leftarrow", "arrowleft"],
+            Right => &["rightarrow", "arrowright"],
+            NumLock => &["numlk"],
+            Numpad1 => &["kp1", "np1"],
+            Numpad2 => &["kp2", "np2"],
+            Numpad3 => &["kp3", "np3"],
+            Numpad4 => &["kp4", "np4"],
+            Numpad5 => &["kp5", "np5"],
+            Numpad6 => &["kp6", "np6"],
+            Numpad7 => &["kp7", "np7"],
+            Numpad8 => &["kp8", "np8"],
+            Numpad9 => &["kp9", "np9"],
+            Numpad0 => &["kp0", "np0"],
+            NumpadPlus => &["kp+", "numadd", "add"],
+            NumpadMinus => &["kp-", "numsub", "subtract"],
+            NumpadMultiply => &["kp*", "nummul", "multiply"],
+            NumpadDivide => &["kp/", "numdiv", "divide"],
+            NumpadSeparator => &["separator"],
+            NumpadDot => &["kp.", "numdel", "decimal"],
+            NumpadEnter => &["kpenter", "numreturn"],
+            MouseLeft => &["mouse1", "lmb", "lbutton"],
+            MouseRight => &["mouse2", "rmb", "rbutton"],
+            MouseMiddle => &["mouse3", "mmb", "mbutton"],
+            MouseX1 => &["mouse4", "x1", "xbutton1"],
+            MouseX2 => &["mouse5", "x2", "xbutton2"],
+            WheelUp => &["wheelup", "scrollup", "mwheelup"],
+            WheelDown => &["wheeldown", "scrolldown", "mwheeldown"],
+            WheelLeft => &["wheelleft", "scrollleft"],
+            WheelRight => &["wheelright", "scrollright"],
+            VolumeMute => &["mute"],
+            VolumeDown => &["vol-", "voldown"],
+            VolumeUp => &["vol+", "volup"],
+            MediaNextTrack => &["nexttrack"],
+            MediaPrevTrack => &["prevtrack"],
+            MediaStop => &["stop"],
+            MediaPlayPause => &["play", "playpause"],
+            BrowserBack => &["browserback"],
+            BrowserForward => &["browserforward"],
+            BrowserRefresh => &["browserrefresh"],
+            BrowserStop => &["browserstop"],
+            BrowserSearch => &["browsersearch"],
+            BrowserFavorites => &["browserfavorites"],
+            BrowserHome => &["browserhome"],
+            Kana => &["katakana", "hiragana"],
+            Hangul => &["hanyeong"],
+            Convert => &["henkan"],
+            NonConvert => &["muhenkan"],
+            IntlBackslash => &["oem102"],
+            _ => &[],
+        }
+    }
 }
 
 impl std::fmt::Display for Key {
@@ -420,6 +1149,45 @@ impl std::fmt::Display for Key {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError(pub String);
+
+impl std::fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "未知按键: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseKeyError {}
+
+/// accepts the variant name, [`Key::label`], [`Key::long_name`] and some common aliases,
+/// ignoring ascii case, e.g. `"KeyZ"`, `"lshift"`, `"mouse1"`, `"num+"`
+impl FromStr for Key {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        /// every accepted name in ascii lowercase, the first key to take a name keeps it
+        static NAMES: OnceLock<ahash::HashMap<String, Key>> = OnceLock::new();
+        let names = NAMES.get_or_init(|| {
+            let mut names = ahash::HashMap::default();
+            Key::iter().for_each(|key| {
+                [key.to_string(), key.label().into(), key.long_name().into()]
+                    .into_iter()
+                    .chain(key.aliases().iter().map(|alias| alias.to_string()))
+                    .for_each(|name| {
+                        names.entry(name.to_ascii_lowercase()).or_insert(key);
+                    });
+            });
+            names
+        });
+        let s = s.trim();
+        names
+            .get(&s.to_ascii_lowercase())
+            .copied()
+            .ok_or_else(|| ParseKeyError(s.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -457,4 +1225,65 @@ mod tests {
             println!("{:?}", k);
         });
     }
+
+    #[test]
+    fn physical_key_round_trip() {
+        Key::iter()
+            .filter_map(|key| Some((key, key.to_hid_usage()?)))
+            .for_each(|(key, hid_usage)| {
+                assert_eq!(Key::from_hid_usage(hid_usage), key);
+                let scan_code = key.to_scan_code().expect("unreachable");
+                if scan_code >> 8 != 0xE1 {
+                    let has_e0 = scan_code >> 8 == 0xE0;
+                    assert_eq!(Key::from_scan_code(scan_code & 0xFF, has_e0), key);
+                }
+            });
+        assert_eq!(Key::from_scan_code(0x10, false), Key::KeyQ);
+        assert_eq!(Key::from_scan_code(0x1C, true), Key::NumpadEnter);
+        assert_eq!(Key::from_scan_code(0x22, true), Key::MediaPlayPause);
+        assert_eq!(Key::MouseLeft.to_hid_usage(), None);
+    }
+
+    #[test]
+    fn extended_keys() {
+        use KeyboardAndMouse::*;
+        // existing keys keep their place
+        assert_eq!(
+            Key::iter().position(|key| key == Key::WheelRight),
+            Some(Key::F13 as usize - 1)
+        );
+        assert_eq!(Key::iter().last(), Some(Key::Unknown));
+        assert_eq!(Key::from_virtual_key(VK_RETURN, true), Key::NumpadEnter);
+        assert_eq!(Key::from_virtual_key(VK_F24, false), Key::F24);
+        assert_eq!(Key::from_virtual_key(VK_VOLUME_UP, true), Key::VolumeUp);
+        assert_eq!(Key::from_virtual_key(VK_HANGUL, false), Key::Kana);
+        Key::iter()
+            .filter(|key| *key as u8 > Key::WheelRight as u8 && *key != Key::Unknown)
+            .filter(|key| *key != Key::Hangul)
+            .for_each(|key| assert_eq!(Key::from_virtual_key(key.to_virtual_key(), false), key));
+        let json = serde_json::to_string(&[Key::KeyZ, Key::F13]).expect("unreachable");
+        assert_eq!(json, r#"["KeyZ","F13"]"#);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        Key::iter().for_each(|key| {
+            assert_eq!(Key::from_str(&key.to_string()), Ok(key));
+            assert_eq!(Key::from_str(key.label()), Ok(key));
+            assert_eq!(Key::from_str(key.long_name()), Ok(key));
+            key.aliases()
+                .iter()
+                .for_each(|alias| assert_eq!(Key::from_str(alias), Ok(key), "{alias}"));
+        });
+        [
+            ("lshift", Key::LeftShift),
+            ("LShift", Key::LeftShift),
+            ("mouse1", Key::MouseLeft),
+            ("num+", Key::NumpadPlus),
+            (" keyz ", Key::KeyZ),
+        ]
+        .into_iter()
+        .for_each(|(s, key)| assert_eq!(Key::from_str(s), Ok(key)));
+        assert!(Key::from_str("not a key").is_err());
+    }
 }
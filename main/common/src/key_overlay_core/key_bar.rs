@@ -4,13 +4,16 @@ use std::time::Instant;
 pub struct KeyBar {
     pub press_instant: Instant,
     pub release_instant: Instant,
+    /// the deepest depth reached while pressed, `1.0` for digital keys
+    pub depth: f32,
 }
 
 impl KeyBar {
-    pub fn new(press_instant: Instant, release_instant: Instant) -> Self {
+    pub fn new(press_instant: Instant, release_instant: Instant, depth: f32) -> Self {
         Self {
             press_instant,
             release_instant,
+            depth,
         }
     }
 }
@@ -8,7 +8,7 @@ use egui::Color32;
 use crate::{
     key_overlay_core::{
         key_bar::KeyBar,
-        key_property::{KeyDirection, KeyProperty},
+        key_property::{DepthEffect, KeyProperty},
     },
     setting::WindowSetting,
 };
@@ -21,45 +21,77 @@ pub struct KeyDrawCache {
     pub key_counter_color: Color32,
     pub max_bar_duration: Duration,
     pub count: u32,
+    /// presses dropped by debouncing
+    pub chatter_count: u32,
     pub bar_queue: VecDeque<KeyBar>,
     pub begin_hold_instant: Option<Instant>,
+    pub depth_effect: DepthEffect,
+    /// the current depth, `1.0` or `0.0` for digital keys
+    pub depth: f32,
+    /// the deepest depth reached since `begin_hold_instant`
+    pub peak_depth: f32,
 }
 
 impl KeyDrawCache {
     pub fn new(window_setting: &WindowSetting, bar_speed: f32, key_property: &KeyProperty) -> Self {
-        let max_distance = if let (true, max_distantce) = key_property.max_distance {
-            max_distantce
-        } else {
-            match key_property.key_direction {
-                KeyDirection::Up => key_property.position.y,
-                KeyDirection::Down => {
-                    window_setting.height - key_property.position.y - key_property.height
-                }
-                KeyDirection::Left => key_property.position.x,
-                KeyDirection::Right => {
-                    window_setting.width - key_property.position.x - key_property.width
-                }
-            }
-            .max(0.0)
-        };
-        let max_bar_duration = Duration::from_secs_f32(max_distance / bar_speed);
+        let max_bar_duration = Self::max_bar_duration(window_setting, bar_speed, key_property);
         Self {
             bar_queue: VecDeque::with_capacity(64),
             key_text_color: key_property.text_color.into(),
             frame_color: key_property.frame_color.into(),
             pressed_color: key_property.pressed_color.into(),
-            key_counter_color: key_property.key_counter.1.text_color.into(),
+            key_counter_color: key_property.key_counter.value.text_color.into(),
             max_bar_duration,
             count: 0,
+            chatter_count: 0,
             begin_hold_instant: None,
+            depth_effect: key_property.depth_effect,
+            depth: 0.0,
+            peak_depth: 0.0,
         }
     }
 
+    /// how long a bar takes to travel `max_distance`, or to the window edge by default,
+    /// see [`KeyProperty::bar_max_distance`]
+    pub fn max_bar_duration(
+        window_setting: &WindowSetting,
+        bar_speed: f32,
+        key_property: &KeyProperty,
+    ) -> Duration {
+        let window_size = egui::vec2(window_setting.width, window_setting.height);
+        Duration::from_secs_f32(key_property.bar_max_distance(window_size) / bar_speed)
+    }
+
     #[inline]
     pub fn increase_count(&mut self) {
         self.count = self.count.wrapping_add(1);
     }
 
+    /// a press within `debounce` after the last release
+    #[inline]
+    pub fn is_chatter(&self, press_instant: Instant, debounce: Duration) -> bool {
+        self.bar_queue.back().is_some_and(|bar| {
+            press_instant.saturating_duration_since(bar.release_instant) < debounce
+        })
+    }
+
+    /// opacity factor of the pressed fill, `0.0` hides it
+    #[inline]
+    pub fn pressed_fill(&self) -> f32 {
+        match self.depth_effect {
+            DepthEffect::PressedFill => self.depth,
+            _ => self.begin_hold_instant.map_or(0.0, |_| 1.0),
+        }
+    }
+
+    #[inline]
+    pub fn bar_width_scale(&self, depth: f32) -> f32 {
+        match self.depth_effect {
+            DepthEffect::BarWidth => depth,
+            _ => 1.0,
+        }
+    }
+
     #[inline]
     pub fn need_repaint(&self) -> bool {
         !self.bar_queue.is_empty() || self.begin_hold_instant.is_some()
@@ -1,12 +1,15 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::{
+    device::DeviceId,
     key::Key,
     key_overlay_core::{
-        key_bar::KeyBar, key_draw_cache::KeyDrawCache, key_message::KeyMessage,
-        key_property::KeyProperty,
+        key_bar::KeyBar,
+        key_draw_cache::KeyDrawCache,
+        key_message::KeyMessage,
+        key_property::{BindMode, KeyGroupProperty, KeyProperty},
     },
-    setting::Setting,
+    setting::{InputSetting, Setting},
 };
 
 #[derive(Debug)]
@@ -17,13 +20,16 @@ struct KeyMap {
 impl KeyMap {
     const CAP: usize = Key::LAST_KEY as usize;
 
-    fn new(key_properties: &[KeyProperty]) -> Self {
+    /// only maps the properties bound with `bind_mode`
+    fn new(key_properties: &[KeyProperty], bind_mode: BindMode) -> Self {
         let init = || -> Option<_> {
             let mut init_map: Box<[_; Self::CAP]> = Box::new(std::array::from_fn(|_| Some(vec![])));
             let iter = key_properties
                 .iter()
-                .filter(|key_property| key_property.key_bind != Key::Unknown)
-                .enumerate();
+                .enumerate()
+                .filter(|(_, key_property)| {
+                    key_property.key_bind != Key::Unknown && key_property.bind_mode == bind_mode
+                });
             for (index, key_property) in iter {
                 let indexes = init_map.get_mut(key_property.key_bind as usize)?.as_mut()?;
                 indexes.push(index);
@@ -49,15 +55,135 @@ impl KeyMap {
     }
 
     fn get(&self, key: Key) -> Option<&[usize]> {
-        debug_assert!(key != Key::Unknown);
+        // a key message may only have a virtual key or a physical key
+        if key == Key::Unknown {
+            return None;
+        }
         unsafe { self.map.get_unchecked(key as usize) }.as_deref()
     }
 }
 
+/// presses of the key properties in a key group, counted whether the keys have a counter or not
+#[derive(Debug)]
+struct KeyGroup {
+    /// sorted
+    indexes: Box<[usize]>,
+    count: u32,
+}
+
+impl KeyGroup {
+    fn new(
+        key_group_property: &KeyGroupProperty,
+        key_maps: &KeyMaps,
+        key_properties: &[KeyProperty],
+        key_filters: &[KeyFilter],
+    ) -> Self {
+        let mut keys: Vec<_> = if key_group_property.all_keys {
+            Key::iter().collect()
+        } else {
+            key_group_property.keys.clone()
+        };
+        keys.sort_unstable_by_key(|&key| key as u8);
+        keys.dedup();
+        // properties bound to the same key may count differently, by device, debounce,
+        // actuation point or bind mode, only the ones counting the same presses are taken once
+        let mut taken = ahash::HashSet::default();
+        let mut indexes: Vec<usize> = keys
+            .into_iter()
+            .flat_map(|key| key_maps.indexes(key))
+            .filter(|&index| {
+                let key_property = &key_properties[index];
+                taken.insert((
+                    key_property.key_bind,
+                    key_property.bind_mode,
+                    key_property.actuation_point.to_bits(),
+                    &key_filters[index],
+                ))
+            })
+            .collect();
+        indexes.sort_unstable();
+        Self {
+            indexes: indexes.into_boxed_slice(),
+            count: 0,
+        }
+    }
+
+    /// a new press of the property at `index`
+    fn count_press(key_groups: &mut [KeyGroup], index: usize) {
+        key_groups
+            .iter_mut()
+            .filter(|key_group| key_group.indexes.binary_search(&index).is_ok())
+            .for_each(|key_group| key_group.count = key_group.count.wrapping_add(1));
+    }
+}
+
+/// one key map per [`BindMode`]
+#[derive(Debug)]
+struct KeyMaps {
+    virtual_key: KeyMap,
+    scan_code: KeyMap,
+}
+
+impl KeyMaps {
+    fn new(key_properties: &[KeyProperty]) -> Self {
+        Self {
+            virtual_key: KeyMap::new(key_properties, BindMode::VirtualKey),
+            scan_code: KeyMap::new(key_properties, BindMode::ScanCode),
+        }
+    }
+
+    fn get(&self, key_message: &KeyMessage) -> impl Iterator<Item = usize> + '_ {
+        let virtual_key_indexes = self.virtual_key.get(key_message.key).unwrap_or_default();
+        let scan_code_indexes = self
+            .scan_code
+            .get(key_message.physical_key)
+            .unwrap_or_default();
+        virtual_key_indexes.iter().chain(scan_code_indexes).copied()
+    }
+
+    /// the properties bound to `key` in either bind mode
+    fn indexes(&self, key: Key) -> impl Iterator<Item = usize> + '_ {
+        [&self.virtual_key, &self.scan_code]
+            .into_iter()
+            .flat_map(move |key_map| key_map.get(key).unwrap_or_default())
+            .copied()
+    }
+}
+
+/// per property input filtering, resolved from the setting at load
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct KeyFilter {
+    device: Option<DeviceId>,
+    debounce: Duration,
+}
+
+impl KeyFilter {
+    fn new(key_property: &KeyProperty, input_setting: &InputSetting) -> Self {
+        Self {
+            device: key_property.device_filter_id(),
+            debounce: key_property.debounce(input_setting),
+        }
+    }
+}
+
+/// what a key message did to the key properties bound to it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KeyUpdate {
+    /// `Some(is_pressed)` if a property went down or up, a bounce included
+    pub changed: Option<bool>,
+    /// a new press, counted by the key or not
+    pub pressed: bool,
+}
+
 pub struct KeyHandler {
     key_properties: Box<[KeyProperty]>,
     key_draw_caches: Box<[KeyDrawCache]>,
-    key_map: KeyMap,
+    key_maps: KeyMaps,
+    /// parallel to `key_properties`
+    key_filters: Box<[KeyFilter]>,
+    key_group_properties: Box<[KeyGroupProperty]>,
+    key_groups: Box<[KeyGroup]>,
+    wheel_press_duration: Duration,
 }
 
 impl KeyHandler {
@@ -65,20 +191,37 @@ impl KeyHandler {
         let Setting {
             window_setting,
             key_properties,
+            key_groups: key_group_properties,
+            input_setting,
             ..
         } = setting;
         let key_properties = key_properties.into_boxed_slice();
-        let key_map = KeyMap::new(&key_properties);
+        let key_maps = KeyMaps::new(&key_properties);
         let key_draw_caches = key_properties
             .iter()
             .map(|key_property| {
                 KeyDrawCache::new(&window_setting, key_property.bar_speed, key_property)
             })
             .collect();
+        let key_filters: Box<[_]> = key_properties
+            .iter()
+            .map(|key_property| KeyFilter::new(key_property, &input_setting))
+            .collect();
+        let key_group_properties = key_group_properties.into_boxed_slice();
+        let key_groups = key_group_properties
+            .iter()
+            .map(|key_group_property| {
+                KeyGroup::new(key_group_property, &key_maps, &key_properties, &key_filters)
+            })
+            .collect();
         Self {
             key_properties,
             key_draw_caches,
-            key_map,
+            key_maps,
+            key_filters,
+            key_group_properties,
+            key_groups,
+            wheel_press_duration: input_setting.wheel_press_duration(),
         }
     }
 
@@ -86,60 +229,230 @@ impl KeyHandler {
         let Setting {
             window_setting,
             key_properties,
+            key_groups: key_group_properties,
+            input_setting,
             ..
         } = setting;
         let new_key_properties = key_properties.clone().into_boxed_slice();
-        let new_key_map = KeyMap::new(&new_key_properties);
+        let new_key_maps = KeyMaps::new(&new_key_properties);
         let new_key_draw_caches = new_key_properties
             .iter()
             .map(|key_property| {
                 KeyDrawCache::new(window_setting, key_property.bar_speed, key_property)
             })
             .collect();
+        let new_key_filters: Box<[_]> = new_key_properties
+            .iter()
+            .map(|key_property| KeyFilter::new(key_property, input_setting))
+            .collect();
+        let new_key_group_properties = key_group_properties.clone().into_boxed_slice();
+        let new_key_groups = new_key_group_properties
+            .iter()
+            .map(|key_group_property| {
+                KeyGroup::new(
+                    key_group_property,
+                    &new_key_maps,
+                    &new_key_properties,
+                    &new_key_filters,
+                )
+            })
+            .collect();
 
+        self.key_filters = new_key_filters;
         self.key_properties = new_key_properties;
-        self.key_map = new_key_map;
+        self.key_maps = new_key_maps;
         self.key_draw_caches = new_key_draw_caches;
+        self.key_group_properties = new_key_group_properties;
+        self.key_groups = new_key_groups;
+        self.wheel_press_duration = input_setting.wheel_press_duration();
+    }
+
+    /// takes the positions and sizes of a re-flowed layout of the same keys,
+    /// counts and bars are kept
+    ///
+    /// the key maps, filters and groups are kept too, so `setting` must bind the same keys
+    /// in the same order with the same input settings, otherwise [`Self::reload`] it
+    pub fn relayout(&mut self, setting: &Setting) {
+        let Setting {
+            window_setting,
+            key_properties,
+            key_groups: key_group_properties,
+            ..
+        } = setting;
+        debug_assert!(
+            key_properties.len() == self.key_properties.len()
+                && key_properties
+                    .iter()
+                    .zip(self.key_properties.iter())
+                    .all(
+                        |(new, old)| new.key_bind == old.key_bind && new.bind_mode == old.bind_mode
+                    )
+                && key_group_properties.len() == self.key_group_properties.len()
+        );
+        self.key_draw_caches
+            .iter_mut()
+            .zip(key_properties)
+            .for_each(|(key_draw_cache, key_property)| {
+                key_draw_cache.max_bar_duration = KeyDrawCache::max_bar_duration(
+                    window_setting,
+                    key_property.bar_speed,
+                    key_property,
+                );
+            });
+        self.key_properties = key_properties.clone().into_boxed_slice();
+        self.key_group_properties = key_group_properties.clone().into_boxed_slice();
     }
 
-    pub fn update(&mut self, key_message: KeyMessage) {
-        debug_assert!(key_message.key != Key::Unknown);
+    pub fn update(&mut self, key_message: KeyMessage) -> KeyUpdate {
+        debug_assert!(key_message.key != Key::Unknown || key_message.physical_key != Key::Unknown);
 
-        let mut inner_update = |indexes: &[usize]| -> Option<()> {
-            let first_key_draw_cache = self.key_draw_caches.get_mut(*indexes.first()?)?;
+        let mut key_update = KeyUpdate::default();
 
-            let now_pressed = key_message.is_pressed;
-            let prev_pressed = first_key_draw_cache.begin_hold_instant.is_some();
+        // wheel keys are released by `release_wheel_keys` instead
+        let is_wheel = key_message.key.is_wheel();
+        let depth = key_message.depth_or_digital();
+
+        // properties bound to the same analog key may actuate at different depths
+        self.key_maps.get(&key_message).for_each(|index| {
+            let (Some(key_filter), Some(key_property), Some(key_draw_cache)) = (
+                self.key_filters.get(index),
+                self.key_properties.get(index),
+                self.key_draw_caches.get_mut(index),
+            ) else {
+                return;
+            };
+            if key_filter
+                .device
+                .is_some_and(|device| device != key_message.device)
+            {
+                return;
+            }
+
+            let now_pressed = match key_message.depth {
+                Some(depth) => key_property.is_actuated(depth),
+                None => key_message.is_pressed,
+            };
+            let prev_pressed = key_draw_cache.begin_hold_instant.is_some();
+            key_draw_cache.depth = depth;
 
             match (prev_pressed, now_pressed) {
+                (false, true)
+                    if !is_wheel
+                        && key_draw_cache.is_chatter(key_message.instant, key_filter.debounce) =>
+                {
+                    // the last release was a bounce, keep holding from the original press
+                    let bar = key_draw_cache.bar_queue.pop_back().expect("unreachable");
+                    key_draw_cache.begin_hold_instant = Some(bar.press_instant);
+                    key_draw_cache.peak_depth = bar.depth.max(depth);
+                    key_draw_cache.chatter_count = key_draw_cache.chatter_count.wrapping_add(1);
+                    key_update.changed = Some(true);
+                }
                 (false, true) => {
-                    for index in indexes.iter() {
-                        let key_property = self.key_properties.get_mut(*index)?;
-                        let key_draw_cache = self.key_draw_caches.get_mut(*index)?;
-                        if key_property.key_counter.0 {
-                            key_draw_cache.increase_count();
-                        }
-                        key_draw_cache.begin_hold_instant = Some(key_message.instant);
+                    if key_property.key_counter.enabled {
+                        key_draw_cache.increase_count();
                     }
+                    KeyGroup::count_press(&mut self.key_groups, index);
+                    key_update = KeyUpdate {
+                        changed: Some(true),
+                        pressed: true,
+                    };
+                    key_draw_cache.begin_hold_instant = Some(key_message.instant);
+                    key_draw_cache.peak_depth = depth;
                 }
-                (true, false) => {
-                    for index in indexes.iter() {
-                        let key_draw_cache = self.key_draw_caches.get_mut(*index)?;
-                        let bar = KeyBar::new(
-                            key_draw_cache.begin_hold_instant.take()?,
-                            key_message.instant,
-                        );
-                        key_draw_cache.add_bar(bar);
+                (true, true) if is_wheel => {
+                    // next notch comes before the previous one is released
+                    let press_instant = key_draw_cache
+                        .begin_hold_instant
+                        .replace(key_message.instant)
+                        .expect("unreachable");
+                    let bar = KeyBar::new(
+                        press_instant,
+                        key_message.instant,
+                        key_draw_cache.peak_depth,
+                    );
+                    key_draw_cache.add_bar(bar);
+                    if key_property.key_counter.enabled {
+                        key_draw_cache.increase_count();
                     }
+                    KeyGroup::count_press(&mut self.key_groups, index);
+                    key_update = KeyUpdate {
+                        changed: Some(true),
+                        pressed: true,
+                    };
+                    key_draw_cache.peak_depth = depth;
+                }
+                (true, true) => {
+                    key_draw_cache.peak_depth = key_draw_cache.peak_depth.max(depth);
+                }
+                (true, false) if !is_wheel => {
+                    let press_instant = key_draw_cache
+                        .begin_hold_instant
+                        .take()
+                        .expect("unreachable");
+                    let bar = KeyBar::new(
+                        press_instant,
+                        key_message.instant,
+                        key_draw_cache.peak_depth,
+                    );
+                    key_draw_cache.add_bar(bar);
+                    key_update.changed = Some(false);
                 }
                 _ => (),
             }
-            Some(())
-        };
+        });
+        key_update
+    }
+
+    pub fn release_wheel_keys(&mut self, instant_now: Instant) {
+        let wheel_press_duration = self.wheel_press_duration;
+        self.key_properties
+            .iter()
+            .zip(self.key_draw_caches.iter_mut())
+            .filter(|(key_property, _)| key_property.key_bind.is_wheel())
+            .for_each(|(_, key_draw_cache)| {
+                let Some(press_instant) = key_draw_cache.begin_hold_instant else {
+                    return;
+                };
+                let release_instant = press_instant + wheel_press_duration;
+                if release_instant <= instant_now {
+                    key_draw_cache.begin_hold_instant = None;
+                    key_draw_cache.depth = 0.0;
+                    let bar =
+                        KeyBar::new(press_instant, release_instant, key_draw_cache.peak_depth);
+                    key_draw_cache.add_bar(bar);
+                }
+            });
+    }
+
+    /// press and chatter counts, key groups included
+    pub fn reset_counters(&mut self) {
+        self.key_draw_caches.iter_mut().for_each(|key_draw_cache| {
+            key_draw_cache.count = 0;
+            key_draw_cache.chatter_count = 0;
+        });
+        self.key_groups
+            .iter_mut()
+            .for_each(|key_group| key_group.count = 0);
+    }
+
+    /// `(key_bind, count)` saved in the order of the properties, skipped where the binding changed
+    pub fn restore_counts(&mut self, counts: impl IntoIterator<Item = (Key, u32)>) {
+        self.key_properties
+            .iter()
+            .zip(self.key_draw_caches.iter_mut())
+            .zip(counts)
+            .filter(|((key_property, _), (key_bind, _))| key_property.key_bind == *key_bind)
+            .for_each(|((_, key_draw_cache), (_, count))| key_draw_cache.count = count);
+    }
 
-        self.key_map
-            .get(key_message.key)
-            .map(|indexes| unsafe { inner_update(indexes).unwrap_unchecked() });
+    /// `(name, count)` saved in the order of the key groups, skipped where the name changed
+    pub fn restore_group_counts<'a>(&mut self, counts: impl IntoIterator<Item = (&'a str, u32)>) {
+        self.key_group_properties
+            .iter()
+            .zip(self.key_groups.iter_mut())
+            .zip(counts)
+            .filter(|((key_group_property, _), (name, _))| key_group_property.name == *name)
+            .for_each(|((_, key_group), (_, count))| key_group.count = count);
     }
 
     pub fn remove_outer_bar(&mut self, instant_now: Instant) {
@@ -156,9 +469,17 @@ impl KeyHandler {
         &self.key_draw_caches
     }
 
-    /// F1: `Fn(index, begin_duration_secs)`
+    /// `(key_group_property, count)`
+    pub fn key_group_counts_iter(&self) -> impl Iterator<Item = (&KeyGroupProperty, u32)> {
+        self.key_group_properties
+            .iter()
+            .zip(self.key_groups.iter())
+            .map(|(key_group_property, key_group)| (key_group_property, key_group.count))
+    }
+
+    /// F1: `Fn(index, begin_duration_secs, width_scale)`
     ///
-    /// F2: `Fn(index, begin_duration_secs, end_duration_secs)`
+    /// F2: `Fn(index, begin_duration_secs, end_duration_secs, width_scale)`
     pub fn key_draw_caches_flat_map_iter<'a, T, F1, F2>(
         &'a self,
         instant_now: Instant,
@@ -167,8 +488,8 @@ impl KeyHandler {
     ) -> impl Iterator<Item = T> + 'a
     where
         T: 'a,
-        F1: Fn(usize, f32) -> T + 'a,
-        F2: Fn(usize, f32, f32) -> T + 'a,
+        F1: Fn(usize, f32, f32) -> T + 'a,
+        F2: Fn(usize, f32, f32, f32) -> T + 'a,
     {
         self.key_draw_caches
             .iter()
@@ -178,7 +499,8 @@ impl KeyHandler {
                     .begin_hold_instant
                     .map(move |instant| {
                         let begin_duration_secs = instant_now.duration_since(instant).as_secs_f32();
-                        begin_hold_instant_map(index, begin_duration_secs)
+                        let width_scale = cache.bar_width_scale(cache.peak_depth);
+                        begin_hold_instant_map(index, begin_duration_secs, width_scale)
                     })
                     .into_iter()
                     .chain(cache.bar_queue.iter().map(move |key_bar| {
@@ -188,7 +510,8 @@ impl KeyHandler {
                         let end_duration_secs = instant_now
                             .duration_since(key_bar.release_instant)
                             .as_secs_f32();
-                        key_bar_map(index, begin_duration_secs, end_duration_secs)
+                        let width_scale = cache.bar_width_scale(key_bar.depth);
+                        key_bar_map(index, begin_duration_secs, end_duration_secs, width_scale)
                     }))
             })
     }
@@ -199,3 +522,330 @@ impl KeyHandler {
             .any(|key_draw_cache| key_draw_cache.need_repaint())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::key_overlay_core::key_property::KeyCounterProperty;
+
+    use super::*;
+
+    /// bound to `key` with a counter
+    fn counted(key: Key) -> KeyProperty {
+        KeyProperty::default()
+            .with_key_bind(key)
+            .with_key_counter(Some(KeyCounterProperty::default()))
+    }
+
+    fn press_and_release(key_handler: &mut KeyHandler, key: Key, instant: Instant) {
+        key_handler.update(KeyMessage::new(key, true, instant));
+        key_handler.update(KeyMessage::new(
+            key,
+            false,
+            instant + Duration::from_millis(10),
+        ));
+    }
+
+    #[test]
+    fn key_group_count() {
+        // only the first one has a counter, the groups count the others too
+        let key_properties = [Key::KeyZ, Key::KeyX, Key::KeyC, Key::KeyZ]
+            .into_iter()
+            .enumerate()
+            .map(|(index, key)| match index {
+                0 => counted(key),
+                _ => KeyProperty::default().with_key_bind(key),
+            })
+            .collect();
+        let key_groups = vec![
+            KeyGroupProperty::default().with_keys(vec![Key::KeyZ, Key::KeyX, Key::KeyZ]),
+            KeyGroupProperty::default().with_all_keys(true),
+            KeyGroupProperty::default().with_keys(vec![Key::KeyV]),
+        ];
+        let setting = Setting {
+            key_properties,
+            key_groups,
+            ..Setting::default()
+        };
+        let mut key_handler = KeyHandler::new(setting);
+
+        let instant = Instant::now();
+        [Key::KeyZ, Key::KeyZ, Key::KeyX, Key::KeyC]
+            .into_iter()
+            .for_each(|key| press_and_release(&mut key_handler, key, instant));
+
+        let counts: Vec<_> = key_handler
+            .key_group_counts_iter()
+            .map(|(_, count)| count)
+            .collect();
+        assert_eq!(counts, [3, 4, 0]);
+        let counts: Vec<_> = key_handler
+            .key_draw_caches()
+            .iter()
+            .map(|key_draw_cache| key_draw_cache.count)
+            .collect();
+        assert_eq!(counts, [2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn key_update() {
+        let setting = Setting {
+            key_properties: vec![counted(Key::KeyZ)],
+            ..Setting::default()
+        };
+        let mut key_handler = KeyHandler::new(setting);
+
+        let instant = Instant::now();
+        // the os repeats the press while the key is held
+        let key_updates: Vec<_> = [
+            (Key::KeyZ, true),
+            (Key::KeyZ, true),
+            (Key::KeyX, true),
+            (Key::KeyZ, false),
+        ]
+        .into_iter()
+        .map(|(key, is_pressed)| key_handler.update(KeyMessage::new(key, is_pressed, instant)))
+        .collect();
+        let pressed = KeyUpdate {
+            changed: Some(true),
+            pressed: true,
+        };
+        let released = KeyUpdate {
+            changed: Some(false),
+            pressed: false,
+        };
+        let unchanged = KeyUpdate::default();
+        assert_eq!(key_updates, [pressed, unchanged, unchanged, released]);
+    }
+
+    #[test]
+    fn wheel_press_and_release() {
+        let key_properties = vec![counted(Key::WheelUp)];
+        let setting = Setting {
+            key_properties,
+            input_setting: InputSetting::default().with_wheel_press_duration_ms(50.0),
+            ..Setting::default()
+        };
+        let mut key_handler = KeyHandler::new(setting);
+
+        let instant = Instant::now();
+        // two notches, the second one comes before the first one is released
+        [Duration::ZERO, Duration::from_millis(20)]
+            .into_iter()
+            .for_each(|offset| press_and_release(&mut key_handler, Key::WheelUp, instant + offset));
+
+        let key_draw_cache = &key_handler.key_draw_caches()[0];
+        assert_eq!(key_draw_cache.count, 2);
+        assert_eq!(key_draw_cache.bar_queue.len(), 1);
+        assert!(key_draw_cache.begin_hold_instant.is_some());
+
+        key_handler.release_wheel_keys(instant + Duration::from_millis(40));
+        assert!(
+            key_handler.key_draw_caches()[0]
+                .begin_hold_instant
+                .is_some()
+        );
+
+        key_handler.release_wheel_keys(instant + Duration::from_millis(70));
+        let key_draw_cache = &key_handler.key_draw_caches()[0];
+        assert!(key_draw_cache.begin_hold_instant.is_none());
+        let bar = key_draw_cache.bar_queue[1];
+        assert_eq!(
+            bar.release_instant - bar.press_instant,
+            Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn analog_actuation_points() {
+        let key_properties = [0.3, 0.7]
+            .into_iter()
+            .map(|actuation_point| counted(Key::KeyZ).with_actuation_point(actuation_point))
+            .collect();
+        let setting = Setting {
+            key_properties,
+            ..Setting::default()
+        };
+        let mut key_handler = KeyHandler::new(setting);
+
+        let instant = Instant::now();
+        [0.2, 0.5, 0.8, 0.6, 0.1, 0.0]
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, depth)| {
+                let instant = instant + Duration::from_millis(10 * i as u64);
+                key_handler.update(KeyMessage::new_analog(Key::KeyZ, depth, instant));
+            });
+
+        let key_draw_caches = key_handler.key_draw_caches();
+        // `(press offset, release offset, depth)` in milliseconds
+        let bars: Vec<Vec<_>> = key_draw_caches
+            .iter()
+            .map(|key_draw_cache| {
+                key_draw_cache
+                    .bar_queue
+                    .iter()
+                    .map(|bar| {
+                        (
+                            (bar.press_instant - instant).as_millis(),
+                            (bar.release_instant - instant).as_millis(),
+                            bar.depth,
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(bars, [vec![(10, 40, 0.8)], vec![(20, 30, 0.8)]]);
+        assert!(key_draw_caches.iter().all(|key_draw_cache| {
+            key_draw_cache.count == 1 && key_draw_cache.begin_hold_instant.is_none()
+        }));
+        assert_eq!(key_draw_caches[0].depth, 0.0);
+    }
+
+    #[test]
+    fn device_filter() {
+        let keyboard_1 = r"\\?\HID#VID_0001&PID_0001#1";
+        let keyboard_2 = r"\\?\HID#VID_0002&PID_0002#1";
+        let key_properties = [None, Some(keyboard_1), Some(keyboard_2)]
+            .into_iter()
+            .map(|device_filter| {
+                counted(Key::KeyZ).with_device_filter(device_filter.map(Into::into))
+            })
+            .collect();
+        let setting = Setting {
+            key_properties,
+            ..Setting::default()
+        };
+        let mut key_handler = KeyHandler::new(setting);
+
+        let instant = Instant::now();
+        [
+            DeviceId::from_name(keyboard_1),
+            DeviceId::from_name(keyboard_1),
+            DeviceId::UNKNOWN,
+        ]
+        .into_iter()
+        .for_each(|device| {
+            [true, false].into_iter().for_each(|is_pressed| {
+                let key_message = KeyMessage::new(Key::KeyZ, is_pressed, instant);
+                key_handler.update(key_message.with_device(device));
+            });
+        });
+
+        let counts: Vec<_> = key_handler
+            .key_draw_caches()
+            .iter()
+            .map(|key_draw_cache| key_draw_cache.count)
+            .collect();
+        assert_eq!(counts, [3, 2, 0]);
+    }
+
+    #[test]
+    fn key_group_count_per_device() {
+        let keyboard_1 = r"\\?\HID#VID_0001&PID_0001#1";
+        let keyboard_2 = r"\\?\HID#VID_0002&PID_0002#1";
+        // two players on one pc, each with their own keyboard, and a duplicate of the first one
+        let key_properties = [keyboard_1, keyboard_2, keyboard_1]
+            .into_iter()
+            .map(|device_filter| counted(Key::KeyZ).with_device_filter(Some(device_filter.into())))
+            .collect();
+        let setting = Setting {
+            key_properties,
+            key_groups: vec![KeyGroupProperty::default().with_all_keys(true)],
+            ..Setting::default()
+        };
+        let mut key_handler = KeyHandler::new(setting);
+
+        let instant = Instant::now();
+        [keyboard_1, keyboard_2, keyboard_2]
+            .into_iter()
+            .for_each(|device| {
+                [true, false].into_iter().for_each(|is_pressed| {
+                    let key_message = KeyMessage::new(Key::KeyZ, is_pressed, instant);
+                    key_handler.update(key_message.with_device(DeviceId::from_name(device)));
+                });
+            });
+
+        let counts: Vec<_> = key_handler
+            .key_group_counts_iter()
+            .map(|(_, count)| count)
+            .collect();
+        assert_eq!(counts, [3]);
+    }
+
+    #[test]
+    fn debounce_chatter() {
+        let key_properties = [None, Some(0.0)]
+            .into_iter()
+            .map(|debounce_ms| counted(Key::KeyZ).with_debounce_ms(debounce_ms))
+            .collect();
+        let setting = Setting {
+            key_properties,
+            input_setting: InputSetting::default().with_debounce_ms(5.0),
+            ..Setting::default()
+        };
+        let mut key_handler = KeyHandler::new(setting);
+
+        let instant = Instant::now();
+        // the press at 32ms is chatter, the one at 60ms is not
+        [(0, true), (30, false), (32, true), (50, false), (60, true)]
+            .into_iter()
+            .for_each(|(offset, is_pressed)| {
+                let instant = instant + Duration::from_millis(offset);
+                key_handler.update(KeyMessage::new(Key::KeyZ, is_pressed, instant));
+            });
+
+        let [debounced, raw] = [0, 1].map(|index| &key_handler.key_draw_caches()[index]);
+        assert_eq!((debounced.count, debounced.chatter_count), (2, 1));
+        assert_eq!(debounced.bar_queue.len(), 1);
+        assert_eq!(
+            debounced.bar_queue[0].release_instant - debounced.bar_queue[0].press_instant,
+            Duration::from_millis(50)
+        );
+        assert_eq!((raw.count, raw.chatter_count), (3, 0));
+        assert_eq!(raw.bar_queue.len(), 2);
+    }
+
+    #[test]
+    fn scan_code_bind_mode() {
+        let key_properties = [BindMode::VirtualKey, BindMode::ScanCode]
+            .into_iter()
+            .map(|bind_mode| counted(Key::KeyA).with_bind_mode(bind_mode))
+            .collect();
+        let setting = Setting {
+            key_properties,
+            ..Setting::default()
+        };
+        let mut key_handler = KeyHandler::new(setting);
+
+        let instant = Instant::now();
+        // `A` on an AZERTY layout is at the position of `Q` on an US layout, and vice versa
+        [(Key::KeyA, Key::KeyQ), (Key::KeyQ, Key::KeyA)]
+            .into_iter()
+            .for_each(|(key, physical_key)| {
+                [true, false].into_iter().for_each(|is_pressed| {
+                    let key_message = KeyMessage::new(key, is_pressed, instant);
+                    key_handler.update(key_message.with_physical_key(physical_key));
+                });
+            });
+
+        let counts: Vec<_> = key_handler
+            .key_draw_caches()
+            .iter()
+            .map(|key_draw_cache| key_draw_cache.count)
+            .collect();
+        assert_eq!(counts, [1, 1]);
+
+        key_handler
+            .update(KeyMessage::new(Key::Unknown, true, instant).with_physical_key(Key::KeyA));
+        assert!(
+            key_handler.key_draw_caches()[0]
+                .begin_hold_instant
+                .is_none()
+        );
+        assert!(
+            key_handler.key_draw_caches()[1]
+                .begin_hold_instant
+                .is_some()
+        );
+    }
+}
@@ -1,11 +1,18 @@
 use std::time::Instant;
 
-use crate::key::Key;
+use crate::{device::DeviceId, key::Key, key_overlay_core::key_property::BindMode};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct KeyMessage {
     pub key: Key,
+    /// the key at the same physical position on an US layout, see [`Key::from_scan_code`],
+    /// same as `key` for mouse keys
+    pub physical_key: Key,
     pub is_pressed: bool,
+    /// travel of an analog key, from `0.0` (released) to `1.0` (bottomed out),
+    /// `None` for digital keys
+    pub depth: Option<f32>,
+    pub device: DeviceId,
     pub instant: Instant,
 }
 
@@ -13,8 +20,92 @@ impl KeyMessage {
     pub fn new(key: Key, is_pressed: bool, instant: Instant) -> Self {
         Self {
             key,
+            physical_key: key,
             is_pressed,
+            depth: None,
+            device: DeviceId::UNKNOWN,
             instant,
         }
     }
+
+    /// `is_pressed` is set on any travel, [`KeyProperty::actuation_point`] decides
+    /// whether the key counts as pressed for the overlay
+    ///
+    /// [`KeyProperty::actuation_point`]: crate::key_overlay_core::key_property::KeyProperty::actuation_point
+    pub fn new_analog(key: Key, depth: f32, instant: Instant) -> Self {
+        let depth = depth.clamp(0.0, 1.0);
+        Self {
+            key,
+            physical_key: key,
+            is_pressed: depth > 0.0,
+            depth: Some(depth),
+            device: DeviceId::UNKNOWN,
+            instant,
+        }
+    }
+
+    pub fn with_device(mut self, device: DeviceId) -> Self {
+        self.device = device;
+        self
+    }
+
+    pub fn with_physical_key(mut self, physical_key: Key) -> Self {
+        self.physical_key = physical_key;
+        self
+    }
+
+    #[inline]
+    pub fn bound_key(&self, bind_mode: BindMode) -> Key {
+        match bind_mode {
+            BindMode::VirtualKey => self.key,
+            BindMode::ScanCode => self.physical_key,
+        }
+    }
+
+    /// `1.0` or `0.0` for digital keys
+    #[inline]
+    pub fn depth_or_digital(&self) -> f32 {
+        self.depth
+            .unwrap_or(if self.is_pressed { 1.0 } else { 0.0 })
+    }
+}
+
+/// relative mouse movement in raw counts, before pointer acceleration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MotionMessage {
+    pub delta: [i32; 2],
+    pub instant: Instant,
+}
+
+impl MotionMessage {
+    pub fn new(delta: [i32; 2], instant: Instant) -> Self {
+        Self { delta, instant }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputMessage {
+    Key(KeyMessage),
+    Motion(MotionMessage),
+}
+
+impl InputMessage {
+    pub fn key(self) -> Option<KeyMessage> {
+        match self {
+            Self::Key(key_message) => Some(key_message),
+            Self::Motion(_) => None,
+        }
+    }
+}
+
+impl From<KeyMessage> for InputMessage {
+    fn from(key_message: KeyMessage) -> Self {
+        Self::Key(key_message)
+    }
+}
+
+impl From<MotionMessage> for InputMessage {
+    fn from(motion_message: MotionMessage) -> Self {
+        Self::Motion(motion_message)
+    }
 }
@@ -1,12 +1,21 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
-use crate::{key::Key, ucolor32::UColor32};
+use crate::{
+    device::DeviceId,
+    key::Key,
+    setting::{InputSetting, Palette},
+    ucolor32::UColor32,
+};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct KeyCounterProperty {
     /// relative to the center of key's frame
+    #[schemars(with = "crate::schema::Pos2")]
     pub position: egui::Pos2,
     pub font_size: f32,
+    #[schemars(with = "crate::schema::PaletteColor")]
     pub text_color: UColor32,
 }
 
@@ -20,16 +29,125 @@ impl Default for KeyCounterProperty {
     }
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum KeyDirection {
     #[default]
     Up,
     Down,
     Left,
     Right,
+    /// degrees clockwise from up
+    Angle(f32),
+}
+
+impl KeyDirection {
+    /// degrees clockwise from up
+    pub fn angle(self) -> f32 {
+        match self {
+            Self::Up => 0.0,
+            Self::Right => 90.0,
+            Self::Down => 180.0,
+            Self::Left => 270.0,
+            Self::Angle(angle) => angle,
+        }
+    }
+
+    /// the unit vector in window coordinates, where y points down
+    pub fn vector(self) -> egui::Vec2 {
+        match self {
+            Self::Up => egui::vec2(0.0, -1.0),
+            Self::Down => egui::vec2(0.0, 1.0),
+            Self::Left => egui::vec2(-1.0, 0.0),
+            Self::Right => egui::vec2(1.0, 0.0),
+            Self::Angle(angle) => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                egui::vec2(sin, -cos)
+            }
+        }
+    }
+}
+
+/// where the bars of a key travel, see [`KeyProperty::bar_axes`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarAxes {
+    /// on the center line of the bars, level with the center of the key
+    pub origin: egui::Pos2,
+    /// the unit vector the bars travel along
+    pub direction: egui::Vec2,
+    /// from `origin` along `direction` to where the bars start, past the key edge
+    pub start: f32,
+    /// half of the bar width
+    pub half_width: f32,
+    pub corner_radius: f32,
+}
+
+impl BarAxes {
+    /// the unit vector across the bars, to the right looking along them
+    #[inline]
+    pub fn across(&self) -> egui::Vec2 {
+        egui::vec2(-self.direction.y, self.direction.x)
+    }
+
+    /// `along` from where the bars start and `across` from the center line
+    pub fn point(&self, along: f32, across: f32) -> egui::Pos2 {
+        self.origin + self.direction * (self.start + along) + self.across() * across
+    }
+}
+
+/// the point of a key that is placed at the same point of the window, see [`KeyProperty::anchor`]
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema,
+)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub const ALL: [Self; 9] = [
+        Self::TopLeft,
+        Self::Top,
+        Self::TopRight,
+        Self::Left,
+        Self::Center,
+        Self::Right,
+        Self::BottomLeft,
+        Self::Bottom,
+        Self::BottomRight,
+    ];
+
+    /// `0.0`, `0.5` or `1.0` of the width and of the height
+    pub fn factor(self) -> egui::Vec2 {
+        let (x, y) = match self {
+            Self::TopLeft => (0.0, 0.0),
+            Self::Top => (0.5, 0.0),
+            Self::TopRight => (1.0, 0.0),
+            Self::Left => (0.0, 0.5),
+            Self::Center => (0.5, 0.5),
+            Self::Right => (1.0, 0.5),
+            Self::BottomLeft => (0.0, 1.0),
+            Self::Bottom => (0.5, 1.0),
+            Self::BottomRight => (1.0, 1.0),
+        };
+        egui::vec2(x, y)
+    }
 }
 
 impl KeyCounterProperty {
+    /// multiplies the lengths by `scale`
+    pub fn scale(&mut self, scale: f32) {
+        self.position = (self.position.to_vec2() * scale).to_pos2();
+        self.font_size *= scale;
+    }
+
     pub fn with_position(mut self, position: egui::Pos2) -> Self {
         self.position = position;
         self
@@ -47,44 +165,208 @@ impl KeyCounterProperty {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// a value that can be turned off, the value is kept while off
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[schemars(rename = "Toggle_for_{T}")]
+pub struct Toggle<T> {
+    pub enabled: bool,
+    pub value: T,
+}
+
+impl<T> Toggle<T> {
+    pub const fn new(enabled: bool, value: T) -> Self {
+        Self { enabled, value }
+    }
+
+    /// `None` if disabled
+    pub fn get(&self) -> Option<&T> {
+        self.enabled.then_some(&self.value)
+    }
+
+    /// `None` disables it and keeps the value
+    pub fn set(&mut self, value: Option<T>) {
+        match value {
+            Some(value) => *self = Self::new(true, value),
+            None => self.enabled = false,
+        }
+    }
+}
+
+/// how the depth of an analog key is shown, digital keys are either `0.0` or `1.0` deep
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema,
+)]
+pub enum DepthEffect {
+    #[default]
+    None,
+    /// the opacity of the pressed fill follows the current depth
+    PressedFill,
+    /// the width of a bar follows the deepest depth reached while pressed
+    BarWidth,
+}
+
+/// the width of the bars
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub enum BarWidth {
+    /// in pixels
+    Absolute(f32),
+    /// of the key's extent across the bars
+    Fraction(f32),
+}
+
+impl Default for BarWidth {
+    fn default() -> Self {
+        Self::Fraction(1.0)
+    }
+}
+
+/// where a bar narrower than the key sits, looking along the bars
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema,
+)]
+pub enum BarAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+/// the bars span the side of the key they start from by default
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct BarShape {
+    pub width: BarWidth,
+    pub align: BarAlign,
+    /// between the key edge and the bars
+    pub gap: f32,
+    pub corner_radius: f32,
+}
+
+impl BarShape {
+    /// multiplies the lengths by `scale`
+    pub fn scale(&mut self, scale: f32) {
+        if let BarWidth::Absolute(width) = &mut self.width {
+            *width *= scale;
+        }
+        self.gap *= scale;
+        self.corner_radius *= scale;
+    }
+
+    #[allow(dead_code)]
+    pub fn with_width(mut self, width: BarWidth) -> Self {
+        self.width = width;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_align(mut self, align: BarAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_gap(mut self, gap: f32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_corner_radius(mut self, corner_radius: f32) -> Self {
+        self.corner_radius = corner_radius;
+        self
+    }
+}
+
+/// how `key_bind` is matched against the input
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema,
+)]
+pub enum BindMode {
+    /// the key the keyboard layout produces
+    #[default]
+    VirtualKey,
+    /// the physical position of the key, the same on any keyboard layout
+    ScanCode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct KeyProperty {
     pub key_bind: Key,
+    #[serde(default)]
+    pub bind_mode: BindMode,
     pub key_text: String,
-    /// top-left corner
+    /// the offset of the key's `anchor` from the window's `anchor`, the top-left corner by default
+    #[schemars(with = "crate::schema::Pos2")]
     pub position: egui::Pos2,
+    #[serde(default)]
+    pub anchor: Anchor,
+    /// `position` is in percent of the window size instead of pixels
+    #[serde(default)]
+    pub percent_position: bool,
     pub width: f32,
     pub height: f32,
     pub thickness: f32,
     pub font_size: f32,
     pub bar_speed: f32,
-    pub max_distance: (bool, f32),
+    pub max_distance: Toggle<f32>,
+    #[schemars(with = "crate::schema::PaletteColor")]
     pub text_color: UColor32,
+    #[schemars(with = "crate::schema::PaletteColor")]
     pub frame_color: UColor32,
+    #[schemars(with = "crate::schema::PaletteColor")]
     pub pressed_color: UColor32,
     pub key_direction: KeyDirection,
-    pub fade_length: (bool, f32),
-    pub key_counter: (bool, KeyCounterProperty),
+    #[serde(default)]
+    pub bar_shape: BarShape,
+    pub fade_length: Toggle<f32>,
+    pub key_counter: Toggle<KeyCounterProperty>,
+    /// analog keys are pressed once their depth reaches this point
+    #[serde(default = "KeyProperty::default_actuation_point")]
+    pub actuation_point: f32,
+    #[serde(default)]
+    pub depth_effect: DepthEffect,
+    /// device interface name, only input from this device is shown, `None` for any device
+    #[serde(default)]
+    pub device_filter: Option<String>,
+    /// overrides [`InputSetting::debounce_ms`] when enabled
+    #[serde(default)]
+    pub debounce_ms: Toggle<f32>,
+    /// name of a [`KeyStyle`] in the setting, fields the key leaves out are taken from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    /// filled when the setting is loaded, the colors are saved as these names again
+    #[serde(default, skip_serializing_if = "PaletteRefs::is_empty")]
+    pub palette_refs: PaletteRefs,
 }
 
 impl Default for KeyProperty {
     fn default() -> Self {
         Self {
             key_bind: Default::default(),
+            bind_mode: Default::default(),
             key_text: "".into(),
             position: egui::Pos2::default(),
+            anchor: Default::default(),
+            percent_position: false,
             width: Self::DEFAULT_WIDTH,
             height: Self::DEFAULT_HEIGHT,
             thickness: Self::DEFAULT_THICKNESS,
             font_size: Self::DEFAULT_FONT_SIZE,
             bar_speed: Self::DEFAULT_BAR_SPEED,
-            max_distance: (false, 200.0),
+            max_distance: Toggle::new(false, 200.0),
             text_color: Self::DEFAULT_TEXT_COLOR,
             frame_color: Self::DEFAULT_FRAME_COLOR,
             pressed_color: Self::DEFAULT_PRESSED_COLOR,
             fade_length: Self::DEFAULT_FADE_LENGTH,
             key_direction: Default::default(),
-            key_counter: (false, KeyCounterProperty::default()),
+            bar_shape: Default::default(),
+            key_counter: Toggle::new(false, KeyCounterProperty::default()),
+            actuation_point: Self::DEFAULT_ACTUATION_POINT,
+            depth_effect: Default::default(),
+            device_filter: None,
+            debounce_ms: Toggle::new(false, 0.0),
+            style: None,
+            palette_refs: Default::default(),
         }
     }
 }
@@ -100,7 +382,19 @@ impl KeyProperty {
     /// same as `Color32::from_white_alpha(0x80)`
     pub const DEFAULT_PRESSED_COLOR: UColor32 = UColor32::WHITE.with_a(128);
 
-    pub const DEFAULT_FADE_LENGTH: (bool, f32) = (true, 50.0);
+    pub const DEFAULT_FADE_LENGTH: Toggle<f32> = Toggle::new(true, 50.0);
+
+    pub const DEFAULT_ACTUATION_POINT: f32 = 0.5;
+
+    fn default_actuation_point() -> f32 {
+        Self::DEFAULT_ACTUATION_POINT
+    }
+
+    /// whether an analog key at `depth` counts as pressed
+    #[inline]
+    pub fn is_actuated(&self, depth: f32) -> bool {
+        depth > 0.0 && depth >= self.actuation_point
+    }
 
     pub fn with_key_bind(mut self, key_bind: Key) -> Self {
         self.key_bind = key_bind;
@@ -145,11 +439,7 @@ impl KeyProperty {
 
     #[allow(dead_code)]
     pub fn with_max_distance(mut self, max_distance: Option<f32>) -> Self {
-        if let Some(max_distance) = max_distance {
-            self.max_distance = (true, max_distance);
-        } else {
-            self.max_distance.0 = false;
-        }
+        self.max_distance.set(max_distance);
         self
     }
 
@@ -170,11 +460,7 @@ impl KeyProperty {
     }
 
     pub fn with_fade_length(mut self, fade_length: Option<f32>) -> Self {
-        if let Some(fade_length) = fade_length {
-            self.fade_length = (true, fade_length);
-        } else {
-            self.fade_length.0 = false
-        }
+        self.fade_length.set(fade_length);
         self
     }
 
@@ -183,12 +469,515 @@ impl KeyProperty {
         self
     }
 
+    #[allow(dead_code)]
+    pub fn with_bar_shape(mut self, bar_shape: BarShape) -> Self {
+        self.bar_shape = bar_shape;
+        self
+    }
+
     pub fn with_key_counter(mut self, key_counter: Option<KeyCounterProperty>) -> Self {
-        if let Some(key_counter) = key_counter {
-            self.key_counter = (true, key_counter);
+        self.key_counter.set(key_counter);
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_bind_mode(mut self, bind_mode: BindMode) -> Self {
+        self.bind_mode = bind_mode;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_actuation_point(mut self, actuation_point: f32) -> Self {
+        self.actuation_point = actuation_point;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_depth_effect(mut self, depth_effect: DepthEffect) -> Self {
+        self.depth_effect = depth_effect;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_device_filter(mut self, device_filter: Option<String>) -> Self {
+        self.device_filter = device_filter;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_debounce_ms(mut self, debounce_ms: Option<f32>) -> Self {
+        self.debounce_ms.set(debounce_ms);
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_style(mut self, style: Option<String>) -> Self {
+        self.style = style;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_percent_position(mut self, percent_position: bool) -> Self {
+        self.percent_position = percent_position;
+        self
+    }
+
+    /// places the key in a window of `window_size` and multiplies the lengths by `scale`,
+    /// `position` is then the top-left corner in pixels
+    pub fn lay_out(&mut self, window_size: egui::Vec2, scale: f32) {
+        self.width *= scale;
+        self.height *= scale;
+        self.thickness *= scale;
+        self.font_size *= scale;
+        self.bar_speed *= scale;
+        self.max_distance.value *= scale;
+        self.fade_length.value *= scale;
+        self.key_counter.value.scale(scale);
+        self.bar_shape.scale(scale);
+        let offset = if self.percent_position {
+            self.position.to_vec2() * window_size / 100.0
         } else {
-            self.key_counter.0 = false;
+            self.position.to_vec2() * scale
+        };
+        let factor = self.anchor.factor();
+        let size = egui::vec2(self.width, self.height);
+        self.position = (factor * (window_size - size) + offset).to_pos2();
+        self.anchor = Anchor::TopLeft;
+        self.percent_position = false;
+    }
+
+    /// recolors the colors that reference `palette`, references to missing colors are dropped
+    pub fn apply_palette(&mut self, palette: &Palette) {
+        let Self {
+            text_color,
+            frame_color,
+            pressed_color,
+            key_counter,
+            palette_refs,
+            ..
+        } = self;
+        palette_refs.apply(
+            palette,
+            [
+                Some(text_color),
+                Some(frame_color),
+                Some(pressed_color),
+                Some(&mut key_counter.value.text_color),
+            ],
+        );
+    }
+
+    /// the bars start at the key edge facing `key_direction` and are shaped by `bar_shape`
+    /// within the key's extent across them, so by default the four axis directions span
+    /// exactly one side of the key
+    pub fn bar_axes(&self) -> BarAxes {
+        let BarShape {
+            width,
+            align,
+            gap,
+            corner_radius,
+        } = self.bar_shape;
+        let direction = self.key_direction.vector();
+        let half_size = egui::vec2(self.width, self.height) / 2.0;
+        let [x, y] = [direction.x.abs(), direction.y.abs()];
+        let key_half_width = y * half_size.x + x * half_size.y;
+        let half_width = match width {
+            BarWidth::Absolute(width) => width / 2.0,
+            BarWidth::Fraction(fraction) => key_half_width * fraction,
+        }
+        .max(0.0);
+        let offset = match align {
+            BarAlign::Left => half_width - key_half_width,
+            BarAlign::Center => 0.0,
+            BarAlign::Right => key_half_width - half_width,
+        };
+        let mut bar_axes = BarAxes {
+            origin: self.position + half_size,
+            direction,
+            start: x * half_size.x + y * half_size.y + gap,
+            half_width,
+            corner_radius: corner_radius.max(0.0),
+        };
+        bar_axes.origin += bar_axes.across() * offset;
+        bar_axes
+    }
+
+    /// `max_distance`, or from the key edge to where the bars leave a window of `window_size`,
+    /// the key is laid out, see [`Self::lay_out`]
+    pub fn bar_max_distance(&self, window_size: egui::Vec2) -> f32 {
+        if let Some(&max_distance) = self.max_distance.get() {
+            return max_distance;
+        }
+        let bar_axes = self.bar_axes();
+        let direction = bar_axes.direction;
+        // distance along the direction to the window edge, the bars are straight rays
+        let axis_exit = |position: f32, direction: f32, size: f32| {
+            if direction > f32::EPSILON {
+                (size - position) / direction
+            } else if direction < -f32::EPSILON {
+                -position / direction
+            } else {
+                f32::INFINITY
+            }
+        };
+        [-bar_axes.half_width, 0.0, bar_axes.half_width]
+            .into_iter()
+            .map(|across| {
+                let point = bar_axes.point(0.0, across);
+                axis_exit(point.x, direction.x, window_size.x).min(axis_exit(
+                    point.y,
+                    direction.y,
+                    window_size.y,
+                ))
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// the debounce window of this key, falls back to the global one
+    pub fn debounce(&self, input_setting: &InputSetting) -> Duration {
+        let debounce_ms = self
+            .debounce_ms
+            .get()
+            .copied()
+            .unwrap_or(input_setting.debounce_ms);
+        Duration::from_micros((debounce_ms.max(0.0) * 1_000.0).round() as u64)
+    }
+
+    #[inline]
+    pub fn device_filter_id(&self) -> Option<DeviceId> {
+        self.device_filter.as_deref().map(DeviceId::from_name)
+    }
+}
+
+/// shared fields of [`KeyProperty`], `None` fields are left to the key or the base style
+///
+/// styles are resolved when a setting is loaded, so renderers only see flat key properties
+///
+/// what a key is bound to and where it sits stay per key, a style can not set the key binding,
+/// bind mode, text, position, anchor, device filter or debounce
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct KeyStyle {
+    pub name: String,
+    /// name of the base style
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thickness: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_speed: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_distance: Option<Toggle<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<crate::schema::PaletteColor>")]
+    pub text_color: Option<UColor32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<crate::schema::PaletteColor>")]
+    pub frame_color: Option<UColor32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<crate::schema::PaletteColor>")]
+    pub pressed_color: Option<UColor32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_direction: Option<KeyDirection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_shape: Option<BarShape>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade_length: Option<Toggle<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_counter: Option<Toggle<KeyCounterProperty>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actuation_point: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth_effect: Option<DepthEffect>,
+    #[serde(default, skip_serializing_if = "PaletteRefs::is_empty")]
+    pub palette_refs: PaletteRefs,
+}
+
+impl KeyStyle {
+    /// overwrites the fields of `key_property` this style sets, and references this style
+    pub fn apply(&self, key_property: &mut KeyProperty) {
+        macro_rules! apply {
+            ($($field:ident),*) => {
+                $(if let Some(value) = &self.$field {
+                    key_property.$field = value.clone();
+                })*
+            };
+        }
+        apply!(
+            width,
+            height,
+            thickness,
+            font_size,
+            bar_speed,
+            max_distance,
+            text_color,
+            frame_color,
+            pressed_color,
+            key_direction,
+            bar_shape,
+            fade_length,
+            key_counter,
+            actuation_point,
+            depth_effect
+        );
+        let refs = &self.palette_refs;
+        let key_refs = &mut key_property.palette_refs;
+        if self.text_color.is_some() {
+            key_refs.text_color = refs.text_color.clone();
+        }
+        if self.frame_color.is_some() {
+            key_refs.frame_color = refs.frame_color.clone();
+        }
+        if self.pressed_color.is_some() {
+            key_refs.pressed_color = refs.pressed_color.clone();
+        }
+        if self.key_counter.is_some() {
+            key_refs.counter_text_color = refs.counter_text_color.clone();
+        }
+        key_property.style = Some(self.name.clone());
+    }
+
+    /// like [`KeyProperty::apply_palette`]
+    pub fn apply_palette(&mut self, palette: &Palette) {
+        let Self {
+            text_color,
+            frame_color,
+            pressed_color,
+            key_counter,
+            palette_refs,
+            ..
+        } = self;
+        palette_refs.apply(
+            palette,
+            [
+                text_color.as_mut(),
+                frame_color.as_mut(),
+                pressed_color.as_mut(),
+                key_counter
+                    .as_mut()
+                    .map(|key_counter| &mut key_counter.value.text_color),
+            ],
+        );
+    }
+}
+
+/// names of [`Palette`] colors that colors follow, `None` for a color of its own
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct PaletteRefs {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressed_color: Option<String>,
+    /// the text color of the key counter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counter_text_color: Option<String>,
+}
+
+impl PaletteRefs {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `colors` in the order of the fields
+    fn apply(&mut self, palette: &Palette, colors: [Option<&mut UColor32>; 4]) {
+        [
+            &mut self.text_color,
+            &mut self.frame_color,
+            &mut self.pressed_color,
+            &mut self.counter_text_color,
+        ]
+        .into_iter()
+        .zip(colors)
+        .for_each(|(name, color)| {
+            let palette_color = name.as_deref().map(|name| palette.colors.get(name));
+            match (palette_color, color) {
+                (Some(Some(palette_color)), Some(color)) => *color = *palette_color,
+                (Some(None), _) => *name = None,
+                _ => {}
+            }
+        });
+    }
+}
+
+/// a counter that sums the press counts of a group of keys
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct KeyGroupProperty {
+    pub name: String,
+    /// count every key bound in the setting, `keys` is ignored
+    pub all_keys: bool,
+    pub keys: Vec<Key>,
+    /// absolute position of the counter's center
+    #[schemars(with = "crate::schema::Pos2")]
+    pub position: egui::Pos2,
+    pub font_size: f32,
+    pub text_color: UColor32,
+}
+
+impl Default for KeyGroupProperty {
+    fn default() -> Self {
+        Self {
+            name: "".into(),
+            all_keys: false,
+            keys: vec![],
+            position: egui::Pos2::default(),
+            font_size: KeyProperty::DEFAULT_FONT_SIZE,
+            text_color: KeyProperty::DEFAULT_TEXT_COLOR,
         }
+    }
+}
+
+impl KeyGroupProperty {
+    /// multiplies the lengths by `scale`
+    pub fn scale(&mut self, scale: f32) {
+        self.position = (self.position.to_vec2() * scale).to_pos2();
+        self.font_size *= scale;
+    }
+
+    #[allow(dead_code)]
+    pub fn with_all_keys(mut self, all_keys: bool) -> Self {
+        self.all_keys = all_keys;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_keys(mut self, keys: Vec<Key>) -> Self {
+        self.keys = keys;
         self
     }
 }
+
+/// visualizes mouse movement with a trail, a speed readout and a distance readout
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct MouseMotionProperty {
+    /// absolute position of the trail's head, the trail follows behind it
+    #[schemars(with = "crate::schema::Pos2")]
+    pub position: egui::Pos2,
+    /// pixels per mouse count
+    pub trail_scale: f32,
+    pub trail_duration_ms: f32,
+    pub trail_thickness: f32,
+    pub trail_color: UColor32,
+    /// mouse counts per second, `position` is relative to the trail's head
+    pub speed_readout: Toggle<KeyCounterProperty>,
+    /// mouse counts moved since start, `position` is relative to the trail's head
+    pub distance_readout: Toggle<KeyCounterProperty>,
+}
+
+impl Default for MouseMotionProperty {
+    fn default() -> Self {
+        Self {
+            position: egui::Pos2::default(),
+            trail_scale: Self::DEFAULT_TRAIL_SCALE,
+            trail_duration_ms: Self::DEFAULT_TRAIL_DURATION_MS,
+            trail_thickness: KeyProperty::DEFAULT_THICKNESS,
+            trail_color: KeyProperty::DEFAULT_PRESSED_COLOR,
+            speed_readout: Toggle::new(
+                true,
+                KeyCounterProperty::default().with_position(egui::pos2(0.0, 20.0)),
+            ),
+            distance_readout: Toggle::new(
+                true,
+                KeyCounterProperty::default().with_position(egui::pos2(0.0, 40.0)),
+            ),
+        }
+    }
+}
+
+impl MouseMotionProperty {
+    pub const DEFAULT_TRAIL_SCALE: f32 = 0.1;
+    pub const DEFAULT_TRAIL_DURATION_MS: f32 = 300.0;
+
+    pub fn trail_duration(&self) -> Duration {
+        Duration::from_secs_f32(self.trail_duration_ms.max(0.0) / 1_000.0)
+    }
+
+    /// multiplies the lengths by `scale`
+    pub fn scale(&mut self, scale: f32) {
+        self.position = (self.position.to_vec2() * scale).to_pos2();
+        self.trail_scale *= scale;
+        self.trail_thickness *= scale;
+        self.speed_readout.value.scale(scale);
+        self.distance_readout.value.scale(scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bars_reach_the_window_edge() {
+        let window_size = egui::vec2(600.0, 400.0);
+        let key_property = KeyProperty::default()
+            .with_position(egui::pos2(100.0, 200.0))
+            .with_width(50.0)
+            .with_height(20.0);
+        let max_distance = |key_direction| {
+            key_property
+                .clone()
+                .with_key_direction(key_direction)
+                .bar_max_distance(window_size)
+        };
+        assert_eq!(max_distance(KeyDirection::Up), 200.0);
+        assert_eq!(max_distance(KeyDirection::Down), 180.0);
+        assert_eq!(max_distance(KeyDirection::Left), 100.0);
+        assert_eq!(max_distance(KeyDirection::Right), 450.0);
+
+        let bar_axes = key_property.bar_axes();
+        assert_eq!(bar_axes.point(0.0, 0.0), egui::pos2(125.0, 200.0));
+        assert_eq!(bar_axes.half_width, 25.0);
+
+        // the farther corner of the bars leaves the window last
+        let diagonal = max_distance(KeyDirection::Angle(45.0));
+        let bar_axes = key_property
+            .with_key_direction(KeyDirection::Angle(45.0))
+            .bar_axes();
+        let far_corner = [-bar_axes.half_width, bar_axes.half_width]
+            .map(|across| bar_axes.point(diagonal, across))
+            .into_iter()
+            .max_by(|a, b| a.y.total_cmp(&b.y))
+            .expect("unreachable");
+        assert!(far_corner.y.abs() < 1e-3);
+    }
+
+    #[test]
+    fn aligned_bars() {
+        let key_property = KeyProperty::default()
+            .with_position(egui::pos2(100.0, 200.0))
+            .with_width(50.0)
+            .with_height(20.0);
+        let bar_axes = |bar_shape| key_property.clone().with_bar_shape(bar_shape).bar_axes();
+
+        let left = bar_axes(
+            BarShape::default()
+                .with_width(BarWidth::Fraction(0.4))
+                .with_align(BarAlign::Left)
+                .with_gap(5.0),
+        );
+        assert_eq!(left.half_width, 10.0);
+        assert_eq!(left.start, 15.0);
+        assert_eq!(left.point(0.0, -left.half_width), egui::pos2(100.0, 195.0));
+
+        let right = bar_axes(
+            BarShape::default()
+                .with_width(BarWidth::Absolute(10.0))
+                .with_align(BarAlign::Right),
+        );
+        assert_eq!(right.half_width, 5.0);
+        assert_eq!(right.point(0.0, right.half_width), egui::pos2(150.0, 200.0));
+    }
+}
@@ -3,49 +3,249 @@ pub mod key_draw_cache;
 pub mod key_handler;
 pub mod key_message;
 pub mod key_property;
+pub mod mouse_motion;
 
-use std::time::Instant;
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{self, AtomicBool},
+    },
+    time::{Duration, Instant},
+};
 
 use crate::{
-    key_overlay_core::{key_handler::KeyHandler, key_message::KeyMessage},
-    setting::Setting,
+    control_server::{ControlCommand, ControlRequest, ControlServer},
+    event_server::{Counts, EventServer},
+    key_overlay_core::{
+        key_handler::KeyHandler,
+        key_message::{InputMessage, KeyMessage},
+        mouse_motion::MouseMotion,
+    },
+    osc_output::OscOutput,
+    setting::{OutputSetting, Setting},
 };
 
 use sak_rs::sync::mpmc::queue::BoundedReceiver as MpscReceiver;
 
 pub struct KeyOverlayCore {
-    key_messages_buffer: Vec<KeyMessage>,
-    keys_receiver: MpscReceiver<KeyMessage>,
+    key_messages_buffer: Vec<InputMessage>,
+    keys_receiver: MpscReceiver<InputMessage>,
     key_handler: KeyHandler,
+    mouse_motion: MouseMotion,
+    event_server: Option<EventServer>,
+    control_server: Option<ControlServer>,
+    osc_output: Option<OscOutput>,
+    /// presses and motion are dropped while paused, releases still come through
+    paused: bool,
+    /// presses within [`Self::KPS_WINDOW`]
+    press_instants: VecDeque<Instant>,
+    /// since started
+    presses: u64,
+    /// loaded by the control server, applied by the app
+    pending_setting: Option<Box<Setting>>,
+    /// whether the setting has mouse motions, shared with [`crate::msg_hook::HookShared`]
+    send_motion: Arc<AtomicBool>,
 }
 
 impl KeyOverlayCore {
     const DEFAULT_BUFFER_CAPACITY: usize = 64;
+    pub const KPS_WINDOW: Duration = Duration::from_secs(1);
 
-    pub fn new(setting: Setting, keys_receiver: MpscReceiver<KeyMessage>) -> Self {
+    pub fn new(setting: Setting, keys_receiver: MpscReceiver<InputMessage>) -> Self {
+        let send_motion = Arc::new(AtomicBool::new(!setting.mouse_motions.is_empty()));
         Self {
             key_messages_buffer: Vec::with_capacity(Self::DEFAULT_BUFFER_CAPACITY),
             keys_receiver,
+            mouse_motion: MouseMotion::new(&setting),
             key_handler: KeyHandler::new(setting),
+            event_server: None,
+            control_server: None,
+            osc_output: None,
+            paused: false,
+            press_instants: VecDeque::with_capacity(Self::DEFAULT_BUFFER_CAPACITY),
+            presses: 0,
+            pending_setting: None,
+            send_motion,
         }
     }
 
+    /// starts the outputs enabled in `output_setting`, the preview of the editor has none
+    ///
+    /// `profile_path` is the setting file the control server reloads
+    pub fn start_outputs(
+        &mut self,
+        output_setting: &OutputSetting,
+        profile_path: PathBuf,
+        request_redraw: Box<dyn FnMut() + Send>,
+    ) {
+        self.event_server = EventServer::start_or_warn(&output_setting.event_server);
+        self.osc_output = OscOutput::start_or_warn(&output_setting.osc);
+        self.control_server = ControlServer::start_or_warn(
+            &output_setting.control_server,
+            profile_path,
+            request_redraw,
+        );
+        self.send_counts();
+    }
+
     pub fn update(&mut self, instant_now: Instant) {
+        if self.paused {
+            // a key held when pausing would otherwise stay pressed
+            let releases = self.keys_receiver.try_iter().filter(|input_message| {
+                matches!(input_message, InputMessage::Key(key_message) if !key_message.is_pressed)
+            });
+            self.key_messages_buffer.extend(releases);
+        } else {
+            self.key_messages_buffer
+                .extend(self.keys_receiver.try_iter());
+        }
+        // the kps sent with each key counts the presses before it
+        self.remove_outer_presses(instant_now);
+        let mut key_updated = false;
         self.key_messages_buffer
-            .extend(self.keys_receiver.try_iter());
-        self.key_messages_buffer.drain(..).for_each(|key_message| {
-            self.key_handler.update(key_message);
-        });
+            .drain(..)
+            .for_each(|input_message| match input_message {
+                InputMessage::Key(key_message) => {
+                    key_updated = true;
+                    let key_update = self.key_handler.update(key_message);
+                    if key_update.pressed {
+                        self.press_instants.push_back(key_message.instant);
+                        self.presses += 1;
+                    }
+                    // repeats of a held key and input the properties filter out are not events
+                    if let (Some(event_server), Some(is_pressed)) =
+                        (&self.event_server, key_update.changed)
+                    {
+                        event_server.send_key(KeyMessage {
+                            is_pressed,
+                            ..key_message
+                        });
+                    }
+                    let kps = self.press_instants.len();
+                    self.osc_output
+                        .as_mut()
+                        .map(|osc_output| osc_output.send_key(&key_message, kps));
+                }
+                InputMessage::Motion(motion_message) => self.mouse_motion.update(motion_message),
+            });
+        if key_updated {
+            self.send_counts();
+        }
+        self.key_handler.release_wheel_keys(instant_now);
         self.key_handler.remove_outer_bar(instant_now);
+        self.mouse_motion.remove_outer_motion(instant_now);
+        self.handle_control_requests();
+    }
+
+    fn remove_outer_presses(&mut self, instant_now: Instant) {
+        let Some(dead_line) = instant_now.checked_sub(Self::KPS_WINDOW) else {
+            return;
+        };
+        while let Some(instant) = self.press_instants.front() {
+            if *instant < dead_line {
+                self.press_instants.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn handle_control_requests(&mut self) {
+        let Some(control_server) = &self.control_server else {
+            return;
+        };
+        let requests: Vec<_> = control_server.try_iter().collect();
+        requests
+            .into_iter()
+            .for_each(|ControlRequest { command, reply }| {
+                let result = match command {
+                    ControlCommand::LoadSetting(setting) => {
+                        self.pending_setting = Some(setting);
+                        serde_json::Value::Null
+                    }
+                    ControlCommand::ResetCounters => {
+                        self.key_handler.reset_counters();
+                        self.send_counts();
+                        serde_json::Value::Null
+                    }
+                    ControlCommand::SetPaused(paused) => {
+                        self.paused = paused.unwrap_or(!self.paused);
+                        serde_json::json!(self.paused)
+                    }
+                    ControlCommand::Query => {
+                        let Counts { keys, groups } = Counts::new(&self.key_handler);
+                        serde_json::json!({
+                            "paused": self.paused,
+                            "kps": self.kps(),
+                            "keys": keys,
+                            "groups": groups,
+                        })
+                    }
+                };
+                reply.send(result);
+            });
+    }
+
+    /// restores counts saved from [`Counts`]
+    pub fn restore_counts(&mut self, counts: &Counts) {
+        self.key_handler.restore_counts(
+            counts
+                .keys
+                .iter()
+                .map(|key_count| (key_count.key, key_count.count)),
+        );
+        self.key_handler.restore_group_counts(
+            counts
+                .groups
+                .iter()
+                .map(|group_count| (&*group_count.name, group_count.count)),
+        );
+    }
+
+    /// a setting loaded by the control server, to be reloaded by the app
+    #[inline]
+    pub fn take_pending_setting(&mut self) -> Option<Box<Setting>> {
+        self.pending_setting.take()
+    }
+
+    /// presses within the last second
+    #[inline]
+    pub fn kps(&self) -> usize {
+        self.press_instants.len()
     }
 
     #[inline]
     pub fn reload(&mut self, setting: &Setting) {
         self.key_handler.reload(setting);
+        self.mouse_motion.reload(setting);
+        self.send_motion
+            .store(!setting.mouse_motions.is_empty(), atomic::Ordering::Relaxed);
+        self.send_counts();
     }
 
+    fn send_counts(&self) {
+        self.event_server.as_ref().map(|event_server| {
+            event_server.send_counts(&self.key_handler, &self.press_instants, self.presses)
+        });
+    }
+
+    /// the same setting laid out for a new window size, see [`KeyHandler::relayout`]
+    #[inline]
+    pub fn relayout(&mut self, setting: &Setting) {
+        self.key_handler.relayout(setting);
+        self.mouse_motion.reload(setting);
+    }
+
+    /// for [`crate::msg_hook::HookShared::send_motion`], follows reloads
     #[inline]
-    pub fn keys_receiver(&self) -> &MpscReceiver<KeyMessage> {
+    pub fn send_motion(&self) -> Arc<AtomicBool> {
+        self.send_motion.clone()
+    }
+
+    #[inline]
+    pub fn keys_receiver(&self) -> &MpscReceiver<InputMessage> {
         &self.keys_receiver
     }
 
@@ -54,8 +254,53 @@ impl KeyOverlayCore {
         &self.key_handler
     }
 
+    #[inline]
+    pub fn mouse_motion(&self) -> &MouseMotion {
+        &self.mouse_motion
+    }
+
     #[inline]
     pub fn need_repaint(&self) -> bool {
-        self.key_handler.need_repaint()
+        self.key_handler.need_repaint() || self.mouse_motion.need_repaint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sak_rs::sync::mpmc;
+
+    use crate::key::Key;
+
+    use super::*;
+
+    #[test]
+    fn releases_while_paused() {
+        let (keys_sender, keys_receiver) = mpmc::queue::bounded(16);
+        let mut core = KeyOverlayCore::new(Setting::default_zxc(), keys_receiver);
+        let index_of = |core: &KeyOverlayCore, key| {
+            core.key_handler()
+                .key_properties()
+                .iter()
+                .position(|key_property| key_property.key_bind == key)
+                .expect("unreachable")
+        };
+        let [z, x] = [Key::KeyZ, Key::KeyX].map(|key| index_of(&core, key));
+
+        let instant = Instant::now();
+        keys_sender.force_send(KeyMessage::new(Key::KeyZ, true, instant).into());
+        core.update(instant);
+        core.paused = true;
+        [(Key::KeyZ, false), (Key::KeyX, true)]
+            .into_iter()
+            .for_each(|(key, is_pressed)| {
+                keys_sender.force_send(KeyMessage::new(key, is_pressed, instant).into());
+            });
+        core.update(instant);
+
+        let key_draw_caches = core.key_handler().key_draw_caches();
+        assert!(key_draw_caches[z].begin_hold_instant.is_none());
+        assert_eq!(key_draw_caches[z].bar_queue.len(), 1);
+        assert!(key_draw_caches[x].begin_hold_instant.is_none());
+        assert_eq!(key_draw_caches[x].count, 0);
     }
 }
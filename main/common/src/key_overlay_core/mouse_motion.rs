@@ -0,0 +1,167 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    key_overlay_core::{key_message::MotionMessage, key_property::MouseMotionProperty},
+    setting::Setting,
+};
+
+/// recent mouse movement, shared by every mouse motion element
+pub struct MouseMotion {
+    mouse_motion_properties: Box<[MouseMotionProperty]>,
+    /// `(instant, delta)`, oldest first
+    history: VecDeque<(Instant, egui::Vec2)>,
+    /// the longest trail duration, at least [`Self::SPEED_WINDOW`]
+    history_duration: Duration,
+    total_distance: f64,
+}
+
+impl MouseMotion {
+    /// speed is averaged over this window
+    pub const SPEED_WINDOW: Duration = Duration::from_millis(100);
+
+    const DEFAULT_HISTORY_CAPACITY: usize = 1024;
+
+    pub fn new(setting: &Setting) -> Self {
+        let mut mouse_motion = Self {
+            mouse_motion_properties: Default::default(),
+            history: VecDeque::with_capacity(Self::DEFAULT_HISTORY_CAPACITY),
+            history_duration: Self::SPEED_WINDOW,
+            total_distance: 0.0,
+        };
+        mouse_motion.reload(setting);
+        mouse_motion
+    }
+
+    /// keeps the history and the total distance
+    pub fn reload(&mut self, setting: &Setting) {
+        self.mouse_motion_properties = setting.mouse_motions.clone().into_boxed_slice();
+        self.history_duration = self
+            .mouse_motion_properties
+            .iter()
+            .map(MouseMotionProperty::trail_duration)
+            .fold(Self::SPEED_WINDOW, Duration::max);
+    }
+
+    pub fn update(&mut self, motion_message: MotionMessage) {
+        if self.mouse_motion_properties.is_empty() {
+            return;
+        }
+        let [dx, dy] = motion_message.delta;
+        let delta = egui::vec2(dx as f32, dy as f32);
+        self.total_distance += (dx as f64).hypot(dy as f64);
+        self.history.push_back((motion_message.instant, delta));
+    }
+
+    pub fn remove_outer_motion(&mut self, instant_now: Instant) {
+        let Some(dead_line) = instant_now.checked_sub(self.history_duration) else {
+            return;
+        };
+        while let Some((instant, _)) = self.history.front() {
+            if *instant < dead_line {
+                self.history.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    #[inline]
+    pub fn mouse_motion_properties(&self) -> &[MouseMotionProperty] {
+        &self.mouse_motion_properties
+    }
+
+    /// mouse counts moved since start
+    #[inline]
+    pub fn total_distance(&self) -> f64 {
+        self.total_distance
+    }
+
+    /// mouse counts per second, averaged over [`Self::SPEED_WINDOW`]
+    pub fn speed(&self, instant_now: Instant) -> f32 {
+        let distance: f32 = self
+            .history
+            .iter()
+            .rev()
+            .take_while(|(instant, _)| {
+                instant_now.saturating_duration_since(*instant) <= Self::SPEED_WINDOW
+            })
+            .map(|(_, delta)| delta.length())
+            .sum();
+        distance / Self::SPEED_WINDOW.as_secs_f32()
+    }
+
+    /// trail points relative to the current mouse position, newest first,
+    /// `(offset, age)` where age goes from `0.0` to `1.0` over `trail_duration`
+    pub fn trail_iter(
+        &self,
+        instant_now: Instant,
+        trail_duration: Duration,
+    ) -> impl Iterator<Item = (egui::Vec2, f32)> + Clone {
+        let trail_duration_secs = trail_duration.as_secs_f32();
+        let points_iter = self
+            .history
+            .iter()
+            .rev()
+            .map(move |(instant, delta)| {
+                let age = instant_now
+                    .saturating_duration_since(*instant)
+                    .as_secs_f32()
+                    / trail_duration_secs;
+                (*delta, age)
+            })
+            .take_while(|(_, age)| *age <= 1.0)
+            .scan(egui::Vec2::ZERO, |offset, (delta, age)| {
+                *offset -= delta;
+                Some((*offset, age))
+            });
+        std::iter::once((egui::Vec2::ZERO, 0.0)).chain(points_iter)
+    }
+
+    #[inline]
+    pub fn need_repaint(&self) -> bool {
+        !self.history.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trail_speed_and_distance() {
+        let setting = Setting {
+            mouse_motions: vec![MouseMotionProperty::default()],
+            ..Setting::default_zxc()
+        };
+        let mut mouse_motion = MouseMotion::new(&setting);
+        let instant = Instant::now();
+        [[3, 4], [-6, -8]]
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, delta)| {
+                let instant = instant + Duration::from_millis(10 * i as u64);
+                mouse_motion.update(MotionMessage::new(delta, instant));
+            });
+        assert_eq!(mouse_motion.total_distance(), 15.0);
+
+        let instant_now = instant + Duration::from_millis(50);
+        assert_eq!(mouse_motion.speed(instant_now), 150.0);
+        let offsets: Vec<_> = mouse_motion
+            .trail_iter(instant_now, Duration::from_secs(1))
+            .map(|(offset, _)| offset)
+            .collect();
+        assert_eq!(
+            offsets,
+            [egui::Vec2::ZERO, egui::vec2(6.0, 8.0), egui::vec2(3.0, 4.0)]
+        );
+
+        let instant_now = instant + Duration::from_secs(1);
+        mouse_motion.remove_outer_motion(instant_now);
+        assert!(!mouse_motion.need_repaint());
+        assert_eq!(mouse_motion.speed(instant_now), 0.0);
+        assert_eq!(mouse_motion.total_distance(), 15.0);
+    }
+}
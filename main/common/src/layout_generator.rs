@@ -0,0 +1,427 @@
+//! generates the keys of common keyboard form factors,
+//! sizes are in units, one unit is the width of a letter key
+
+use egui::{Pos2, pos2};
+
+use crate::{
+    key::Key,
+    key_overlay_core::key_property::{KeyDirection, KeyProperty},
+    setting::{Setting, WindowSetting},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutKind {
+    /// full size with an ANSI main block
+    Ansi,
+    /// full size with an ISO main block
+    Iso,
+    /// ANSI without the numpad
+    Tkl,
+    /// the ANSI main block only
+    Sixty,
+    Numpad,
+    /// the left hand block of the main block
+    Wasd,
+    /// a row of keys bound like osu!mania, from 1 to [`LayoutKind::MAX_MANIA_KEYS`] keys
+    Mania(usize),
+}
+
+impl LayoutKind {
+    pub const MAX_MANIA_KEYS: usize = 10;
+
+    pub fn name(self) -> String {
+        match self {
+            Self::Ansi => "全尺寸 ANSI".into(),
+            Self::Iso => "全尺寸 ISO".into(),
+            Self::Tkl => "TKL".into(),
+            Self::Sixty => "60%".into(),
+            Self::Numpad => "小键盘".into(),
+            Self::Wasd => "WASD".into(),
+            Self::Mania(count) => format!("{count}K"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutOptions {
+    /// pixels per unit
+    pub unit: f32,
+    /// pixels between neighboring keys
+    pub gap: f32,
+    /// top-left corner of the layout
+    pub origin: Pos2,
+    pub key_direction: KeyDirection,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            unit: Self::DEFAULT_UNIT,
+            gap: Self::DEFAULT_GAP,
+            origin: pos2(Self::DEFAULT_UNIT / 2.0, Self::DEFAULT_UNIT * 4.0),
+            key_direction: Default::default(),
+        }
+    }
+}
+
+impl LayoutOptions {
+    pub const DEFAULT_UNIT: f32 = 60.0;
+    pub const DEFAULT_GAP: f32 = 4.0;
+
+    #[allow(dead_code)]
+    pub fn with_unit(mut self, unit: f32) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_gap(mut self, gap: f32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_origin(mut self, origin: Pos2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_key_direction(mut self, key_direction: KeyDirection) -> Self {
+        self.key_direction = key_direction;
+        self
+    }
+}
+
+/// a key in units
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct UnitKey {
+    key: Key,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// keys placed from left to right
+struct Row {
+    x: f32,
+    y: f32,
+    keys: Vec<UnitKey>,
+}
+
+impl Row {
+    fn new(x: f32, y: f32) -> Self {
+        Self { x, y, keys: vec![] }
+    }
+
+    fn key(self, key: Key) -> Self {
+        self.wide(key, 1.0)
+    }
+
+    fn keys(self, keys: impl IntoIterator<Item = Key>) -> Self {
+        keys.into_iter().fold(self, Self::key)
+    }
+
+    fn wide(self, key: Key, width: f32) -> Self {
+        self.sized(key, width, 1.0)
+    }
+
+    fn sized(mut self, key: Key, width: f32, height: f32) -> Self {
+        self.keys.push(UnitKey {
+            key,
+            x: self.x,
+            y: self.y,
+            width,
+            height,
+        });
+        self.x += width;
+        self
+    }
+
+    fn skip(mut self, width: f32) -> Self {
+        self.x += width;
+        self
+    }
+}
+
+const FUNCTION_ROW_Y: f32 = 0.0;
+/// the main block is half a unit below the function row
+const MAIN_BLOCK_Y: f32 = 1.5;
+const NAVIGATION_X: f32 = 15.25;
+const NUMPAD_X: f32 = 18.5;
+
+fn function_row() -> Vec<UnitKey> {
+    use Key::*;
+    Row::new(0.0, FUNCTION_ROW_Y)
+        .key(Escape)
+        .skip(1.0)
+        .keys([F1, F2, F3, F4])
+        .skip(0.5)
+        .keys([F5, F6, F7, F8])
+        .skip(0.5)
+        .keys([F9, F10, F11, F12])
+        .skip(0.25)
+        .keys([PrintScreen, ScrollLock, Pause])
+        .keys
+}
+
+/// the letter rows shared by ANSI and ISO, from the number row to the bottom row
+fn main_block(iso: bool, x: f32, y: f32) -> Vec<UnitKey> {
+    use Key::*;
+    let number_row = Row::new(x, y)
+        .key(BackTick)
+        .keys([Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0])
+        .keys([Minus, Equal])
+        .wide(Backspace, 2.0);
+    let top_row = Row::new(x, y + 1.0)
+        .wide(Tab, 1.5)
+        .keys([KeyQ, KeyW, KeyE, KeyR, KeyT, KeyY, KeyU, KeyI, KeyO, KeyP])
+        .keys([LeftSquareBracket, RightSquareBracket]);
+    let home_row = Row::new(x, y + 2.0)
+        .wide(CapsLock, 1.75)
+        .keys([KeyA, KeyS, KeyD, KeyF, KeyG, KeyH, KeyJ, KeyK, KeyL])
+        .keys([Semicolon, Apostrophe]);
+    let bottom_letter_row = Row::new(x, y + 3.0);
+    let letters = [KeyZ, KeyX, KeyC, KeyV, KeyB, KeyN, KeyM];
+    let punctuations = [Comma, Period, ForwardSlash];
+    let (top_row, home_row, bottom_letter_row) = if iso {
+        // the iso enter spans two rows, drawn as its lower part
+        (
+            top_row.skip(0.25).sized(Enter, 1.25, 2.0),
+            home_row.key(BackwardSlash),
+            bottom_letter_row.wide(LeftShift, 1.25).key(IntlBackslash),
+        )
+    } else {
+        (
+            top_row.wide(BackwardSlash, 1.5),
+            home_row.wide(Enter, 2.25),
+            bottom_letter_row.wide(LeftShift, 2.25),
+        )
+    };
+    let bottom_letter_row = bottom_letter_row
+        .keys(letters)
+        .keys(punctuations)
+        .wide(RightShift, 2.75);
+    let bottom_row = Row::new(x, y + 4.0)
+        .wide(LeftControl, 1.25)
+        .wide(LeftWin, 1.25)
+        .wide(LeftAlt, 1.25)
+        .wide(Space, 6.25)
+        .wide(RightAlt, 1.25)
+        .wide(RightWin, 1.25)
+        .wide(Apps, 1.25)
+        .wide(RightControl, 1.25);
+    [number_row, top_row, home_row, bottom_letter_row, bottom_row]
+        .into_iter()
+        .flat_map(|row| row.keys)
+        .collect()
+}
+
+fn navigation_cluster() -> Vec<UnitKey> {
+    use Key::*;
+    [
+        Row::new(NAVIGATION_X, MAIN_BLOCK_Y).keys([Insert, Home, PageUp]),
+        Row::new(NAVIGATION_X, MAIN_BLOCK_Y + 1.0).keys([Delete, End, PageDown]),
+        Row::new(NAVIGATION_X + 1.0, MAIN_BLOCK_Y + 3.0).key(Up),
+        Row::new(NAVIGATION_X, MAIN_BLOCK_Y + 4.0).keys([Left, Down, Right]),
+    ]
+    .into_iter()
+    .flat_map(|row| row.keys)
+    .collect()
+}
+
+fn numpad(x: f32, y: f32) -> Vec<UnitKey> {
+    use Key::*;
+    [
+        Row::new(x, y).keys([NumLock, NumpadDivide, NumpadMultiply, NumpadMinus]),
+        Row::new(x, y + 1.0)
+            .keys([Numpad7, Numpad8, Numpad9])
+            .sized(NumpadPlus, 1.0, 2.0),
+        Row::new(x, y + 2.0).keys([Numpad4, Numpad5, Numpad6]),
+        Row::new(x, y + 3.0)
+            .keys([Numpad1, Numpad2, Numpad3])
+            .sized(NumpadEnter, 1.0, 2.0),
+        Row::new(x, y + 4.0).wide(Numpad0, 2.0).key(NumpadDot),
+    ]
+    .into_iter()
+    .flat_map(|row| row.keys)
+    .collect()
+}
+
+fn wasd() -> Vec<UnitKey> {
+    use Key::*;
+    [
+        Row::new(0.0, 0.0)
+            .key(BackTick)
+            .keys([Key1, Key2, Key3, Key4]),
+        Row::new(0.0, 1.0)
+            .wide(Tab, 1.5)
+            .keys([KeyQ, KeyW, KeyE, KeyR]),
+        Row::new(0.0, 2.0)
+            .wide(CapsLock, 1.75)
+            .keys([KeyA, KeyS, KeyD, KeyF]),
+        Row::new(0.0, 3.0)
+            .wide(LeftShift, 2.25)
+            .keys([KeyZ, KeyX, KeyC, KeyV]),
+        Row::new(0.0, 4.0)
+            .wide(LeftControl, 1.25)
+            .wide(LeftWin, 1.25)
+            .wide(LeftAlt, 1.25)
+            .wide(Space, 2.5),
+    ]
+    .into_iter()
+    .flat_map(|row| row.keys)
+    .collect()
+}
+
+/// the common osu!mania bindings, the count is clamped to `1..=MAX_MANIA_KEYS`
+fn mania(count: usize) -> Vec<UnitKey> {
+    use Key::*;
+    let keys: &[Key] = match count.clamp(1, LayoutKind::MAX_MANIA_KEYS) {
+        1 => &[Space],
+        2 => &[KeyF, KeyJ],
+        3 => &[KeyF, Space, KeyJ],
+        4 => &[KeyD, KeyF, KeyJ, KeyK],
+        5 => &[KeyD, KeyF, Space, KeyJ, KeyK],
+        6 => &[KeyS, KeyD, KeyF, KeyJ, KeyK, KeyL],
+        7 => &[KeyS, KeyD, KeyF, Space, KeyJ, KeyK, KeyL],
+        8 => &[KeyA, KeyS, KeyD, KeyF, KeyJ, KeyK, KeyL, Semicolon],
+        9 => &[KeyA, KeyS, KeyD, KeyF, Space, KeyJ, KeyK, KeyL, Semicolon],
+        _ => &[
+            KeyA, KeyS, KeyD, KeyF, KeyV, KeyN, KeyJ, KeyK, KeyL, Semicolon,
+        ],
+    };
+    Row::new(0.0, 0.0).keys(keys.iter().copied()).keys
+}
+
+fn unit_keys(kind: LayoutKind) -> Vec<UnitKey> {
+    match kind {
+        LayoutKind::Ansi | LayoutKind::Iso => {
+            let mut keys = function_row();
+            keys.extend(main_block(kind == LayoutKind::Iso, 0.0, MAIN_BLOCK_Y));
+            keys.extend(navigation_cluster());
+            keys.extend(numpad(NUMPAD_X, MAIN_BLOCK_Y));
+            keys
+        }
+        LayoutKind::Tkl => {
+            let mut keys = function_row();
+            keys.extend(main_block(false, 0.0, MAIN_BLOCK_Y));
+            keys.extend(navigation_cluster());
+            keys
+        }
+        LayoutKind::Sixty => main_block(false, 0.0, 0.0),
+        LayoutKind::Numpad => numpad(0.0, 0.0),
+        LayoutKind::Wasd => wasd(),
+        LayoutKind::Mania(count) => mania(count),
+    }
+}
+
+/// the keys of `kind`, labeled by [`Key::label`]
+pub fn generate(kind: LayoutKind, options: &LayoutOptions) -> Vec<KeyProperty> {
+    let LayoutOptions {
+        unit,
+        gap,
+        origin,
+        key_direction,
+    } = *options;
+    unit_keys(kind)
+        .into_iter()
+        .map(|unit_key| {
+            let position =
+                origin + egui::vec2(unit_key.x, unit_key.y) * unit + egui::Vec2::splat(gap / 2.0);
+            KeyProperty::default()
+                .with_key_bind(unit_key.key)
+                .with_key_text(unit_key.key.label().into())
+                .with_position(position)
+                .with_width((unit_key.width * unit - gap).max(1.0))
+                .with_height((unit_key.height * unit - gap).max(1.0))
+                .with_font_size(unit * 0.3)
+                .with_key_direction(key_direction)
+        })
+        .collect()
+}
+
+/// a setting with the keys of `kind`, the window fits the keys with `origin` as the margin
+pub fn generate_setting(kind: LayoutKind, options: &LayoutOptions) -> Setting {
+    let key_properties = generate(kind, options);
+    let (right, bottom) =
+        key_properties
+            .iter()
+            .fold((0.0_f32, 0.0_f32), |(right, bottom), key_property| {
+                (
+                    right.max(key_property.position.x + key_property.width),
+                    bottom.max(key_property.position.y + key_property.height),
+                )
+            });
+    Setting {
+        window_setting: WindowSetting::default()
+            .with_width((right + options.origin.x).ceil())
+            .with_height((bottom + options.origin.y).ceil()),
+        key_properties,
+        ..Setting::default_zxc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(l: &KeyProperty, r: &KeyProperty) -> bool {
+        let rect = |key_property: &KeyProperty| {
+            egui::Rect::from_min_size(
+                key_property.position,
+                egui::vec2(key_property.width, key_property.height),
+            )
+        };
+        rect(l).intersects(rect(r))
+    }
+
+    #[test]
+    fn layouts() {
+        let options = LayoutOptions::default();
+        [
+            (LayoutKind::Ansi, 104),
+            (LayoutKind::Iso, 105),
+            (LayoutKind::Tkl, 87),
+            (LayoutKind::Sixty, 61),
+            (LayoutKind::Numpad, 17),
+            (LayoutKind::Mania(7), 7),
+            (LayoutKind::Mania(100), LayoutKind::MAX_MANIA_KEYS),
+        ]
+        .into_iter()
+        .for_each(|(kind, count)| {
+            let key_properties = generate(kind, &options);
+            assert_eq!(key_properties.len(), count, "{kind:?}");
+            key_properties.iter().enumerate().for_each(|(i, l)| {
+                key_properties[i + 1..].iter().for_each(|r| {
+                    assert_ne!(l.key_bind, r.key_bind, "{kind:?}");
+                    assert!(
+                        !overlaps(l, r),
+                        "{kind:?} {:?} {:?}",
+                        l.key_bind,
+                        r.key_bind
+                    );
+                });
+            });
+        });
+    }
+
+    #[test]
+    fn mania_bindings() {
+        let options = LayoutOptions::default()
+            .with_unit(100.0)
+            .with_gap(10.0)
+            .with_origin(pos2(0.0, 200.0));
+        let key_properties = generate(LayoutKind::Mania(4), &options);
+        let keys: Vec<_> = key_properties.iter().map(|k| k.key_bind).collect();
+        assert_eq!(keys, [Key::KeyD, Key::KeyF, Key::KeyJ, Key::KeyK]);
+        assert_eq!(key_properties[1].position, pos2(105.0, 205.0));
+        assert_eq!(key_properties[1].width, 90.0);
+        assert_eq!(key_properties[1].key_text, "F");
+
+        let setting = generate_setting(LayoutKind::Mania(4), &options);
+        assert_eq!(setting.window_setting.width, 395.0);
+        assert_eq!(setting.window_setting.height, 495.0);
+    }
+}
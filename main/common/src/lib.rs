@@ -1,13 +1,24 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 
+pub mod app_daemon;
 pub mod app_kps_dashboard;
 pub mod app_main;
 pub mod app_main_vk;
 pub mod app_setting;
 
+mod control_server;
+mod device;
+mod event_server;
+mod html_export;
+mod importer;
 mod key;
 mod key_overlay_core;
+mod layout_generator;
 mod msg_hook;
+mod net_input;
+mod osc_output;
+mod report;
+mod schema;
 mod setting;
 mod ucolor32;
 mod utils;
@@ -1,45 +1,167 @@
-use sak_rs::os::windows::input::{global_listener::WinMsg, raw_input};
+use std::sync::{
+    Arc,
+    atomic::{self, AtomicBool},
+};
+
+use sak_rs::os::windows::input::global_listener::WinMsg;
 use windows::Win32::{
-    Foundation::HWND,
-    UI::{Input::KeyboardAndMouse::VK_SHIFT, WindowsAndMessaging::WM_INPUT},
+    Foundation::{HANDLE, HWND},
+    UI::{
+        Input::{
+            GetRawInputData, HRAWINPUT,
+            KeyboardAndMouse::{VIRTUAL_KEY, VK_SHIFT},
+            MOUSE_MOVE_ABSOLUTE, RAWINPUT, RAWINPUTDEVICE, RAWINPUTHEADER, RAWKEYBOARD, RAWMOUSE,
+            RID_INPUT, RIDEV_DEVNOTIFY, RIDEV_INPUTSINK, RIDEV_NOLEGACY, RIM_TYPEKEYBOARD,
+            RIM_TYPEMOUSE, RegisterRawInputDevices,
+        },
+        WindowsAndMessaging::{
+            GIDC_REMOVAL, RI_KEY_BREAK, RI_KEY_E0, RI_MOUSE_BUTTON_4_DOWN, RI_MOUSE_BUTTON_4_UP,
+            RI_MOUSE_BUTTON_5_DOWN, RI_MOUSE_BUTTON_5_UP, RI_MOUSE_HWHEEL,
+            RI_MOUSE_LEFT_BUTTON_DOWN, RI_MOUSE_LEFT_BUTTON_UP, RI_MOUSE_MIDDLE_BUTTON_DOWN,
+            RI_MOUSE_MIDDLE_BUTTON_UP, RI_MOUSE_RIGHT_BUTTON_DOWN, RI_MOUSE_RIGHT_BUTTON_UP,
+            RI_MOUSE_WHEEL, WM_INPUT, WM_INPUT_DEVICE_CHANGE,
+        },
+    },
 };
 
 use sak_rs::sync::mpmc::queue::BoundedSender as MpscSender;
 
-use crate::{key::Key, key_overlay_core::key_message::KeyMessage};
+use crate::{
+    device::{self, DeviceId},
+    key::Key,
+    key_overlay_core::key_message::{InputMessage, KeyMessage, MotionMessage},
+    net_input::KeyForwarder,
+};
 
 pub struct HookShared {
     pub request_redraw: Box<dyn FnMut() + Send>,
+    /// forward raw mouse movement as [`InputMessage::Motion`], read for every movement
+    /// so a reloaded setting can turn it on or off
+    pub send_motion: Arc<AtomicBool>,
+    /// also forward key messages to another machine
+    pub key_forwarder: Option<KeyForwarder>,
 }
 
-pub fn create_register_raw_input_hook(no_mouse: bool) -> impl FnOnce(&HWND) {
+/// `no_legacy` stops the legacy keyboard messages, a window taking text input still needs them
+pub fn create_register_raw_input_hook(no_mouse: bool, no_legacy: bool) -> impl FnOnce(&HWND) {
     move |&hwnd| {
-        use sak_rs::os::windows::input::raw_input::device;
-        device::register(
-            device::DeviceType::Keyboard,
-            device::OptionType::inputsink_with_no_legacy(hwnd),
-        );
-        if no_mouse {
-            return;
+        // generic desktop page, keyboard and mouse usages
+        const USAGE_PAGE: u16 = 0x01;
+        const USAGE_KEYBOARD: u16 = 0x06;
+        const USAGE_MOUSE: u16 = 0x02;
+        // `RIDEV_DEVNOTIFY` sends `WM_INPUT_DEVICE_CHANGE` so removed devices can be forgotten
+        let keyboard = RAWINPUTDEVICE {
+            usUsagePage: USAGE_PAGE,
+            usUsage: USAGE_KEYBOARD,
+            dwFlags: if no_legacy {
+                RIDEV_INPUTSINK | RIDEV_NOLEGACY | RIDEV_DEVNOTIFY
+            } else {
+                RIDEV_INPUTSINK | RIDEV_DEVNOTIFY
+            },
+            hwndTarget: hwnd,
+        };
+        let mouse = RAWINPUTDEVICE {
+            usUsage: USAGE_MOUSE,
+            dwFlags: RIDEV_INPUTSINK | RIDEV_DEVNOTIFY,
+            ..keyboard
+        };
+        let devices = if no_mouse {
+            &[keyboard][..]
+        } else {
+            &[keyboard, mouse][..]
+        };
+        // Safety: `devices` are valid for the window
+        let registered =
+            unsafe { RegisterRawInputDevices(devices, size_of::<RAWINPUTDEVICE>() as u32) };
+        registered.expect("Failed to register raw input devices");
+    }
+}
+
+/// splits wheel deltas into notches, high resolution wheels report a fraction of a notch
+#[derive(Debug, Default)]
+struct WheelAccumulator {
+    /// `[vertical, horizontal]`
+    deltas: [i32; 2],
+}
+
+impl WheelAccumulator {
+    /// `WHEEL_DELTA`
+    const NOTCH_DELTA: i32 = 120;
+
+    /// returns signed notch count
+    fn accumulate(&mut self, axis: usize, delta: i16) -> i32 {
+        let accumulated = &mut self.deltas[axis];
+        let delta = delta as i32;
+        if accumulated.signum() * delta.signum() < 0 {
+            *accumulated = 0;
         }
-        device::register(
-            device::DeviceType::Mouse,
-            device::OptionType::inputsink(hwnd),
-        );
+        *accumulated += delta;
+        let notches = *accumulated / Self::NOTCH_DELTA;
+        *accumulated %= Self::NOTCH_DELTA;
+        notches
     }
 }
 
+/// maps raw input device handles to [`DeviceId`]s, a name is only queried once per handle
+#[derive(Debug, Default)]
+struct DeviceRegistry {
+    map: ahash::HashMap<usize, DeviceId>,
+}
+
+impl DeviceRegistry {
+    fn device_id(&mut self, device: HANDLE) -> DeviceId {
+        if device.is_invalid() {
+            return DeviceId::UNKNOWN;
+        }
+        *self.map.entry(device.0 as usize).or_insert_with(|| {
+            device::device_name(device).map_or(DeviceId::UNKNOWN, |name| DeviceId::from_name(&name))
+        })
+    }
+
+    /// handles are reused by later devices, a removed one must be queried again
+    fn remove(&mut self, device: HANDLE) {
+        self.map.remove(&(device.0 as usize));
+    }
+}
+
+/// reads the whole `RAWINPUT` of a `WM_INPUT` once, keyboard and mouse input has a fixed size
+fn read_raw_input(msg: &WinMsg) -> Option<RAWINPUT> {
+    let mut raw_input = RAWINPUT::default();
+    let mut size = size_of::<RAWINPUT>() as u32;
+    // Safety: `raw_input` is `size` bytes large
+    let copied = unsafe {
+        GetRawInputData(
+            HRAWINPUT(msg.msg.lParam.0 as _),
+            RID_INPUT,
+            Some((&mut raw_input as *mut RAWINPUT).cast()),
+            &mut size,
+            size_of::<RAWINPUTHEADER>() as u32,
+        )
+    };
+    (copied != u32::MAX).then_some(raw_input)
+}
+
 pub fn create_msg_hook(
-    msg_sender: MpscSender<KeyMessage>,
+    msg_sender: MpscSender<InputMessage>,
     mut hook_shared: HookShared,
 ) -> impl FnMut(&WinMsg) -> bool {
-    let mut reader = raw_input::RawInputBufReader::new();
+    let mut wheel_accumulator = WheelAccumulator::default();
+    let mut device_registry = DeviceRegistry::default();
     move |msg| {
+        if msg.msg.message == WM_INPUT_DEVICE_CHANGE {
+            if msg.msg.wParam.0 as u32 == GIDC_REMOVAL {
+                device_registry.remove(HANDLE(msg.msg.lParam.0 as _));
+            }
+            return true;
+        }
         if msg.msg.message == WM_INPUT {
             handle_raw_input(
                 msg,
                 &msg_sender,
-                &mut reader,
+                &mut wheel_accumulator,
+                &mut device_registry,
+                hook_shared.send_motion.load(atomic::Ordering::Relaxed),
+                hook_shared.key_forwarder.as_ref(),
                 &mut hook_shared.request_redraw,
             );
             return true;
@@ -51,66 +173,124 @@ pub fn create_msg_hook(
 #[inline(always)]
 fn handle_raw_input(
     msg: &WinMsg,
-    msg_sender: &MpscSender<KeyMessage>,
-    reader: &mut raw_input::RawInputBufReader,
+    msg_sender: &MpscSender<InputMessage>,
+    wheel_accumulator: &mut WheelAccumulator,
+    device_registry: &mut DeviceRegistry,
+    send_motion: bool,
+    key_forwarder: Option<&KeyForwarder>,
     request_redraw: &mut dyn FnMut(),
 ) {
-    let raw_input = reader.read_from_msg(&msg.msg).expect("unreachable");
-    match raw_input {
-        raw_input::RawData::Keyboard(keyboard) => {
-            let virtual_key = keyboard.virtual_key();
+    let send_key = |key_message: KeyMessage| {
+        #[cfg(debug_assertions)]
+        println!("{key_message:?}");
+        key_forwarder.map(|key_forwarder| key_forwarder.forward(key_message));
+        let oldest = msg_sender.force_send(key_message.into());
+        oldest.map(|o| eprintln!("queue is full! oldest: {o:?}"));
+    };
+    let Some(raw_input) = read_raw_input(msg) else {
+        return;
+    };
+    let device = device_registry.device_id(raw_input.header.hDevice);
+    match raw_input.header.dwType {
+        dw_type if dw_type == RIM_TYPEKEYBOARD.0 => {
+            // Safety: the type says the data is a keyboard
+            let keyboard: RAWKEYBOARD = unsafe { raw_input.data.keyboard };
+            let virtual_key = VIRTUAL_KEY(keyboard.VKey);
+            let make_code = keyboard.MakeCode;
+            let has_e0 = keyboard.Flags as u32 & RI_KEY_E0 != 0;
             let is_extend = if virtual_key == VK_SHIFT {
-                keyboard.make_code() == 0x0036
+                make_code == 0x0036
             } else {
-                keyboard.has_e0()
+                has_e0
             };
-            let key = if keyboard.make_code() == 0x0037 && is_extend {
-                Key::PrintScreen
-            } else {
-                Key::from_virtual_key(virtual_key, is_extend)
+            let key = match Key::from_virtual_key(virtual_key, is_extend) {
+                _ if make_code == 0x0037 && is_extend => Key::PrintScreen,
+                // `VK_KANA` and `VK_HANGUL` are the same, only the Japanese key has this scan code
+                Key::Kana if make_code != 0x0070 => Key::Hangul,
+                key => key,
+            };
+            // `Pause` is reported as an `0xE1` prefixed `LeftControl` scan code,
+            // the keys without a scan code keep the virtual key
+            let physical_key = match Key::from_scan_code(make_code, has_e0) {
+                _ if key == Key::Pause => key,
+                Key::Unknown => key,
+                physical_key => physical_key,
             };
-            if key == Key::Unknown {
+            if key == Key::Unknown && physical_key == Key::Unknown {
                 #[cfg(debug_assertions)]
                 println!(
                     "unkown: mc = {:#x?}, vk = {:?}, is_ext = {:?}",
-                    keyboard.make_code(),
-                    virtual_key,
-                    is_extend
+                    make_code, virtual_key, is_extend
                 );
                 return;
             }
-            let is_pressed = keyboard.key_is_down();
-            let key_message = KeyMessage::new(key, is_pressed, msg.instant);
-            #[cfg(debug_assertions)]
-            println!("{key_message:?}");
-            let oldest = msg_sender.force_send(key_message);
+            let is_pressed = keyboard.Flags as u32 & RI_KEY_BREAK == 0;
+            let key_message = KeyMessage::new(key, is_pressed, msg.instant)
+                .with_physical_key(physical_key)
+                .with_device(device);
+            send_key(key_message);
             request_redraw();
-            oldest.map(|o| eprintln!("queue is full! oldest: {o:?}"));
         }
-        raw_input::RawData::Mouse(mouse) => {
+        dw_type if dw_type == RIM_TYPEMOUSE.0 => {
+            // Safety: the type says the data is a mouse
+            let mouse: RAWMOUSE = unsafe { raw_input.data.mouse };
+            // Safety: the button flags and data are always valid
+            let buttons = unsafe { mouse.Anonymous.Anonymous };
+            let button_flags = buttons.usButtonFlags as u32;
             [
-                (mouse.is_left_button_down(), Key::MouseLeft, true),
-                (mouse.is_right_button_down(), Key::MouseRight, true),
-                (mouse.is_middle_button_down(), Key::MouseMiddle, true),
-                (mouse.is_ext1_button_down(), Key::MouseX1, true),
-                (mouse.is_ext2_button_down(), Key::MouseX2, true),
-                (mouse.is_left_button_up(), Key::MouseLeft, false),
-                (mouse.is_right_button_up(), Key::MouseRight, false),
-                (mouse.is_middle_button_up(), Key::MouseMiddle, false),
-                (mouse.is_ext1_button_up(), Key::MouseX1, false),
-                (mouse.is_ext2_button_up(), Key::MouseX2, false),
+                (RI_MOUSE_LEFT_BUTTON_DOWN, Key::MouseLeft, true),
+                (RI_MOUSE_RIGHT_BUTTON_DOWN, Key::MouseRight, true),
+                (RI_MOUSE_MIDDLE_BUTTON_DOWN, Key::MouseMiddle, true),
+                (RI_MOUSE_BUTTON_4_DOWN, Key::MouseX1, true),
+                (RI_MOUSE_BUTTON_5_DOWN, Key::MouseX2, true),
+                (RI_MOUSE_LEFT_BUTTON_UP, Key::MouseLeft, false),
+                (RI_MOUSE_RIGHT_BUTTON_UP, Key::MouseRight, false),
+                (RI_MOUSE_MIDDLE_BUTTON_UP, Key::MouseMiddle, false),
+                (RI_MOUSE_BUTTON_4_UP, Key::MouseX1, false),
+                (RI_MOUSE_BUTTON_5_UP, Key::MouseX2, false),
             ]
             .into_iter()
-            .filter(|(cond, ..)| *cond)
+            .filter(|(flag, ..)| button_flags & flag != 0)
             .for_each(|(_, key, is_pressed)| {
-                let key_message = KeyMessage::new(key, is_pressed, msg.instant);
-                #[cfg(debug_assertions)]
-                println!("{key_message:?}");
-                let oldest = msg_sender.force_send(key_message);
+                send_key(KeyMessage::new(key, is_pressed, msg.instant).with_device(device));
+                request_redraw();
+            });
+
+            // `(flag, axis, positive_key, negative_key)`
+            [
+                (RI_MOUSE_WHEEL, 0, Key::WheelUp, Key::WheelDown),
+                (RI_MOUSE_HWHEEL, 1, Key::WheelRight, Key::WheelLeft),
+            ]
+            .into_iter()
+            .filter(|(flag, ..)| button_flags & flag != 0)
+            .for_each(|(_, axis, positive_key, negative_key)| {
+                let delta = buttons.usButtonData as i16;
+                let notches = wheel_accumulator.accumulate(axis, delta);
+                let key = if notches > 0 {
+                    positive_key
+                } else {
+                    negative_key
+                };
+                // a press and a release per notch, the release is delayed by `KeyHandler`
+                (0..notches.unsigned_abs())
+                    .flat_map(|_| [true, false])
+                    .for_each(|is_pressed| {
+                        send_key(KeyMessage::new(key, is_pressed, msg.instant).with_device(device));
+                    });
+                (notches != 0).then(|| request_redraw());
+            });
+
+            // absolute positions come from tablets and remote desktops, they carry no motion
+            let delta = [mouse.lLastX, mouse.lLastY];
+            let is_motion =
+                send_motion && mouse.usFlags.0 & MOUSE_MOVE_ABSOLUTE.0 == 0 && delta != [0, 0];
+            is_motion.then(|| {
+                let motion_message = MotionMessage::new(delta, msg.instant);
+                let oldest = msg_sender.force_send(motion_message.into());
                 request_redraw();
                 oldest.map(|o| eprintln!("queue is full! oldest: {o:?}"));
             });
         }
-        _ => unreachable!("unexpected raw input"),
+        _ => {}
     };
 }
@@ -0,0 +1,757 @@
+//! key messages over the network, e.g. the game runs on one machine and OBS on another
+//!
+//! the receiver listens on [`NetInputSetting::address`] and the sender sends to it,
+//! every packet is a line of json, one packet per datagram over udp,
+//! an udp receiver only takes packets from the sender that said [`Packet::Hello`] first
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Write},
+    net::{
+        IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket,
+    },
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use sak_rs::sync::mpmc::queue::BoundedSender as MpscSender;
+
+use crate::{
+    device::DeviceId,
+    key::Key,
+    key_overlay_core::key_message::{InputMessage, KeyMessage},
+    report,
+    setting::{NetInputSetting, NetMode, NetProtocol},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+/// an udp receiver lets another sender take over after its sender is silent for this long
+const PEER_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_PACKET_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct RemoteKeyMessage {
+    key: Key,
+    physical_key: Key,
+    is_pressed: bool,
+    depth: Option<f32>,
+    device: u64,
+    /// microseconds on the sender's clock
+    sender_us: u64,
+}
+
+impl RemoteKeyMessage {
+    fn new(key_message: &KeyMessage, sender_us: u64) -> Self {
+        Self {
+            key: key_message.key,
+            physical_key: key_message.physical_key,
+            is_pressed: key_message.is_pressed,
+            depth: key_message.depth,
+            device: key_message.device.0,
+            sender_us,
+        }
+    }
+
+    fn to_key_message(self, instant: Instant) -> KeyMessage {
+        KeyMessage {
+            key: self.key,
+            physical_key: self.physical_key,
+            is_pressed: self.is_pressed,
+            depth: self.depth,
+            device: DeviceId(self.device),
+            instant,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Packet {
+    Key(RemoteKeyMessage),
+    /// receiver to sender
+    Ping {
+        receiver_us: u64,
+    },
+    /// sender to receiver, answers a [`Packet::Ping`] right away
+    Pong {
+        receiver_us: u64,
+        sender_us: u64,
+    },
+    /// sender to receiver when a link starts and again while no ping comes,
+    /// an udp receiver locks onto the first sender saying it
+    Hello,
+}
+
+/// microseconds since the clock was created
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    base: Instant,
+}
+
+impl Clock {
+    fn new() -> Self {
+        Self {
+            base: Instant::now(),
+        }
+    }
+
+    fn micros(&self, instant: Instant) -> u64 {
+        instant.saturating_duration_since(self.base).as_micros() as u64
+    }
+
+    fn now(&self) -> u64 {
+        self.micros(Instant::now())
+    }
+
+    fn instant(&self, micros: u64) -> Instant {
+        self.base + Duration::from_micros(micros)
+    }
+}
+
+/// the sender's clock minus the local clock, NTP-like,
+/// the sample with the shortest round trip is the most accurate one
+#[derive(Debug, Default)]
+struct ClockOffset {
+    /// `(round_trip_us, offset_us)`, newest last
+    samples: VecDeque<(u64, i64)>,
+    offset_us: Option<i64>,
+    /// from the first message until the first sample, only keeps the later ones in order
+    guess_us: Option<i64>,
+}
+
+impl ClockOffset {
+    const SAMPLE_COUNT: usize = 8;
+
+    fn add_sample(&mut self, ping_us: u64, sender_us: u64, pong_us: u64) {
+        let round_trip_us = pong_us.saturating_sub(ping_us);
+        let offset_us = sender_us as i64 - (ping_us + round_trip_us / 2) as i64;
+        self.samples.push_back((round_trip_us, offset_us));
+        (self.samples.len() > Self::SAMPLE_COUNT).then(|| self.samples.pop_front());
+        self.offset_us = self
+            .samples
+            .iter()
+            .min_by_key(|(round_trip_us, _)| *round_trip_us)
+            .map(|(_, offset_us)| *offset_us);
+    }
+
+    /// local time of a sender's timestamp, once sampled never later than when it was received
+    ///
+    /// until the first sample the first message is taken as received right away,
+    /// the later ones keep their intervals to it even if they arrive together
+    fn local_us(&mut self, sender_us: u64, receive_us: u64) -> u64 {
+        match self.offset_us {
+            Some(offset_us) => (sender_us as i64 - offset_us).clamp(0, receive_us as i64) as u64,
+            None => {
+                let guess_us = *self
+                    .guess_us
+                    .get_or_insert(sender_us as i64 - receive_us as i64);
+                (sender_us as i64 - guess_us).max(0) as u64
+            }
+        }
+    }
+
+    #[inline]
+    fn is_synced(&self) -> bool {
+        self.offset_us.is_some()
+    }
+}
+
+enum Link {
+    /// `peer` is where packets go and the only address packets are taken from,
+    /// the receiver learns it from a [`Packet::Hello`]
+    Udp {
+        socket: UdpSocket,
+        peer: Option<SocketAddr>,
+        /// the peer may change to whoever says hello after it is silent for [`PEER_TIMEOUT`]
+        learns_peer: bool,
+        last_heard: Instant,
+    },
+    Tcp {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+        /// a line may be cut by the read timeout
+        line: Vec<u8>,
+    },
+}
+
+impl Link {
+    /// the receiver has no `peer` until a sender says hello, the sender's `peer` never changes
+    fn udp(socket: UdpSocket, peer: Option<SocketAddr>) -> io::Result<Self> {
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        Ok(Self::Udp {
+            socket,
+            peer,
+            learns_peer: peer.is_none(),
+            last_heard: Instant::now(),
+        })
+    }
+
+    fn tcp(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        Ok(Self::Tcp {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            line: vec![],
+        })
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Self::Udp { socket, peer, .. } => Self::udp(socket.try_clone()?, *peer),
+            Self::Tcp { writer, .. } => Self::tcp(writer.try_clone()?),
+        }
+    }
+
+    /// an udp receiver has nowhere to send until the sender's first packet
+    fn has_peer(&self) -> bool {
+        match self {
+            Self::Udp { peer, .. } => peer.is_some(),
+            Self::Tcp { .. } => true,
+        }
+    }
+
+    fn send(&mut self, packet: &Packet) -> io::Result<()> {
+        let mut buf = serde_json::to_vec(packet).map_err(io::Error::other)?;
+        match self {
+            Self::Udp { socket, peer, .. } => {
+                peer.map(|peer| socket.send_to(&buf, peer)).transpose()?;
+                Ok(())
+            }
+            Self::Tcp { writer, .. } => {
+                buf.push(b'\n');
+                writer.write_all(&buf)
+            }
+        }
+    }
+
+    /// `None` on timeout, on a malformed packet or on a packet from another udp sender
+    fn recv(&mut self) -> io::Result<Option<Packet>> {
+        let is_timeout = |err: &io::Error| {
+            matches!(
+                err.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            )
+        };
+        match self {
+            Self::Udp {
+                socket,
+                peer,
+                learns_peer,
+                last_heard,
+            } => {
+                let mut buf = [0; MAX_PACKET_SIZE];
+                match socket.recv_from(&mut buf) {
+                    Ok((len, addr)) => {
+                        let packet = serde_json::from_slice(&buf[..len]).ok();
+                        let is_free = peer.is_none() || last_heard.elapsed() > PEER_TIMEOUT;
+                        if *learns_peer && is_free && packet == Some(Packet::Hello) {
+                            *peer = Some(addr);
+                        }
+                        if *peer != Some(addr) {
+                            return Ok(None);
+                        }
+                        *last_heard = Instant::now();
+                        Ok(packet)
+                    }
+                    // windows reports an earlier datagram sent to a closed port as a reset
+                    Err(err)
+                        if is_timeout(&err) || err.kind() == io::ErrorKind::ConnectionReset =>
+                    {
+                        Ok(None)
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+            Self::Tcp { reader, line, .. } => match reader.read_until(b'\n', line) {
+                Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => {
+                    let packet = serde_json::from_slice(line).ok();
+                    line.clear();
+                    Ok(packet)
+                }
+                Err(err) if is_timeout(&err) => Ok(None),
+                Err(err) => Err(err),
+            },
+        }
+    }
+
+    fn shutdown(&self) {
+        if let Self::Tcp { writer, .. } = self {
+            let _ = writer.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+/// forwards local key messages to the sender thread, see [`NetInput::forwarder`]
+#[derive(Debug, Clone)]
+pub struct KeyForwarder {
+    sender: mpsc::Sender<KeyMessage>,
+}
+
+impl KeyForwarder {
+    #[inline]
+    pub fn forward(&self, key_message: KeyMessage) {
+        let _ = self.sender.send(key_message);
+    }
+}
+
+/// a running receiver or sender, stopped on drop
+pub struct NetInput {
+    running: Arc<AtomicBool>,
+    /// the receiver has measured the sender's clock, see [`Self::is_synced`]
+    synced: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    forwarder: Option<KeyForwarder>,
+    local_addr: Option<SocketAddr>,
+}
+
+impl NetInput {
+    /// `None` if [`NetMode::Off`], received key messages are sent to `msg_sender`
+    pub fn start(
+        net_input_setting: &NetInputSetting,
+        msg_sender: MpscSender<InputMessage>,
+        request_redraw: Box<dyn FnMut() + Send>,
+    ) -> io::Result<Option<Self>> {
+        let NetInputSetting {
+            mode,
+            protocol,
+            address,
+        } = net_input_setting;
+        if *mode == NetMode::Off {
+            return Ok(None);
+        }
+        let addr = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, address.clone()))?;
+        let running = Arc::new(AtomicBool::new(true));
+        let synced = Arc::new(AtomicBool::new(false));
+        let (thread, forwarder, local_addr) = match mode {
+            NetMode::Off => unreachable!(),
+            NetMode::Receive => {
+                let (thread, local_addr) = Self::spawn_receiver(
+                    *protocol,
+                    addr,
+                    msg_sender,
+                    request_redraw,
+                    &running,
+                    &synced,
+                )?;
+                (thread, None, Some(local_addr))
+            }
+            NetMode::Send => {
+                let (sender, key_receiver) = mpsc::channel();
+                let thread = Self::spawn_sender(*protocol, addr, key_receiver, &running)?;
+                (thread, Some(KeyForwarder { sender }), None)
+            }
+        };
+        Ok(Some(Self {
+            running,
+            synced,
+            thread: Some(thread),
+            forwarder,
+            local_addr,
+        }))
+    }
+
+    /// like [`Self::start`], a failure is reported by [`report::warning`]
+    pub fn start_or_warn(
+        net_input_setting: &NetInputSetting,
+        msg_sender: MpscSender<InputMessage>,
+        request_redraw: Box<dyn FnMut() + Send>,
+    ) -> Option<Self> {
+        Self::start(net_input_setting, msg_sender, request_redraw).unwrap_or_else(|err| {
+            report::warning(format!("网络输入启动失败: {err}"));
+            None
+        })
+    }
+
+    /// `Some` when sending, hand it to [`msg_hook::HookShared`]
+    ///
+    /// [`msg_hook::HookShared`]: crate::msg_hook::HookShared
+    pub fn forwarder(&self) -> Option<KeyForwarder> {
+        self.forwarder.clone()
+    }
+
+    /// the address actually listened on when receiving
+    #[allow(dead_code)]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// whether the receiver has a clock offset from a ping of the current sender,
+    /// the latency of the messages before it is unknown
+    #[allow(dead_code)]
+    pub fn is_synced(&self) -> bool {
+        self.synced.load(Ordering::Relaxed)
+    }
+
+    fn spawn_receiver(
+        protocol: NetProtocol,
+        addr: SocketAddr,
+        msg_sender: MpscSender<InputMessage>,
+        mut request_redraw: Box<dyn FnMut() + Send>,
+        running: &Arc<AtomicBool>,
+        synced: &Arc<AtomicBool>,
+    ) -> io::Result<(JoinHandle<()>, SocketAddr)> {
+        let running = running.clone();
+        let synced = synced.clone();
+        match protocol {
+            NetProtocol::Udp => {
+                let socket = UdpSocket::bind(addr)?;
+                let local_addr = socket.local_addr()?;
+                let link = Link::udp(socket, None)?;
+                let thread = std::thread::spawn(move || {
+                    receive(link, &msg_sender, &mut request_redraw, &running, &synced)
+                        .unwrap_or_else(|err| report::warning(format!("网络输入已停止: {err}")));
+                });
+                Ok((thread, local_addr))
+            }
+            NetProtocol::Tcp => {
+                let listener = TcpListener::bind(addr)?;
+                listener.set_nonblocking(true)?;
+                let local_addr = listener.local_addr()?;
+                let thread = std::thread::spawn(move || {
+                    // a failure is reported once until a sender gets through
+                    let mut failed = false;
+                    while running.load(Ordering::Relaxed) {
+                        let link = listener.accept().and_then(|(stream, _)| {
+                            stream.set_nonblocking(false)?;
+                            Link::tcp(stream)
+                        });
+                        match link {
+                            Ok(link) => {
+                                failed = false;
+                                receive(link, &msg_sender, &mut request_redraw, &running, &synced)
+                                    .unwrap_or_else(|err| {
+                                        report::warning(format!("发送方已断开: {err}"))
+                                    })
+                            }
+                            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                                std::thread::sleep(POLL_INTERVAL)
+                            }
+                            Err(err) => {
+                                if !failed {
+                                    report::warning(format!("无法接受发送方的连接: {err}"));
+                                }
+                                failed = true;
+                                std::thread::sleep(POLL_INTERVAL)
+                            }
+                        }
+                    }
+                });
+                Ok((thread, local_addr))
+            }
+        }
+    }
+
+    fn spawn_sender(
+        protocol: NetProtocol,
+        addr: SocketAddr,
+        key_receiver: mpsc::Receiver<KeyMessage>,
+        running: &Arc<AtomicBool>,
+    ) -> io::Result<JoinHandle<()>> {
+        let running = running.clone();
+        let clock = Clock::new();
+        let udp_socket = match protocol {
+            NetProtocol::Udp => {
+                let unspecified: IpAddr = if addr.is_ipv4() {
+                    Ipv4Addr::UNSPECIFIED.into()
+                } else {
+                    Ipv6Addr::UNSPECIFIED.into()
+                };
+                Some(UdpSocket::bind((unspecified, 0))?)
+            }
+            NetProtocol::Tcp => None,
+        };
+        let thread = std::thread::spawn(move || {
+            // a failure is reported once until the receiver is reached again
+            let mut failed = false;
+            while running.load(Ordering::Relaxed) {
+                let link = match &udp_socket {
+                    Some(socket) => socket
+                        .try_clone()
+                        .and_then(|socket| Link::udp(socket, Some(addr))),
+                    None => connect(addr, &running).and_then(Link::tcp),
+                };
+                // whatever was pressed while disconnected is outdated
+                key_receiver.try_iter().for_each(drop);
+                match link {
+                    Ok(link) => {
+                        failed = false;
+                        send(link, &key_receiver, &clock, &running).unwrap_or_else(|err| {
+                            failed = true;
+                            report::warning(format!("与接收方的连接已断开: {err}"))
+                        })
+                    }
+                    Err(_) if !running.load(Ordering::Relaxed) => (),
+                    Err(err) => {
+                        if !failed {
+                            report::warning(format!("无法连接到 {addr}: {err}"));
+                        }
+                        failed = true;
+                        sleep_while_running(RECONNECT_INTERVAL, &running);
+                    }
+                }
+            }
+        });
+        Ok(thread)
+    }
+}
+
+impl Drop for NetInput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        // the forwarder of the hook may outlive `self`, the thread stops on its own,
+        // within a poll interval as it never blocks longer
+        self.thread.take().map(JoinHandle::join);
+    }
+}
+
+/// connects on a thread of its own so a stop is noticed within a poll interval,
+/// an attempt left behind finishes on its own
+fn connect(addr: SocketAddr, running: &AtomicBool) -> io::Result<TcpStream> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(TcpStream::connect_timeout(&addr, RECONNECT_INTERVAL));
+    });
+    loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(stream) => return stream,
+            Err(mpsc::RecvTimeoutError::Timeout) if running.load(Ordering::Relaxed) => (),
+            Err(_) => return Err(io::ErrorKind::Interrupted.into()),
+        }
+    }
+}
+
+fn sleep_while_running(duration: Duration, running: &AtomicBool) {
+    let dead_line = Instant::now() + duration;
+    while running.load(Ordering::Relaxed) && Instant::now() < dead_line {
+        std::thread::sleep(POLL_INTERVAL.min(dead_line.saturating_duration_since(Instant::now())));
+    }
+}
+
+fn receive(
+    mut link: Link,
+    msg_sender: &MpscSender<InputMessage>,
+    request_redraw: &mut dyn FnMut(),
+    running: &AtomicBool,
+    synced: &AtomicBool,
+) -> io::Result<()> {
+    let clock = Clock::new();
+    let mut clock_offset = ClockOffset::default();
+    synced.store(false, Ordering::Relaxed);
+    let mut next_ping = Instant::now();
+    while running.load(Ordering::Relaxed) {
+        let instant_now = Instant::now();
+        // the first ping goes out as soon as there is a peer
+        if next_ping <= instant_now && link.has_peer() {
+            link.send(&Packet::Ping {
+                receiver_us: clock.micros(instant_now),
+            })?;
+            next_ping = instant_now + PING_INTERVAL;
+        }
+        match link.recv()? {
+            Some(Packet::Key(remote_key_message)) => {
+                let local_us = clock_offset.local_us(remote_key_message.sender_us, clock.now());
+                let key_message = remote_key_message.to_key_message(clock.instant(local_us));
+                let oldest = msg_sender.force_send(key_message.into());
+                request_redraw();
+                oldest.map(|o| eprintln!("queue is full! oldest: {o:?}"));
+            }
+            Some(Packet::Pong {
+                receiver_us,
+                sender_us,
+            }) => {
+                clock_offset.add_sample(receiver_us, sender_us, clock.now());
+                synced.store(clock_offset.is_synced(), Ordering::Relaxed);
+            }
+            // a new sender, or the same one after it lost the pings, has another clock
+            Some(Packet::Hello) => {
+                clock_offset = ClockOffset::default();
+                synced.store(false, Ordering::Relaxed);
+            }
+            Some(Packet::Ping { .. }) | None => (),
+        }
+    }
+    Ok(())
+}
+
+/// forwards key messages while answering pings on another thread, returns on disconnection
+fn send(
+    mut link: Link,
+    key_receiver: &mpsc::Receiver<KeyMessage>,
+    clock: &Clock,
+    running: &AtomicBool,
+) -> io::Result<()> {
+    link.send(&Packet::Hello)?;
+    let mut pong_link = link.try_clone()?;
+    let connected = AtomicBool::new(true);
+    // when the last ping came, or the last hello went out
+    let last_ping_us = AtomicU64::new(clock.now());
+    let is_connected = || running.load(Ordering::Relaxed) && connected.load(Ordering::Relaxed);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            while is_connected() {
+                let pong = match pong_link.recv() {
+                    Ok(Some(Packet::Ping { receiver_us })) => {
+                        last_ping_us.store(clock.now(), Ordering::Relaxed);
+                        Packet::Pong {
+                            receiver_us,
+                            sender_us: clock.now(),
+                        }
+                    }
+                    Ok(_) => continue,
+                    Err(_) => break,
+                };
+                if pong_link.send(&pong).is_err() {
+                    break;
+                }
+            }
+            connected.store(false, Ordering::Relaxed);
+        });
+
+        let result = (|| {
+            while is_connected() {
+                match key_receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(key_message) => {
+                        let sender_us = clock.micros(key_message.instant);
+                        link.send(&Packet::Key(RemoteKeyMessage::new(&key_message, sender_us)))?;
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => (),
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+                // e.g. the udp receiver restarted and waits for a hello, or took another sender
+                let silent_us = clock
+                    .now()
+                    .saturating_sub(last_ping_us.load(Ordering::Relaxed));
+                if silent_us > 2 * PING_INTERVAL.as_micros() as u64 {
+                    link.send(&Packet::Hello)?;
+                    last_ping_us.store(clock.now(), Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        })();
+        connected.store(false, Ordering::Relaxed);
+        link.shutdown();
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use sak_rs::sync::mpmc;
+
+    use super::*;
+
+    #[test]
+    fn clock_offset() {
+        let mut clock_offset = ClockOffset::default();
+        // no sample yet, the first message is taken as received right away
+        assert_eq!(clock_offset.local_us(10_000, 1_000), 1_000);
+        // a burst keeps its intervals
+        assert_eq!(clock_offset.local_us(10_020, 1_001), 1_020);
+        // the sample with the 20us round trip wins, the sender is 5000us ahead
+        clock_offset.add_sample(1_000, 6_080, 1_100);
+        clock_offset.add_sample(2_000, 7_010, 2_020);
+        clock_offset.add_sample(3_000, 8_150, 3_200);
+        assert_eq!(clock_offset.offset_us, Some(5_000));
+        assert_eq!(clock_offset.local_us(9_000, 4_500), 4_000);
+        // never in the future
+        assert_eq!(clock_offset.local_us(9_000, 3_900), 3_900);
+    }
+
+    fn loopback(protocol: NetProtocol) {
+        let (msg_sender, msg_receiver) = mpmc::queue::bounded(64);
+        let receiver_setting = NetInputSetting::default()
+            .with_mode(NetMode::Receive)
+            .with_protocol(protocol)
+            .with_address("127.0.0.1:0");
+        let receiver = NetInput::start(&receiver_setting, msg_sender.clone(), Box::new(|| ()))
+            .expect("unreachable")
+            .expect("unreachable");
+        let receiver_addr = receiver.local_addr().expect("unreachable");
+
+        let sender_setting = NetInputSetting::default()
+            .with_mode(NetMode::Send)
+            .with_protocol(protocol)
+            .with_address(receiver_addr.to_string());
+        let sender = NetInput::start(&sender_setting, msg_sender, Box::new(|| ()))
+            .expect("unreachable")
+            .expect("unreachable");
+        let forwarder = sender.forwarder().expect("unreachable");
+
+        // the sender connects and answers the first ping, messages sent before are dropped
+        // or timed by a guess
+        let dead_line = Instant::now() + Duration::from_secs(5);
+        while !receiver.is_synced() && Instant::now() < dead_line {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(receiver.is_synced());
+        let instant = Instant::now();
+        std::thread::sleep(Duration::from_millis(30));
+        forwarder.forward(KeyMessage::new(Key::KeyZ, true, instant).with_physical_key(Key::KeyY));
+        forwarder.forward(KeyMessage::new(
+            Key::KeyZ,
+            false,
+            instant + Duration::from_millis(20),
+        ));
+
+        let mut key_messages = vec![];
+        let dead_line = Instant::now() + Duration::from_secs(5);
+        while key_messages.len() < 2 && Instant::now() < dead_line {
+            key_messages.extend(msg_receiver.try_iter().filter_map(InputMessage::key));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let [press, release] = [&key_messages[0], &key_messages[1]];
+        assert_eq!(
+            (press.key, press.physical_key, press.is_pressed),
+            (Key::KeyZ, Key::KeyY, true)
+        );
+        assert_eq!((release.key, release.is_pressed), (Key::KeyZ, false));
+        let interval = release.instant - press.instant;
+        assert!(interval.abs_diff(Duration::from_millis(20)) < Duration::from_millis(5));
+    }
+
+    #[test]
+    fn udp_peer_lock() {
+        let receiver_socket = UdpSocket::bind("127.0.0.1:0").expect("unreachable");
+        let receiver_addr = receiver_socket.local_addr().expect("unreachable");
+        let mut receiver = Link::udp(receiver_socket, None).expect("unreachable");
+        let [mut first, mut second] = [(); 2].map(|_| {
+            let socket = UdpSocket::bind("127.0.0.1:0").expect("unreachable");
+            Link::udp(socket, Some(receiver_addr)).expect("unreachable")
+        });
+        let ping = Packet::Ping { receiver_us: 1 };
+
+        // nothing is taken before a hello
+        first.send(&ping).expect("unreachable");
+        assert_eq!(receiver.recv().expect("unreachable"), None);
+        first.send(&Packet::Hello).expect("unreachable");
+        assert_eq!(receiver.recv().expect("unreachable"), Some(Packet::Hello));
+        // the first sender keeps the receiver while it talks
+        second.send(&Packet::Hello).expect("unreachable");
+        assert_eq!(receiver.recv().expect("unreachable"), None);
+        first.send(&ping).expect("unreachable");
+        assert_eq!(receiver.recv().expect("unreachable"), Some(ping));
+    }
+
+    #[test]
+    fn udp_loopback() {
+        loopback(NetProtocol::Udp);
+    }
+
+    #[test]
+    fn tcp_loopback() {
+        loopback(NetProtocol::Tcp);
+    }
+}
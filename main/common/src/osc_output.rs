@@ -0,0 +1,163 @@
+//! open sound control messages over udp, for vj software and visualizers
+//!
+//! every press and release is sent as `/key/KeyZ 1|0` followed by `/kps <value>`,
+//! both addresses come from [`OscSetting`]
+
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+};
+
+use crate::{key::Key, key_overlay_core::key_message::KeyMessage, report, setting::OscSetting};
+
+pub struct OscOutput {
+    socket: UdpSocket,
+    target: SocketAddr,
+    /// the key address of each key, indexed by the key
+    key_addresses: Vec<String>,
+    kps_address: String,
+    /// keys held down, the os repeats presses while a key is held
+    pressed_keys: ahash::HashSet<Key>,
+    buf: Vec<u8>,
+}
+
+impl OscOutput {
+    /// `None` if disabled
+    pub fn start(osc_setting: &OscSetting) -> io::Result<Option<Self>> {
+        if !osc_setting.enabled {
+            return Ok(None);
+        }
+        let target = osc_setting
+            .target
+            .to_socket_addrs()?
+            .next()
+            .ok_or(io::ErrorKind::AddrNotAvailable)?;
+        let bind_addr: SocketAddr = if target.is_ipv4() {
+            (std::net::Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(bind_addr)?;
+        // never blocks rendering, a message that does not fit is dropped
+        socket.set_nonblocking(true)?;
+        Ok(Some(Self {
+            socket,
+            target,
+            key_addresses: Key::iter()
+                .map(|key| {
+                    osc_setting
+                        .key_address
+                        .replace("{key}", &format!("{key:?}"))
+                })
+                .collect(),
+            kps_address: osc_setting.kps_address.clone(),
+            pressed_keys: Default::default(),
+            buf: Vec::with_capacity(64),
+        }))
+    }
+
+    /// like [`Self::start`], a failure is reported by [`report::warning`]
+    pub fn start_or_warn(osc_setting: &OscSetting) -> Option<Self> {
+        Self::start(osc_setting).unwrap_or_else(|err| {
+            report::warning(format!("OSC输出启动失败: {err}"));
+            None
+        })
+    }
+
+    /// `kps` after the message is counted, repeated presses are skipped
+    pub fn send_key(&mut self, key_message: &KeyMessage, kps: usize) {
+        let Self {
+            socket,
+            target,
+            key_addresses,
+            kps_address,
+            pressed_keys,
+            buf,
+        } = self;
+        let KeyMessage {
+            key, is_pressed, ..
+        } = *key_message;
+        let changed = if is_pressed {
+            pressed_keys.insert(key)
+        } else {
+            pressed_keys.remove(&key)
+        };
+        if !changed {
+            return;
+        }
+        let mut send = |address: &str, value: i32| {
+            if address.is_empty() {
+                return;
+            }
+            buf.clear();
+            encode_message(buf, address, value);
+            let _ = socket.send_to(buf, *target);
+        };
+        send(&key_addresses[key as usize], is_pressed as i32);
+        send(kps_address, kps as i32);
+    }
+}
+
+/// an osc message with a single int32 argument
+fn encode_message(buf: &mut Vec<u8>, address: &str, value: i32) {
+    encode_string(buf, address);
+    encode_string(buf, ",i");
+    buf.extend(value.to_be_bytes());
+}
+
+/// null terminated and padded to 4 bytes
+fn encode_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    let padding = 4 - s.len() % 4;
+    buf.extend(std::iter::repeat_n(0, padding));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn message_encoding() {
+        let mut buf = vec![];
+        encode_message(&mut buf, "/kps", 7);
+        assert_eq!(buf, b"/kps\0\0\0\0,i\0\0\0\0\0\x07");
+    }
+
+    #[test]
+    fn udp_loopback() {
+        let listener = UdpSocket::bind("127.0.0.1:0").expect("unreachable");
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .expect("unreachable");
+        let osc_setting = OscSetting::default()
+            .with_enabled(true)
+            .with_target(listener.local_addr().expect("unreachable").to_string());
+        let mut osc_output = OscOutput::start(&osc_setting)
+            .expect("unreachable")
+            .expect("unreachable");
+
+        let instant = Instant::now();
+        // the repeated press is skipped
+        [(true, 1), (true, 1), (false, 1)]
+            .into_iter()
+            .for_each(|(is_pressed, kps)| {
+                osc_output.send_key(&KeyMessage::new(Key::KeyZ, is_pressed, instant), kps);
+            });
+
+        let recv = || {
+            let mut buf = [0; 64];
+            let len = listener.recv(&mut buf).expect("unreachable");
+            buf[..len].to_vec()
+        };
+        let mut expected = vec![];
+        [("/key/KeyZ", 1), ("/kps", 1), ("/key/KeyZ", 0), ("/kps", 1)]
+            .into_iter()
+            .for_each(|(address, value)| {
+                expected.clear();
+                encode_message(&mut expected, address, value);
+                assert_eq!(recv(), expected);
+            });
+    }
+}
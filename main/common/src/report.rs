@@ -0,0 +1,51 @@
+//! warnings for the user, shown in a message dialog, or logged in headless mode
+
+use std::{
+    io::Write,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+
+use crate::message_dialog;
+
+static LOG: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+
+/// switches to logging, e.g. to stderr or a file, only the first call takes effect
+pub fn log_to(writer: Box<dyn Write + Send>) {
+    let _ = LOG.set(Mutex::new(writer));
+}
+
+#[inline]
+pub fn is_headless() -> bool {
+    LOG.get().is_some()
+}
+
+/// the dialog is shown on a thread of its own, worker threads call this too and must not wait on it
+pub fn warning(message: impl Into<String>) {
+    let message = message.into();
+    if is_headless() {
+        log("warning", &message);
+    } else {
+        std::thread::spawn(move || message_dialog::warning(message).show());
+    }
+}
+
+/// only logged in headless mode
+pub fn info(message: impl AsRef<str>) {
+    log("info", message.as_ref());
+}
+
+fn log(level: &str, message: &str) {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let mut log = log.lock();
+    let _ = writeln!(log, "[{secs:.3}] [{level}] {message}");
+    let _ = log.flush();
+}
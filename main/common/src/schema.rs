@@ -0,0 +1,100 @@
+//! json schema of setting files, published as `setting.schema.json` for editors
+//!
+//! generated from the types, the `schema_up_to_date` test regenerates the published file
+
+use std::borrow::Cow;
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde_json::{Value, json};
+
+use crate::{
+    key_overlay_core::key_property::{KeyProperty, KeyStyle},
+    setting::Setting,
+    ucolor32::UColor32,
+};
+
+/// the serialized form of [`egui::Pos2`]
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+pub struct Pos2 {
+    x: f32,
+    y: f32,
+}
+
+/// a color of a key or a style, which can also be the name of a palette color
+#[allow(dead_code)]
+pub struct PaletteColor;
+
+impl JsonSchema for PaletteColor {
+    fn schema_name() -> Cow<'static, str> {
+        "PaletteColor".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "a color, or the name of a color in the palette",
+            "anyOf": [generator.subschema_for::<UColor32>(), { "type": "string" }]
+        })
+    }
+}
+
+pub fn setting_schema() -> Value {
+    let mut schema = schemars::schema_for!(Setting).to_value();
+    // a key that references a style can leave out the fields the style sets
+    let style_fields: Vec<Value> = schema
+        .pointer(&format!("/$defs/{}/properties", KeyStyle::schema_name()))
+        .and_then(Value::as_object)
+        .map(|properties| properties.keys().cloned().map(Value::from).collect())
+        .unwrap_or_default();
+    schema
+        .pointer_mut(&format!("/$defs/{}/required", KeyProperty::schema_name()))
+        .and_then(Value::as_array_mut)
+        .map(|required| required.retain(|field| !style_fields.contains(field)));
+    // only saved files carry it, see `Setting::VERSION`
+    if let Some(properties) = schema
+        .pointer_mut("/properties")
+        .and_then(Value::as_object_mut)
+    {
+        let version = json!({ "description": "the layout of the file", "type": "integer" });
+        properties.insert("version".into(), version);
+    }
+    schema
+}
+
+pub fn export_schema_to_file(path: impl AsRef<std::path::Path>) -> Result<(), &'static str> {
+    let schema = serde_json::to_string_pretty(&setting_schema()).expect("unreachable");
+    std::fs::write(path, schema + "\n").map_err(|_| "无法写入文件")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// only compares, `UPDATE_SCHEMA=1` regenerates the file instead
+    #[test]
+    fn schema_up_to_date() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../setting.schema.json");
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            export_schema_to_file(&path).expect("unreachable");
+            return;
+        }
+        let schema = serde_json::to_string_pretty(&setting_schema()).expect("unreachable") + "\n";
+        assert!(
+            std::fs::read_to_string(&path).ok().as_deref() == Some(schema.as_str()),
+            "setting.schema.json is out of date, \
+            run `UPDATE_SCHEMA=1 cargo test schema_up_to_date` to regenerate it"
+        );
+    }
+
+    #[test]
+    fn key_properties_can_follow_styles() {
+        let schema = setting_schema();
+        let key_property = &schema["$defs"]["KeyProperty"];
+        assert!(key_property["properties"]["key_bind"].is_object());
+        let required = key_property["required"].as_array().expect("unreachable");
+        assert!(required.contains(&"key_bind".into()));
+        assert!(!required.contains(&"width".into()));
+        let keys = serde_json::to_string(&schema["$defs"]["Key"]).expect("unreachable");
+        assert!(keys.contains("\"KeyZ\""));
+    }
+}
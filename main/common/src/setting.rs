@@ -1,19 +1,37 @@
 use egui::Pos2;
 use serde::{Deserialize, Serialize};
 
-use crate::{key::Key, message_dialog, ucolor32::UColor32};
+use crate::{key::Key, report, ucolor32::UColor32};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct WindowSetting {
     pub width: f32,
     pub height: f32,
     pub enable_vsync: bool,
+    /// multiplies the window size and the pixel lengths of the layout
+    #[serde(default = "WindowSetting::default_scale")]
+    pub scale: f32,
+    /// the layout re-flows to the new size, see [`KeyProperty::anchor`]
+    ///
+    /// [`KeyProperty::anchor`]: crate::key_overlay_core::key_property::KeyProperty::anchor
+    #[serde(default)]
+    pub resizable: bool,
 }
 
 impl WindowSetting {
     pub const DEFAULT_WIDTH: f32 = 600.;
     pub const DEFAULT_HEIGHT: f32 = 600.;
     pub const DEFAULT_ENABLE_VSYNC: bool = true;
+    pub const DEFAULT_SCALE: f32 = 1.0;
+
+    fn default_scale() -> f32 {
+        Self::DEFAULT_SCALE
+    }
+
+    /// the scaled window size
+    pub fn size(&self) -> egui::Vec2 {
+        egui::vec2(self.width, self.height) * self.scale
+    }
 }
 
 impl Default for WindowSetting {
@@ -22,6 +40,8 @@ impl Default for WindowSetting {
             width: Self::DEFAULT_WIDTH,
             height: Self::DEFAULT_HEIGHT,
             enable_vsync: Self::DEFAULT_ENABLE_VSYNC,
+            scale: Self::DEFAULT_SCALE,
+            resizable: false,
         }
     }
 }
@@ -41,23 +61,414 @@ impl WindowSetting {
         self.enable_vsync = enable_vsync;
         self
     }
+
+    #[cfg(test)]
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct InputSetting {
+    /// how long a wheel notch is held, see [`Key::is_wheel`]
+    pub wheel_press_duration_ms: f32,
+    /// a press this soon after a release is chatter, `0.0` disables debouncing,
+    /// see also [`KeyProperty::debounce_ms`]
+    ///
+    /// [`KeyProperty::debounce_ms`]: crate::key_overlay_core::key_property::KeyProperty::debounce_ms
+    pub debounce_ms: f32,
+    pub network: NetInputSetting,
+}
+
+impl InputSetting {
+    pub const DEFAULT_WHEEL_PRESS_DURATION_MS: f32 = 50.0;
+}
+
+impl Default for InputSetting {
+    fn default() -> Self {
+        Self {
+            wheel_press_duration_ms: Self::DEFAULT_WHEEL_PRESS_DURATION_MS,
+            debounce_ms: 0.0,
+            network: Default::default(),
+        }
+    }
+}
+
+impl InputSetting {
+    #[cfg(test)]
+    pub fn with_wheel_press_duration_ms(mut self, wheel_press_duration_ms: f32) -> Self {
+        self.wheel_press_duration_ms = wheel_press_duration_ms;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_debounce_ms(mut self, debounce_ms: f32) -> Self {
+        self.debounce_ms = debounce_ms;
+        self
+    }
+
+    /// rounded to microseconds, `50.0` is exactly 50ms instead of the nearest `f32` seconds
+    pub fn wheel_press_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_micros(
+            (self.wheel_press_duration_ms.max(0.0) * 1_000.0).round() as u64
+        )
+    }
+}
+
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema,
+)]
+pub enum NetMode {
+    #[default]
+    Off,
+    /// show the key messages sent by another machine, local input is still shown
+    Receive,
+    /// forward local input to another machine
+    Send,
+}
+
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema,
+)]
+pub enum NetProtocol {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct NetInputSetting {
+    pub mode: NetMode,
+    pub protocol: NetProtocol,
+    /// the local address to listen on when receiving, the receiver's address when sending
+    pub address: String,
+}
+
+impl NetInputSetting {
+    /// only this machine, receiving from another one is opted into with e.g. `0.0.0.0:23333`
+    pub const DEFAULT_ADDRESS: &str = "127.0.0.1:23333";
+}
+
+impl Default for NetInputSetting {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            protocol: Default::default(),
+            address: Self::DEFAULT_ADDRESS.into(),
+        }
+    }
+}
+
+impl NetInputSetting {
+    #[cfg(test)]
+    pub fn with_mode(mut self, mode: NetMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_protocol(mut self, protocol: NetProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+}
+
+/// where the input is published besides the overlay window, and how the overlay is controlled
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct OutputSetting {
+    pub event_server: EventServerSetting,
+    pub control_server: ControlServerSetting,
+    pub osc: OscSetting,
+}
+
+/// a websocket server broadcasting key events and snapshots as json, for browser sources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct EventServerSetting {
+    pub enabled: bool,
+    pub address: String,
+    /// how often counters and kps are broadcast
+    pub snapshot_interval_ms: f32,
+}
+
+impl EventServerSetting {
+    pub const DEFAULT_ADDRESS: &str = "127.0.0.1:23334";
+    pub const DEFAULT_SNAPSHOT_INTERVAL_MS: f32 = 500.0;
+}
+
+impl Default for EventServerSetting {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: Self::DEFAULT_ADDRESS.into(),
+            snapshot_interval_ms: Self::DEFAULT_SNAPSHOT_INTERVAL_MS,
+        }
+    }
+}
+
+impl EventServerSetting {
+    #[cfg(test)]
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+
+    pub fn snapshot_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f32(self.snapshot_interval_ms.max(1.0) / 1_000.0)
+    }
+}
+
+/// a local http endpoint taking json-rpc commands, e.g. from stream deck style tools
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct ControlServerSetting {
+    pub enabled: bool,
+    pub address: String,
+}
+
+impl ControlServerSetting {
+    pub const DEFAULT_ADDRESS: &str = "127.0.0.1:23335";
+}
+
+impl Default for ControlServerSetting {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: Self::DEFAULT_ADDRESS.into(),
+        }
+    }
+}
+
+impl ControlServerSetting {
+    #[cfg(test)]
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+}
+
+/// open sound control messages over udp for each press and release
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct OscSetting {
+    pub enabled: bool,
+    pub target: String,
+    /// `{key}` is replaced by the name of the key, e.g. `/key/KeyZ`, empty to send nothing
+    pub key_address: String,
+    /// empty to send nothing
+    pub kps_address: String,
+}
+
+impl OscSetting {
+    pub const DEFAULT_TARGET: &str = "127.0.0.1:9000";
+    pub const DEFAULT_KEY_ADDRESS: &str = "/key/{key}";
+    pub const DEFAULT_KPS_ADDRESS: &str = "/kps";
+}
+
+impl Default for OscSetting {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: Self::DEFAULT_TARGET.into(),
+            key_address: Self::DEFAULT_KEY_ADDRESS.into(),
+            kps_address: Self::DEFAULT_KPS_ADDRESS.into(),
+        }
+    }
+}
+
+impl OscSetting {
+    #[cfg(test)]
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = target.into();
+        self
+    }
+}
+
+/// named colors that key colors can reference, swapping it recolors every referencing key
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Palette {
+    pub name: String,
+    pub colors: std::collections::BTreeMap<String, UColor32>,
+}
+
+impl Palette {
+    pub const TEXT: &str = "text";
+    pub const FRAME: &str = "frame";
+    pub const ACCENT: &str = "accent";
+
+    /// the palettes offered by the editor, all of them define the same names
+    pub fn builtin() -> Vec<Self> {
+        [
+            (
+                "经典",
+                UColor32::WHITE,
+                UColor32::WHITE,
+                UColor32::WHITE.with_a(128),
+            ),
+            (
+                "霓虹",
+                UColor32::from_rgb(0, 255, 255),
+                UColor32::from_rgb(255, 0, 255),
+                UColor32::from_rgb(255, 0, 255).with_a(128),
+            ),
+            (
+                "樱花",
+                UColor32::from_rgb(255, 240, 245),
+                UColor32::from_rgb(255, 105, 180),
+                UColor32::from_rgb(255, 182, 193).with_a(160),
+            ),
+            (
+                "海洋",
+                UColor32::from_rgb(224, 255, 255),
+                UColor32::from_rgb(0, 119, 190),
+                UColor32::from_rgb(0, 180, 216).with_a(128),
+            ),
+            (
+                "墨",
+                UColor32::DARK_GRAY,
+                UColor32::BLACK,
+                UColor32::BLACK.with_a(128),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, text, frame, accent)| {
+            Self::default()
+                .with_name(name)
+                .with_color(Self::TEXT, text)
+                .with_color(Self::FRAME, frame)
+                .with_color(Self::ACCENT, accent)
+        })
+        .collect()
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_color(mut self, name: impl Into<String>, color: UColor32) -> Self {
+        self.colors.insert(name.into(), color);
+        self
+    }
+}
+
+/// the format of a setting file, chosen by the extension of its path
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SettingFormat {
+    #[default]
+    Json,
+    Toml,
+    Ron,
+}
+
+impl SettingFormat {
+    pub const EXTENSIONS: [&str; 3] = ["json", "toml", "ron"];
+
+    /// json for an unknown extension
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Self {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("toml") => Self::Toml,
+            Some("ron") => Self::Ron,
+            _ => Self::Json,
+        }
+    }
+
+    /// `Some(..)` and the parentheses of newtypes can be left out in ron,
+    /// e.g. `width: 100.0` and `frame_color: (255, 0, 0, 255)`
+    const RON_EXTENSIONS: ron::extensions::Extensions = ron::extensions::Extensions::IMPLICIT_SOME
+        .union(ron::extensions::Extensions::UNWRAP_NEWTYPES);
+
+    pub fn parse<T: serde::de::DeserializeOwned>(self, text: &str) -> Result<T, String> {
+        match self {
+            Self::Json => serde_json::from_str(text).map_err(|err| format!("格式错误: {err}")),
+            Self::Toml => toml::from_str(text).map_err(|err| format!("TOML格式错误: {err}")),
+            Self::Ron => ron::Options::default()
+                .with_default_extension(Self::RON_EXTENSIONS)
+                .from_str(text)
+                .map_err(|err| format!("RON格式错误: {err}")),
+        }
+    }
+
+    pub fn print(self, value: &impl Serialize) -> Result<String, String> {
+        match self {
+            Self::Json => serde_json::to_string_pretty(value)
+                .map_err(|err| format!("serde_json::to_string_pretty错误: {err}")),
+            Self::Toml => toml::to_string_pretty(value)
+                .map_err(|err| format!("toml::to_string_pretty错误: {err}")),
+            Self::Ron => {
+                let config = ron::ser::PrettyConfig::default().extensions(Self::RON_EXTENSIONS);
+                ron::ser::to_string_pretty(value, config)
+                    .map_err(|err| format!("ron::ser::to_string_pretty错误: {err}"))
+            }
+        }
+    }
 }
 
 pub use v2::Setting;
 
 pub mod v2 {
-    use std::io::Seek;
+    use std::collections::BTreeMap;
 
-    use crate::key_overlay_core::key_property::{KeyCounterProperty, KeyDirection, KeyProperty};
+    use crate::key_overlay_core::key_property::{
+        Anchor, BarShape, BindMode, DepthEffect, KeyCounterProperty, KeyDirection,
+        KeyGroupProperty, KeyProperty, KeyStyle, MouseMotionProperty, PaletteRefs, Toggle,
+    };
 
     use super::*;
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
     pub struct Setting {
         pub window_setting: WindowSetting,
         pub font_name: Box<str>,
         pub background_color: UColor32,
+        #[serde(default)]
+        pub palette: Palette,
+        /// resolved, fields a style leaves out are already taken from its base style
+        #[serde(default)]
+        pub styles: Vec<KeyStyle>,
         pub key_properties: Vec<KeyProperty>,
+        #[serde(default)]
+        pub key_groups: Vec<KeyGroupProperty>,
+        #[serde(default)]
+        pub mouse_motions: Vec<MouseMotionProperty>,
+        #[serde(default)]
+        pub input_setting: InputSetting,
+        #[serde(default)]
+        pub output_setting: OutputSetting,
     }
 
     impl Default for Setting {
@@ -71,63 +482,167 @@ pub mod v2 {
             self.window_setting == other.window_setting
                 && self.font_name == other.font_name
                 && self.background_color == other.background_color
+                && self.palette == other.palette
+                && self.styles == other.styles
                 && self.key_properties.len() == other.key_properties.len()
                 && self
                     .key_properties
                     .iter()
                     .zip(other.key_properties.iter())
                     .all(|(l, r)| l == r)
+                && self.key_groups == other.key_groups
+                && self.mouse_motions == other.mouse_motions
+                && self.input_setting == other.input_setting
+                && self.output_setting == other.output_setting
         }
     }
 
     impl Setting {
-        pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, &'static str> {
-            let file = std::fs::File::options()
-                .read(true)
-                .open(path)
-                .map_err(|_| "无法读取文件")?;
-            let mut reader = std::io::BufReader::new(&file);
-            let setting = if let Ok(setting) = serde_json::de::from_reader(&mut reader) {
-                setting
-            } else {
-                reader
-                    .seek(std::io::SeekFrom::Start(0))
-                    .map_err(|_| "无法读取文件")?;
-                let setting_v1: v1::Setting =
-                    serde_json::de::from_reader(reader).map_err(|_| "格式错误")?;
-                Self {
-                    window_setting: setting_v1.window_setting,
-                    font_name: setting_v1.font_name,
-                    background_color: UColor32::TRANSPARENT,
-                    key_properties: setting_v1.key_properties,
+        /// the format is chosen by the extension, see [`SettingFormat::from_path`]
+        pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+            let path = path.as_ref();
+            let text =
+                std::fs::read_to_string(path).map_err(|err| format!("无法读取文件: {err}"))?;
+            Self::parse(&text, SettingFormat::from_path(path))
+        }
+
+        /// styles and palette colors are resolved after the setting is read,
+        /// json without a version is migrated first, see [`Self::migrate`]
+        pub fn parse(text: &str, format: SettingFormat) -> Result<Self, String> {
+            let SettingVersion { version } = format.parse(text)?;
+            match (version, format) {
+                (None, SettingFormat::Json) => Self::migrate(format.parse(text)?),
+                _ => format.parse::<SettingFile>(text)?.resolve(),
+            }
+        }
+
+        /// like [`Self::parse`] for json
+        #[cfg(test)]
+        pub fn from_value(value: serde_json::Value) -> Result<Self, String> {
+            Self::parse(&value.to_string(), SettingFormat::Json)
+        }
+
+        /// only json was saved before there was a version, version 1 had a background color
+        /// of three flags, and version 2 saved toggles as `[enabled, value]`
+        fn migrate(mut value: serde_json::Value) -> Result<Self, String> {
+            Self::migrate_toggles(&mut value);
+            match SettingFile::deserialize(&value) {
+                Ok(setting) => setting.resolve(),
+                Err(err) => v1::Setting::deserialize(&value)
+                    .map(Self::from_v1)
+                    .map_err(|_| format!("格式错误: {err}")),
+            }
+        }
+
+        /// `[enabled, value]` to `{ "enabled": enabled, "value": value }`
+        fn migrate_toggles(value: &mut serde_json::Value) {
+            const TOGGLES: [&str; 3] = ["max_distance", "fade_length", "key_counter"];
+            for field in ["styles", "key_properties"] {
+                let Some(properties) = value.get_mut(field).and_then(|v| v.as_array_mut()) else {
+                    continue;
+                };
+                for property in properties.iter_mut() {
+                    for name in TOGGLES {
+                        let Some(toggle) = property.get_mut(name) else {
+                            continue;
+                        };
+                        if let Some([enabled, value]) = toggle.as_array().map(Vec::as_slice) {
+                            *toggle = serde_json::json!({ "enabled": enabled, "value": value });
+                        }
+                    }
                 }
-            };
+            }
+        }
 
-            Ok(setting)
+        fn from_v1(setting_v1: v1::Setting) -> Self {
+            Self {
+                window_setting: setting_v1.window_setting,
+                font_name: setting_v1.font_name,
+                background_color: UColor32::TRANSPARENT,
+                palette: Default::default(),
+                styles: vec![],
+                key_properties: setting_v1.key_properties,
+                key_groups: vec![],
+                mouse_motions: vec![],
+                input_setting: Default::default(),
+                output_setting: Default::default(),
+            }
+        }
+
+        /// fields equal to the referenced style are left out, so the keys keep following it,
+        /// colors referencing the palette are written as their names
+        #[cfg(test)]
+        pub fn to_value(&self) -> serde_json::Value {
+            serde_json::to_value(SettingFile::new(self)).expect("unreachable")
         }
 
+        /// the format is chosen by the extension, see [`SettingFormat::from_path`]
         pub fn to_file(&self, path: impl AsRef<std::path::Path>) -> Result<(), String> {
-            let file = std::fs::File::create(path).map_err(|_| "无法写入文件")?;
-            let writer = std::io::BufWriter::new(&file);
-            serde_json::ser::to_writer_pretty(writer, &self)
-                .map_err(|err| format!("serde_json::ser::to_writer_pretty错误: {err}"))?;
+            let path = path.as_ref();
+            let text = self.print(SettingFormat::from_path(path))?;
+            std::fs::write(path, text).map_err(|_| "无法写入文件")?;
             Ok(())
         }
 
+        /// not through `to_value`, which widens `f32`s, e.g. `0.1` to `0.10000000149011612`
+        pub fn print(&self, format: SettingFormat) -> Result<String, String> {
+            format.print(&SettingFile::new(self))
+        }
+
+        /// recolors every key and style that references the palette
+        pub fn set_palette(&mut self, palette: Palette) {
+            self.palette = palette;
+            self.key_properties
+                .iter_mut()
+                .for_each(|key_property| key_property.apply_palette(&self.palette));
+            self.styles
+                .iter_mut()
+                .for_each(|style| style.apply_palette(&self.palette));
+        }
+
+        /// the setting for a window of `window_size`, with every key at its top-left corner
+        /// in pixels and every length multiplied by the scale, see [`KeyProperty::lay_out`]
+        pub fn laid_out(&self, window_size: egui::Vec2) -> Self {
+            let mut setting = self.clone();
+            let scale = setting.window_setting.scale;
+            setting.window_setting.width = window_size.x;
+            setting.window_setting.height = window_size.y;
+            setting.window_setting.scale = 1.0;
+            setting
+                .key_properties
+                .iter_mut()
+                .for_each(|key_property| key_property.lay_out(window_size, scale));
+            setting
+                .key_groups
+                .iter_mut()
+                .for_each(|key_group| key_group.scale(scale));
+            setting
+                .mouse_motions
+                .iter_mut()
+                .for_each(|mouse_motion| mouse_motion.scale(scale));
+            setting
+        }
+
+        fn from_bundled(json: &str) -> Self {
+            Self::parse(json, SettingFormat::Json).expect("load default setting failed")
+        }
+
         pub fn load_from_local_setting() -> Self {
-            let path = crate::key_overlay_setting_path();
-            Self::from_file(&path).unwrap_or_else(|_| {
+            Self::load_or_create(crate::key_overlay_setting_path())
+        }
+
+        /// a default setting is written to `path` if it can not be read
+        pub fn load_or_create(path: impl AsRef<std::path::Path>) -> Self {
+            let path = path.as_ref();
+            Self::from_file(path).unwrap_or_else(|err| {
                 let setting = Self::default_zxc();
                 let _ = setting
                     .to_file(path)
-                    .map(|_| {
-                        message_dialog::warning("读取配置文件失败，已生成默认配置").show();
-                    })
+                    .map(|_| report::warning(format!("读取配置文件失败，已生成默认配置\n{err}")))
                     .map_err(|_| {
-                        message_dialog::warning(
-                            "读取配置文件失败，且无法生成配置文件，使用默认配置",
-                        )
-                        .show();
+                        report::warning(format!(
+                            "读取配置文件失败，且无法生成配置文件，使用默认配置\n{err}"
+                        ))
                     });
                 setting
             })
@@ -140,7 +655,13 @@ pub mod v2 {
                 window_setting: Default::default(),
                 font_name: Self::DEFAULT_FONT_NAME.into(),
                 background_color: UColor32::TRANSPARENT,
+                palette: Default::default(),
+                styles: vec![],
                 key_properties: Self::property_zxc(),
+                key_groups: vec![],
+                mouse_motions: vec![],
+                input_setting: Default::default(),
+                output_setting: Default::default(),
             }
         }
 
@@ -149,7 +670,13 @@ pub mod v2 {
                 window_setting: Default::default(),
                 font_name: Self::DEFAULT_FONT_NAME.into(),
                 background_color: UColor32::TRANSPARENT,
+                palette: Default::default(),
+                styles: vec![],
                 key_properties: Self::property_mouse(),
+                key_groups: vec![],
+                mouse_motions: vec![],
+                input_setting: Default::default(),
+                output_setting: Default::default(),
             }
         }
 
@@ -158,38 +685,45 @@ pub mod v2 {
                 window_setting: Default::default(),
                 font_name: Self::DEFAULT_FONT_NAME.into(),
                 background_color: UColor32::TRANSPARENT,
+                palette: Default::default(),
+                styles: vec![],
                 key_properties: Self::property_four_directions(),
+                key_groups: vec![],
+                mouse_motions: vec![],
+                input_setting: Default::default(),
+                output_setting: Default::default(),
             }
         }
 
         pub fn default_4k() -> Self {
-            serde_json::de::from_str(include_str!("../../default_settings/4K.json"))
-                .expect("load default setting failed")
+            Self::from_bundled(include_str!("../../default_settings/4K.json"))
         }
 
         pub fn default_7k() -> Self {
-            serde_json::de::from_str(include_str!("../../default_settings/7K.json"))
-                .expect("load default setting failed")
+            Self::from_bundled(include_str!("../../default_settings/7K.json"))
         }
 
         pub fn default_26k() -> Self {
-            serde_json::de::from_str(include_str!("../../default_settings/26K.json"))
-                .expect("load default setting failed")
+            Self::from_bundled(include_str!("../../default_settings/26K.json"))
         }
 
         pub fn default_hello_world() -> Self {
-            serde_json::de::from_str(include_str!("../../default_settings/HelloWorld.json"))
-                .expect("load default setting failed")
+            Self::from_bundled(include_str!("../../default_settings/HelloWorld.json"))
         }
 
         pub fn default_single_counter() -> Self {
-            serde_json::de::from_str(include_str!("../../default_settings/单个计数器.json"))
-                .expect("load default setting failed")
+            Self::from_bundled(include_str!("../../default_settings/单个计数器.json"))
         }
     }
 
     impl Setting {
         pub const DEFAULT_FONT_NAME: &str = "Microsoft Yahei";
+        /// the layout of saved settings, see [`Self::migrate`] for the older ones
+        pub const VERSION: u32 = 3;
+
+        fn default_version() -> u32 {
+            Self::VERSION
+        }
 
         fn property_zxc() -> Vec<KeyProperty> {
             let width = 100.0;
@@ -332,6 +866,553 @@ pub mod v2 {
             vec![key_left, key_up, key_right, key_down]
         }
     }
+
+    /// calls `$op!($file, $style, fields...)` with the fields a [`KeyStyle`] sets
+    macro_rules! with_style_fields {
+        ($op:ident!($file:expr, $style:expr)) => {
+            $op!(
+                $file,
+                $style,
+                width,
+                height,
+                thickness,
+                font_size,
+                bar_speed,
+                max_distance,
+                text_color,
+                frame_color,
+                pressed_color,
+                key_direction,
+                bar_shape,
+                fade_length,
+                key_counter,
+                actuation_point,
+                depth_effect
+            )
+        };
+    }
+
+    /// fills the fields `$file` leaves out from the style it references
+    macro_rules! inherit_style {
+        ($file:expr, $style:expr, $($field:ident),*) => {{
+            $($file.$field = $file.$field.take().or_else(|| $style.$field.clone());)*
+        }};
+    }
+
+    /// leaves out the fields of `$file` that equal those of the style it references
+    macro_rules! strip_style {
+        ($file:expr, $style:expr, $($field:ident),*) => {{
+            $(if $file.$field == $style.$field {
+                $file.$field = None;
+            })*
+        }};
+    }
+
+    /// a color, or the name of the [`Palette`] color it follows
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    #[serde(untagged)]
+    enum ColorRef {
+        Color(UColor32),
+        Name(String),
+    }
+
+    impl ColorRef {
+        fn new(color: UColor32, palette_ref: &Option<String>) -> Self {
+            palette_ref.clone().map_or(Self::Color(color), Self::Name)
+        }
+
+        /// the name is kept in `palette_ref`
+        fn resolve(
+            self,
+            colors: &BTreeMap<String, UColor32>,
+            palette_ref: &mut Option<String>,
+        ) -> Result<UColor32, String> {
+            match self {
+                Self::Color(color) => Ok(color),
+                Self::Name(name) => {
+                    let color = *colors
+                        .get(&name)
+                        .ok_or_else(|| format!("未知调色板颜色: {name}"))?;
+                    *palette_ref = Some(name);
+                    Ok(color)
+                }
+            }
+        }
+    }
+
+    /// not derived, an untagged enum would lose the variant names of ron
+    impl<'de> Deserialize<'de> for ColorRef {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct ColorRefVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for ColorRefVisitor {
+                type Value = ColorRef;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("a color or the name of a palette color")
+                }
+
+                fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<ColorRef, E> {
+                    Ok(ColorRef::Name(name.into()))
+                }
+
+                fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                    self,
+                    seq: A,
+                ) -> Result<ColorRef, A::Error> {
+                    let deserializer = serde::de::value::SeqAccessDeserializer::new(seq);
+                    <[u8; 4]>::deserialize(deserializer).map(|rgba| ColorRef::Color(UColor32(rgba)))
+                }
+
+                fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+                    self,
+                    deserializer: D,
+                ) -> Result<ColorRef, D::Error> {
+                    UColor32::deserialize(deserializer).map(ColorRef::Color)
+                }
+            }
+
+            deserializer.deserialize_any(ColorRefVisitor)
+        }
+    }
+
+    /// [`KeyCounterProperty`] as saved
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct KeyCounterFile {
+        position: Pos2,
+        font_size: f32,
+        text_color: ColorRef,
+    }
+
+    impl KeyCounterFile {
+        fn new(key_counter: &KeyCounterProperty, palette_ref: &Option<String>) -> Self {
+            Self {
+                position: key_counter.position,
+                font_size: key_counter.font_size,
+                text_color: ColorRef::new(key_counter.text_color, palette_ref),
+            }
+        }
+
+        fn resolve(
+            key_counter: Toggle<Self>,
+            colors: &BTreeMap<String, UColor32>,
+            palette_ref: &mut Option<String>,
+        ) -> Result<Toggle<KeyCounterProperty>, String> {
+            let Toggle { enabled, value } = key_counter;
+            Ok(Toggle::new(
+                enabled,
+                KeyCounterProperty {
+                    position: value.position,
+                    font_size: value.font_size,
+                    text_color: value.text_color.resolve(colors, palette_ref)?,
+                },
+            ))
+        }
+    }
+
+    /// [`KeyStyle`] as saved, colors following the palette are written as their names
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct KeyStyleFile {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        style: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thickness: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        font_size: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bar_speed: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_distance: Option<Toggle<f32>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text_color: Option<ColorRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        frame_color: Option<ColorRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pressed_color: Option<ColorRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_direction: Option<KeyDirection>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bar_shape: Option<BarShape>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fade_length: Option<Toggle<f32>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_counter: Option<Toggle<KeyCounterFile>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        actuation_point: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        depth_effect: Option<DepthEffect>,
+    }
+
+    impl KeyStyleFile {
+        fn new(style: &KeyStyle) -> Self {
+            let refs = &style.palette_refs;
+            Self {
+                name: style.name.clone(),
+                style: style.style.clone(),
+                width: style.width,
+                height: style.height,
+                thickness: style.thickness,
+                font_size: style.font_size,
+                bar_speed: style.bar_speed,
+                max_distance: style.max_distance,
+                text_color: style
+                    .text_color
+                    .map(|color| ColorRef::new(color, &refs.text_color)),
+                frame_color: style
+                    .frame_color
+                    .map(|color| ColorRef::new(color, &refs.frame_color)),
+                pressed_color: style
+                    .pressed_color
+                    .map(|color| ColorRef::new(color, &refs.pressed_color)),
+                key_direction: style.key_direction,
+                bar_shape: style.bar_shape,
+                fade_length: style.fade_length,
+                key_counter: style.key_counter.as_ref().map(|key_counter| {
+                    Toggle::new(
+                        key_counter.enabled,
+                        KeyCounterFile::new(&key_counter.value, &refs.counter_text_color),
+                    )
+                }),
+                actuation_point: style.actuation_point,
+                depth_effect: style.depth_effect,
+            }
+        }
+
+        fn resolve(self, colors: &BTreeMap<String, UColor32>) -> Result<KeyStyle, String> {
+            let mut refs = PaletteRefs::default();
+            Ok(KeyStyle {
+                name: self.name,
+                style: self.style,
+                width: self.width,
+                height: self.height,
+                thickness: self.thickness,
+                font_size: self.font_size,
+                bar_speed: self.bar_speed,
+                max_distance: self.max_distance,
+                text_color: self
+                    .text_color
+                    .map(|color| color.resolve(colors, &mut refs.text_color))
+                    .transpose()?,
+                frame_color: self
+                    .frame_color
+                    .map(|color| color.resolve(colors, &mut refs.frame_color))
+                    .transpose()?,
+                pressed_color: self
+                    .pressed_color
+                    .map(|color| color.resolve(colors, &mut refs.pressed_color))
+                    .transpose()?,
+                key_direction: self.key_direction,
+                bar_shape: self.bar_shape,
+                fade_length: self.fade_length,
+                key_counter: self
+                    .key_counter
+                    .map(|key_counter| {
+                        KeyCounterFile::resolve(key_counter, colors, &mut refs.counter_text_color)
+                    })
+                    .transpose()?,
+                actuation_point: self.actuation_point,
+                depth_effect: self.depth_effect,
+                palette_refs: refs,
+            })
+        }
+    }
+
+    /// [`KeyProperty`] as saved, fields equal to its style are left out,
+    /// fields left out by both the key and its style are the defaults
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct KeyPropertyFile {
+        key_bind: Key,
+        #[serde(default)]
+        bind_mode: BindMode,
+        key_text: String,
+        position: Pos2,
+        #[serde(default)]
+        anchor: Anchor,
+        #[serde(default)]
+        percent_position: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thickness: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        font_size: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bar_speed: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_distance: Option<Toggle<f32>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text_color: Option<ColorRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        frame_color: Option<ColorRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pressed_color: Option<ColorRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_direction: Option<KeyDirection>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bar_shape: Option<BarShape>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fade_length: Option<Toggle<f32>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_counter: Option<Toggle<KeyCounterFile>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        actuation_point: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        depth_effect: Option<DepthEffect>,
+        #[serde(default)]
+        device_filter: Option<String>,
+        #[serde(default)]
+        debounce_ms: Toggle<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        style: Option<String>,
+    }
+
+    impl KeyPropertyFile {
+        fn new(key_property: &KeyProperty) -> Self {
+            let refs = &key_property.palette_refs;
+            Self {
+                key_bind: key_property.key_bind,
+                bind_mode: key_property.bind_mode,
+                key_text: key_property.key_text.clone(),
+                position: key_property.position,
+                anchor: key_property.anchor,
+                percent_position: key_property.percent_position,
+                width: Some(key_property.width),
+                height: Some(key_property.height),
+                thickness: Some(key_property.thickness),
+                font_size: Some(key_property.font_size),
+                bar_speed: Some(key_property.bar_speed),
+                max_distance: Some(key_property.max_distance),
+                text_color: Some(ColorRef::new(key_property.text_color, &refs.text_color)),
+                frame_color: Some(ColorRef::new(key_property.frame_color, &refs.frame_color)),
+                pressed_color: Some(ColorRef::new(
+                    key_property.pressed_color,
+                    &refs.pressed_color,
+                )),
+                key_direction: Some(key_property.key_direction),
+                bar_shape: Some(key_property.bar_shape),
+                fade_length: Some(key_property.fade_length),
+                key_counter: Some(Toggle::new(
+                    key_property.key_counter.enabled,
+                    KeyCounterFile::new(&key_property.key_counter.value, &refs.counter_text_color),
+                )),
+                actuation_point: Some(key_property.actuation_point),
+                depth_effect: Some(key_property.depth_effect),
+                device_filter: key_property.device_filter.clone(),
+                debounce_ms: key_property.debounce_ms,
+                style: key_property.style.clone(),
+            }
+        }
+
+        fn resolve(self, colors: &BTreeMap<String, UColor32>) -> Result<KeyProperty, String> {
+            let default = KeyProperty::default();
+            let mut refs = PaletteRefs::default();
+            Ok(KeyProperty {
+                key_bind: self.key_bind,
+                bind_mode: self.bind_mode,
+                key_text: self.key_text,
+                position: self.position,
+                anchor: self.anchor,
+                percent_position: self.percent_position,
+                width: self.width.unwrap_or(default.width),
+                height: self.height.unwrap_or(default.height),
+                thickness: self.thickness.unwrap_or(default.thickness),
+                font_size: self.font_size.unwrap_or(default.font_size),
+                bar_speed: self.bar_speed.unwrap_or(default.bar_speed),
+                max_distance: self.max_distance.unwrap_or(default.max_distance),
+                text_color: self
+                    .text_color
+                    .map(|color| color.resolve(colors, &mut refs.text_color))
+                    .transpose()?
+                    .unwrap_or(default.text_color),
+                frame_color: self
+                    .frame_color
+                    .map(|color| color.resolve(colors, &mut refs.frame_color))
+                    .transpose()?
+                    .unwrap_or(default.frame_color),
+                pressed_color: self
+                    .pressed_color
+                    .map(|color| color.resolve(colors, &mut refs.pressed_color))
+                    .transpose()?
+                    .unwrap_or(default.pressed_color),
+                key_direction: self.key_direction.unwrap_or(default.key_direction),
+                bar_shape: self.bar_shape.unwrap_or(default.bar_shape),
+                fade_length: self.fade_length.unwrap_or(default.fade_length),
+                key_counter: self
+                    .key_counter
+                    .map(|key_counter| {
+                        KeyCounterFile::resolve(key_counter, colors, &mut refs.counter_text_color)
+                    })
+                    .transpose()?
+                    .unwrap_or(default.key_counter),
+                actuation_point: self.actuation_point.unwrap_or(default.actuation_point),
+                depth_effect: self.depth_effect.unwrap_or(default.depth_effect),
+                device_filter: self.device_filter,
+                debounce_ms: self.debounce_ms,
+                style: self.style,
+                palette_refs: refs,
+            })
+        }
+    }
+
+    /// [`Setting`] as saved, styles and palette colors are stripped and resolved on these
+    /// typed fields, so each format reads and writes its own idioms, e.g. unquoted enums in ron,
+    /// and `f32`s keep their shortest form
+    ///
+    /// the fields a style can set are `Option`s here, a key inherits exactly those it leaves out,
+    /// which a `#[serde(default)]` on [`KeyProperty`] could not tell from a default value
+    #[derive(Debug, Serialize, Deserialize)]
+    struct SettingFile {
+        /// toml and ron were only saved with the current layout, they may leave it out
+        #[serde(default = "Setting::default_version")]
+        version: u32,
+        window_setting: WindowSetting,
+        font_name: Box<str>,
+        background_color: UColor32,
+        #[serde(default)]
+        palette: Palette,
+        #[serde(default)]
+        styles: Vec<KeyStyleFile>,
+        key_properties: Vec<KeyPropertyFile>,
+        #[serde(default)]
+        key_groups: Vec<KeyGroupProperty>,
+        #[serde(default)]
+        mouse_motions: Vec<MouseMotionProperty>,
+        #[serde(default)]
+        input_setting: InputSetting,
+        #[serde(default)]
+        output_setting: OutputSetting,
+    }
+
+    /// read before the rest of a setting to tell how to read it
+    #[derive(Deserialize)]
+    struct SettingVersion {
+        version: Option<u32>,
+    }
+
+    impl SettingFile {
+        /// a reference to a missing style is dropped
+        fn new(setting: &Setting) -> Self {
+            let full_styles: Vec<KeyStyleFile> =
+                setting.styles.iter().map(KeyStyleFile::new).collect();
+            let find_style = |name: &Option<String>| {
+                let name = name.as_deref()?;
+                full_styles.iter().find(|style| style.name == name)
+            };
+            let styles = full_styles
+                .iter()
+                .map(|style| {
+                    let mut style = style.clone();
+                    match find_style(&style.style) {
+                        Some(base) => with_style_fields!(strip_style!(style, base)),
+                        None => style.style = None,
+                    }
+                    style
+                })
+                .collect();
+            let key_properties = setting
+                .key_properties
+                .iter()
+                .map(|key_property| {
+                    let mut key_property = KeyPropertyFile::new(key_property);
+                    match find_style(&key_property.style) {
+                        Some(style) => with_style_fields!(strip_style!(key_property, style)),
+                        None => key_property.style = None,
+                    }
+                    key_property
+                })
+                .collect();
+            Self {
+                version: Setting::VERSION,
+                window_setting: setting.window_setting.clone(),
+                font_name: setting.font_name.clone(),
+                background_color: setting.background_color,
+                palette: setting.palette.clone(),
+                styles,
+                key_properties,
+                key_groups: setting.key_groups.clone(),
+                mouse_motions: setting.mouse_motions.clone(),
+                input_setting: setting.input_setting.clone(),
+                output_setting: setting.output_setting.clone(),
+            }
+        }
+
+        /// the fields each style and key leaves out are taken from the style it references,
+        /// then the palette names are replaced by the colors
+        fn resolve(self) -> Result<Setting, String> {
+            if self.version > Setting::VERSION {
+                return Err(format!("不支持的配置版本: {}", self.version));
+            }
+            let mut styles = self.styles;
+            // a base style is resolved before the styles that reference it,
+            // more passes than styles means a cycle
+            let mut resolved = vec![false; styles.len()];
+            for _ in 0..=styles.len() {
+                if resolved.iter().all(|resolved| *resolved) {
+                    break;
+                }
+                for i in 0..styles.len() {
+                    if resolved[i] {
+                        continue;
+                    }
+                    let Some(base_name) = styles[i].style.clone() else {
+                        resolved[i] = true;
+                        continue;
+                    };
+                    let base_index = styles
+                        .iter()
+                        .position(|style| style.name == base_name)
+                        .ok_or_else(|| format!("未知样式: {base_name}"))?;
+                    if resolved[base_index] && base_index != i {
+                        let base = styles[base_index].clone();
+                        with_style_fields!(inherit_style!(styles[i], base));
+                        resolved[i] = true;
+                    }
+                }
+            }
+            if !resolved.iter().all(|resolved| *resolved) {
+                return Err("样式循环引用".into());
+            }
+
+            let colors = &self.palette.colors;
+            let key_properties = self
+                .key_properties
+                .into_iter()
+                .map(|mut key_property| {
+                    if let Some(name) = &key_property.style {
+                        let style = styles
+                            .iter()
+                            .find(|style| &style.name == name)
+                            .ok_or_else(|| format!("未知样式: {name}"))?;
+                        with_style_fields!(inherit_style!(key_property, style));
+                    }
+                    key_property.resolve(colors)
+                })
+                .collect::<Result<_, _>>()?;
+            let styles = styles
+                .into_iter()
+                .map(|style| style.resolve(colors))
+                .collect::<Result<_, _>>()?;
+            Ok(Setting {
+                window_setting: self.window_setting,
+                font_name: self.font_name,
+                background_color: self.background_color,
+                palette: self.palette,
+                styles,
+                key_properties,
+                key_groups: self.key_groups,
+                mouse_motions: self.mouse_motions,
+                input_setting: self.input_setting,
+                output_setting: self.output_setting,
+            })
+        }
+    }
 }
 
 mod v1 {
@@ -354,3 +1435,341 @@ mod v1 {
         pub key_properties: Vec<KeyProperty>,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::key_overlay_core::key_property::{KeyDirection, Toggle};
+
+    use super::*;
+
+    #[test]
+    fn styles_round_trip() {
+        let setting = Setting::default_4k();
+        assert!(setting.key_properties.iter().all(|key_property| {
+            key_property.style.as_deref() == Some("按键") && key_property.width == 100.0
+        }));
+
+        let value = setting.to_value();
+        let key_property = &value["key_properties"][0];
+        assert_eq!(key_property["style"], "按键");
+        assert!(key_property.get("width").is_none());
+        assert!(key_property.get("frame_color").is_some());
+        assert_eq!(Setting::from_value(value), Ok(setting));
+    }
+
+    #[test]
+    fn style_inheritance() {
+        let mut value = Setting::default_zxc().to_value();
+        value["styles"] = json!([
+            { "name": "base", "width": 50.0, "height": 60.0 },
+            { "name": "wide", "style": "base", "width": 80.0 },
+        ]);
+        value["key_properties"][0]["style"] = json!("wide");
+        value["key_properties"][0]
+            .as_object_mut()
+            .expect("unreachable")
+            .retain(|field, _| !matches!(field.as_str(), "width" | "height"));
+        value["key_properties"][1]["style"] = json!("base");
+
+        let setting = Setting::from_value(value.clone()).expect("unreachable");
+        assert_eq!(setting.styles[1].height, Some(60.0));
+        assert_eq!(setting.key_properties[0].width, 80.0);
+        assert_eq!(setting.key_properties[0].height, 60.0);
+        // the key overrides the style
+        assert_eq!(setting.key_properties[1].width, 100.0);
+        assert_eq!(setting.key_properties[1].height, 100.0);
+        assert_eq!(setting.to_value(), value);
+
+        let mut unknown = value.clone();
+        unknown["key_properties"][2]["style"] = json!("missing");
+        assert_eq!(
+            Setting::from_value(unknown),
+            Err("未知样式: missing".into())
+        );
+        let mut cycle = value;
+        cycle["styles"][0]["style"] = json!("wide");
+        assert_eq!(Setting::from_value(cycle), Err("样式循环引用".into()));
+    }
+
+    #[test]
+    fn palette_references() {
+        let mut value = Setting::default_zxc().to_value();
+        value["palette"] = serde_json::to_value(&Palette::builtin()[0]).expect("unreachable");
+        value["key_properties"][0]["frame_color"] = json!(Palette::FRAME);
+        value["key_properties"][0]["key_counter"]["value"]["text_color"] = json!(Palette::TEXT);
+
+        let mut setting = Setting::from_value(value.clone()).expect("unreachable");
+        let key_property = &setting.key_properties[0];
+        assert_eq!(key_property.frame_color, UColor32::WHITE);
+        assert_eq!(
+            key_property.palette_refs.counter_text_color.as_deref(),
+            Some(Palette::TEXT)
+        );
+        assert_eq!(setting.to_value(), value);
+
+        let neon = Palette::builtin()[1].clone();
+        setting.set_palette(neon.clone());
+        assert_eq!(
+            setting.key_properties[0].frame_color,
+            neon.colors[Palette::FRAME]
+        );
+        // colors of their own are kept
+        assert_eq!(setting.key_properties[1].frame_color, UColor32::GREEN);
+
+        value["key_properties"][1]["text_color"] = json!("missing");
+        assert_eq!(
+            Setting::from_value(value),
+            Err("未知调色板颜色: missing".into())
+        );
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        let mut setting = Setting::default_26k();
+        setting.set_palette(Palette::builtin()[0].clone());
+        setting.key_properties[0].palette_refs.frame_color = Some(Palette::FRAME.into());
+        setting.key_properties[1].device_filter = Some("device".into());
+        setting.set_palette(Palette::builtin()[1].clone());
+        [SettingFormat::Json, SettingFormat::Toml, SettingFormat::Ron]
+            .into_iter()
+            .for_each(|format| {
+                let text = setting.print(format).expect("unreachable");
+                assert_eq!(
+                    Setting::parse(&text, format),
+                    Ok(setting.clone()),
+                    "{format:?}"
+                );
+            });
+    }
+
+    #[test]
+    fn floats_keep_shortest_form() {
+        let mut setting = Setting::default_zxc();
+        setting.key_properties[0].width = 0.1;
+        [
+            (SettingFormat::Json, "\"width\": 0.1,"),
+            (SettingFormat::Toml, "width = 0.1\n"),
+            (SettingFormat::Ron, "width: 0.1,"),
+        ]
+        .into_iter()
+        .for_each(|(format, width)| {
+            let text = setting.print(format).expect("unreachable");
+            assert!(text.contains(width), "{text}");
+            assert_eq!(Setting::parse(&text, format), Ok(setting.clone()));
+        });
+    }
+
+    #[test]
+    fn formats_read_idiomatic_text() {
+        let ron = r#"(
+            window_setting: (width: 600.0, height: 600.0, enable_vsync: true),
+            font_name: "Microsoft Yahei",
+            background_color: (0, 0, 0, 0),
+            palette: (name: "经典", colors: { "frame": (255, 255, 255, 255) }),
+            styles: [
+                (
+                    name: "按键",
+                    width: 80.0,
+                    key_direction: Angle(45.0),
+                    fade_length: (enabled: true, value: 0.1),
+                ),
+            ],
+            key_properties: [
+                (
+                    key_bind: KeyZ,
+                    key_text: "Z",
+                    position: (x: 10.0, y: 20.0),
+                    height: 100.0,
+                    thickness: 3.0,
+                    font_size: 30.0,
+                    bar_speed: 1.5,
+                    max_distance: (enabled: true, value: 120.0),
+                    text_color: (255, 0, 0, 255),
+                    frame_color: "frame",
+                    pressed_color: (255, 0, 0, 128),
+                    key_direction: Right,
+                    style: "按键",
+                ),
+            ],
+        )"#;
+        let toml = r#"
+            font_name = "Microsoft Yahei"
+            background_color = [0, 0, 0, 0]
+
+            [window_setting]
+            width = 600.0
+            height = 600.0
+            enable_vsync = true
+
+            [palette]
+            name = "经典"
+            colors = { frame = [255, 255, 255, 255] }
+
+            [[styles]]
+            name = "按键"
+            width = 80.0
+            key_direction = { Angle = 45.0 }
+            fade_length = { enabled = true, value = 0.1 }
+
+            [[key_properties]]
+            key_bind = "KeyZ"
+            key_text = "Z"
+            position = { x = 10.0, y = 20.0 }
+            height = 100.0
+            thickness = 3.0
+            font_size = 30.0
+            bar_speed = 1.5
+            max_distance = { enabled = true, value = 120.0 }
+            text_color = [255, 0, 0, 255]
+            frame_color = "frame"
+            pressed_color = [255, 0, 0, 128]
+            key_direction = "Right"
+            style = "按键"
+        "#;
+        let setting = Setting::parse(ron, SettingFormat::Ron).expect("unreachable");
+        assert_eq!(
+            Setting::parse(toml, SettingFormat::Toml),
+            Ok(setting.clone())
+        );
+
+        let key_property = &setting.key_properties[0];
+        assert_eq!(key_property.key_bind, Key::KeyZ);
+        assert_eq!(key_property.width, 80.0);
+        assert_eq!(key_property.key_direction, KeyDirection::Right);
+        assert_eq!(key_property.fade_length.get(), Some(&0.1));
+        assert_eq!(key_property.max_distance.get(), Some(&120.0));
+        assert_eq!(key_property.frame_color, UColor32::WHITE);
+        assert_eq!(
+            key_property.palette_refs.frame_color.as_deref(),
+            Some(Palette::FRAME)
+        );
+
+        let ron = setting.print(SettingFormat::Ron).expect("unreachable");
+        assert!(ron.contains("key_bind: KeyZ,"), "{ron}");
+        assert!(ron.contains("key_direction: Angle(45.0),"), "{ron}");
+        [SettingFormat::Json, SettingFormat::Toml, SettingFormat::Ron]
+            .into_iter()
+            .for_each(|format| {
+                let text = setting.print(format).expect("unreachable");
+                assert_eq!(
+                    Setting::parse(&text, format),
+                    Ok(setting.clone()),
+                    "{format:?}"
+                );
+            });
+    }
+
+    #[test]
+    fn formats_migrate_v1() {
+        let v1 = v1::Setting {
+            window_setting: WindowSetting::default()
+                .with_width(100.0)
+                .with_height(100.0),
+            font_name: Setting::DEFAULT_FONT_NAME.into(),
+            background_color: v1::BackgroundColor {
+                r: true,
+                g: false,
+                b: false,
+            },
+            key_properties: Setting::default_zxc().key_properties,
+        };
+        // only json was saved before there was a version
+        let text = SettingFormat::Json.print(&v1).expect("unreachable");
+        let setting = Setting::parse(&text, SettingFormat::Json).expect("unreachable");
+        assert_eq!(setting.window_setting.width, 100.0);
+        assert_eq!(setting.background_color, UColor32::TRANSPARENT);
+        assert_eq!(
+            setting.key_properties,
+            Setting::default_zxc().key_properties
+        );
+        assert_eq!(SettingFormat::from_path("a/b.TOML"), SettingFormat::Toml);
+        assert_eq!(SettingFormat::from_path("a/b"), SettingFormat::Json);
+    }
+
+    #[test]
+    fn toggles_migrate_from_tuples() {
+        let mut value = Setting::default_zxc().to_value();
+        value
+            .as_object_mut()
+            .expect("unreachable")
+            .remove("version");
+        value["palette"] = serde_json::to_value(&Palette::builtin()[0]).expect("unreachable");
+        let key_property = &mut value["key_properties"][0];
+        key_property["max_distance"] = json!([true, 120.0]);
+        let mut counter = key_property["key_counter"]["value"].take();
+        counter["text_color"] = json!(Palette::TEXT);
+        key_property["key_counter"] = json!([true, counter.clone()]);
+
+        let setting = Setting::from_value(value.clone()).expect("unreachable");
+        let key_property = &setting.key_properties[0];
+        assert_eq!(key_property.max_distance.get(), Some(&120.0));
+        assert!(key_property.key_counter.enabled);
+        assert_eq!(
+            key_property.palette_refs.counter_text_color.as_deref(),
+            Some(Palette::TEXT)
+        );
+
+        let migrated = &setting.to_value()["key_properties"][0];
+        assert_eq!(
+            migrated["max_distance"],
+            json!({ "enabled": true, "value": 120.0 })
+        );
+        assert_eq!(
+            migrated["key_counter"],
+            json!({ "enabled": true, "value": counter })
+        );
+    }
+
+    #[test]
+    fn baseline_setting_migrates() {
+        let text = include_str!("../test_data/4K_v2.json");
+        let setting = Setting::parse(text, SettingFormat::Json).expect("unreachable");
+        let key_property = &setting.key_properties[0];
+        assert_eq!(key_property.key_bind, Key::KeyD);
+        assert_eq!(key_property.max_distance, Toggle::new(false, 200.0));
+        assert_eq!(key_property.fade_length, Toggle::new(true, 50.0));
+        assert!(key_property.key_counter.enabled);
+
+        let mut value = setting.to_value();
+        assert_eq!(value["version"], json!(Setting::VERSION));
+        value["version"] = json!(Setting::VERSION + 1);
+        assert_eq!(
+            Setting::from_value(value),
+            Err(format!("不支持的配置版本: {}", Setting::VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn anchored_layout() {
+        use crate::key_overlay_core::key_property::{Anchor, KeyProperty};
+
+        let mut setting = Setting::default_zxc();
+        setting.window_setting = setting.window_setting.with_scale(2.0);
+        setting.key_properties[0] = KeyProperty::default()
+            .with_position(Pos2::new(-10.0, 0.0))
+            .with_width(50.0)
+            .with_height(20.0)
+            .with_anchor(Anchor::BottomRight);
+        setting.key_properties[1] = KeyProperty::default()
+            .with_position(Pos2::new(50.0, 25.0))
+            .with_width(50.0)
+            .with_height(20.0)
+            .with_anchor(Anchor::Center)
+            .with_percent_position(true);
+
+        let size = setting.window_setting.size();
+        assert_eq!(size, egui::vec2(1200.0, 1200.0));
+        let laid_out = setting.laid_out(egui::vec2(800.0, 400.0));
+        assert_eq!(laid_out.window_setting.scale, 1.0);
+        let [bottom_right, center] = [0, 1].map(|index| &laid_out.key_properties[index]);
+        assert_eq!(
+            bottom_right.position,
+            Pos2::new(800.0 - 100.0 - 20.0, 400.0 - 40.0)
+        );
+        assert_eq!(bottom_right.anchor, Anchor::TopLeft);
+        assert_eq!(center.position, Pos2::new(350.0 + 400.0, 180.0 + 100.0));
+        assert_eq!(center.width, 100.0);
+    }
+}
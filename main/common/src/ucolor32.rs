@@ -3,7 +3,9 @@
 use serde::{Deserialize, Serialize};
 
 /// unmultiplied version of [`egui::Color32`]
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema,
+)]
 pub struct UColor32(pub [u8; 4]);
 
 impl UColor32 {